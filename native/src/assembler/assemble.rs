@@ -1,11 +1,15 @@
+use super::config::AssemblerConfig;
 use super::error::*;
-use crate::component::{Instruction, RegisterName, TypeI, TypeJ, TypeR};
+use super::lint::{lint, Warning};
+use crate::component::{FpRegisterName, Instruction, RegisterName, TypeFR, TypeI, TypeJ, TypeR};
 use crate::memory::{EndianMode, Segment};
 use lazy_static::lazy_static;
 use regex::Regex;
+use snafu::ResultExt;
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
-use std::ops::RangeInclusive;
+use std::ops::{Range, RangeInclusive};
 use std::str::FromStr;
 
 /*
@@ -21,12 +25,17 @@ Note: https://phoenix.goucher.edu/~kelliher/f2009/cs220/mipsir.html
 
 lazy_static! {
     static ref RE_SEPARATOR: Regex = Regex::new(r"[\s,]+").unwrap();
+    // `a - b` is one operand, not two; `1 -2` stays two
+    static ref RE_EXPR_OP: Regex = Regex::new(r"\s*([+-])\s+").unwrap();
+    // `8 ( $t1 )` is one operand
+    static ref RE_PAREN: Regex = Regex::new(r"\s*([()])\s*").unwrap();
 }
 
 enum Token<'a> {
     Text { text: &'a str },
     Number { text: &'a str, num: i64 },
     Register { text: &'a str, reg: RegisterName },
+    FpRegister { text: &'a str, reg: FpRegisterName },
     LabelDef { text: &'a str },
 }
 
@@ -36,6 +45,7 @@ impl Token<'_> {
             Token::Text { text } => text,
             Token::Number { text, .. } => text,
             Token::Register { text, .. } => text,
+            Token::FpRegister { text, .. } => text,
             Token::LabelDef { text } => text,
         }
     }
@@ -52,14 +62,93 @@ impl Token<'_> {
     }
 
     fn as_register(&self) -> Result<RegisterName, AssemblerError> {
-        if let Token::Register { reg, .. } = self {
-            Ok(*reg)
-        } else {
-            TokenNotRegisterSnafu {
+        match self {
+            Token::Register { reg, .. } => Ok(*reg),
+            Token::FpRegister { reg, .. } => FpRegisterNotAllowedSnafu { reg: reg.num() }.fail(),
+            _ => TokenNotRegisterSnafu {
                 token: self.as_text(),
             }
-            .fail()
+            .fail(),
+        }
+    }
+
+    fn as_fp_register(&self) -> Result<FpRegisterName, AssemblerError> {
+        match self {
+            Token::FpRegister { reg, .. } => Ok(*reg),
+            Token::Register { reg, .. } => IntRegisterNotAllowedSnafu { reg: reg.num() }.fail(),
+            _ => TokenNotRegisterSnafu {
+                token: self.as_text(),
+            }
+            .fail(),
+        }
+    }
+}
+
+/// Result of [`eval_expr`].
+#[derive(Debug, Clone, Copy, Default)]
+struct Expr {
+    num: i64,
+    /// Whether a label contributed to `num`. Labels are unknown on the first pass, so
+    /// anything sized by such a value must not depend on it.
+    has_label: bool,
+}
+
+/// Evaluates an immediate expression: numbers, `.equ` constants and labels joined by `+`
+/// and `-`. It is deliberately simple; there is no precedence or grouping, terms are just
+/// summed left to right, and there is no other operator. On the first pass (`labels` is
+/// `None`) every label counts as 0.
+fn eval_expr(
+    text: &str,
+    labels: &Option<HashMap<String, u32>>,
+    constants: &HashMap<String, Expr>,
+) -> Result<Expr, AssemblerError> {
+    let mut ret = Expr::default();
+    let mut negate = false;
+    let mut rest = text;
+
+    if let Some(x) = rest.strip_prefix('-') {
+        negate = true;
+        rest = x;
+    } else if let Some(x) = rest.strip_prefix('+') {
+        rest = x;
+    }
+
+    loop {
+        let end = rest.find(['+', '-']).unwrap_or(rest.len());
+        let term = &rest[..end];
+
+        let val = if let Some(x) = try_parse_number(term) {
+            x
+        } else if let Some(x) = constants.get(term) {
+            ret.has_label |= x.has_label;
+            x.num
+        } else if term.is_empty()
+            || try_parse_reg(term).is_some()
+            || try_parse_fpreg(term).is_some()
+        {
+            return InvalidTokenSnafu { token: text }.fail();
+        } else {
+            ret.has_label = true;
+            match labels {
+                Some(labels) => *labels
+                    .get(term)
+                    .ok_or_else(|| LabelNotFoundSnafu { label: term }.build())?
+                    as i64,
+                None => 0,
+            }
+        };
+
+        ret.num = if negate {
+            ret.num.wrapping_sub(val)
+        } else {
+            ret.num.wrapping_add(val)
+        };
+
+        if end == rest.len() {
+            return Ok(ret);
         }
+        negate = rest.as_bytes()[end] == b'-';
+        rest = &rest[end + 1..];
     }
 }
 
@@ -67,12 +156,39 @@ struct LineContext<'a> {
     line: &'a str,
     mnemonic: &'a str,
     args: &'a [Token<'a>],
-    args_raw: &'a str,
     pc: u32,
+    gp: u32,
     labels: &'a Option<HashMap<String, u32>>,
+    constants: &'a HashMap<String, Expr>,
 }
 
 impl LineContext<'_> {
+    /// The offset of `addr` from `$gp`, if it fits in 16 bits. On the first pass labels
+    /// are unknown, so a label is assumed to fit; the pass after corrects that.
+    fn gp_offset(&self, addr: Expr) -> Option<u16> {
+        if addr.has_label && self.labels.is_none() {
+            return Some(0);
+        }
+
+        let offset = addr.num.wrapping_sub(self.gp as i64);
+        expect_extendable(offset, true).ok()
+    }
+
+    /// Reads an immediate operand, which may be an expression; see [`eval_expr`].
+    fn eval(&self, token: &Token) -> Result<Expr, AssemblerError> {
+        match token {
+            Token::Number { num, .. } => Ok(Expr {
+                num: *num,
+                has_label: false,
+            }),
+            Token::Text { text } => eval_expr(text, self.labels, self.constants),
+            _ => TokenNotNumberSnafu {
+                token: token.as_text(),
+            }
+            .fail(),
+        }
+    }
+
     fn resolve_label(&self, label: &Token, relative: bool) -> Result<u32, AssemblerError> {
         let next_pc = self.pc.wrapping_add(4);
 
@@ -136,26 +252,306 @@ impl LineContext<'_> {
     }
 }
 
+/// Joins the pieces of operands that may be written with spaces inside, like `a + 4` and
+/// `8 ( $sp )`, so that `split_operands` keeps each whole.
+fn normalize_operands(args: &str) -> Cow<'_, str> {
+    match RE_EXPR_OP.replace_all(args, "$1") {
+        Cow::Borrowed(x) => RE_PAREN.replace_all(x, "$1"),
+        Cow::Owned(x) => Cow::Owned(RE_PAREN.replace_all(&x, "$1").into_owned()),
+    }
+}
+
+/// Splits normalized operands on commas, spaces and tabs in any mix.
+fn split_operands(args: &str) -> impl Iterator<Item = &str> {
+    RE_SEPARATOR.split(args).filter(|x| !x.is_empty())
+}
+
 fn tokenize<'a>(args: impl Iterator<Item = &'a str>) -> Vec<Token<'a>> {
-    args.filter(|x| !x.is_empty())
-        .map(|token| {
-            if let Some(x) = try_parse_reg(token) {
-                Token::Register {
-                    text: token,
-                    reg: x,
+    args.map(|token| {
+        if let Some(x) = try_parse_reg(token) {
+            Token::Register {
+                text: token,
+                reg: x,
+            }
+        } else if let Some(x) = try_parse_fpreg(token) {
+            Token::FpRegister {
+                text: token,
+                reg: x,
+            }
+        } else if let Some(x) = try_parse_number(token) {
+            Token::Number {
+                text: token,
+                num: x,
+            }
+        } else if token.ends_with(':') {
+            Token::LabelDef { text: token }
+        } else {
+            Token::Text { text: token }
+        }
+    })
+    .collect()
+}
+
+/// Value lists are split on commas and whitespace alike, so `1,,2` would quietly read as
+/// `1 2`. Rejects empty entries, counting from 1, including leading and trailing commas.
+fn check_value_list(line: &str, values: &str) -> Result<(), AssemblerError> {
+    if values.trim().is_empty() {
+        return Ok(());
+    }
+
+    match values.split(',').position(|x| x.trim().is_empty()) {
+        Some(i) => EmptyOperandSnafu { index: i + 1, line }.fail(),
+        None => Ok(()),
+    }
+}
+
+/// Given the opening `'` of a character literal at `start`, finds its closing `'`.
+fn char_literal_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'\'' => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Parses what is between the quotes of a character literal: one ASCII character or one of
+/// the escapes `\n \t \0 \\ \'`.
+fn try_parse_char(body: &str) -> Option<i64> {
+    let mut chars = body.chars();
+    let c = match chars.next()? {
+        '\\' => match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '\'' => '\'',
+            _ => return None,
+        },
+        x => x,
+    };
+
+    if chars.next().is_some() || !c.is_ascii() {
+        return None;
+    }
+    Some(c as i64)
+}
+
+/// Replaces every character literal outside string literals with its ASCII code. This runs
+/// before the statement is lowercased and split, which would mangle `'Z'` or `' '`.
+fn expand_char_literals(statement: &str) -> Result<Cow<'_, str>, AssemblerError> {
+    if !statement.contains('\'') {
+        return Ok(Cow::Borrowed(statement));
+    }
+
+    let bytes = statement.as_bytes();
+    let mut ret = String::with_capacity(statement.len());
+    let mut in_string = false;
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if in_string => i += 1,
+            b'"' => in_string = !in_string,
+            b'\'' if !in_string => {
+                let end = match char_literal_end(bytes, i) {
+                    Some(x) => x,
+                    None => {
+                        let literal = &statement[i..];
+                        return InvalidCharLiteralSnafu { literal }.fail();
+                    }
+                };
+                let literal = &statement[i..=end];
+                let value = literal
+                    .strip_prefix('\'')
+                    .and_then(|x| x.strip_suffix('\''))
+                    .and_then(try_parse_char)
+                    .ok_or_else(|| InvalidCharLiteralSnafu { literal }.build())?;
+
+                ret.push_str(&statement[copied..i]);
+                ret.push_str(&value.to_string());
+                copied = end + 1;
+                i = end;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    ret.push_str(&statement[copied..]);
+    Ok(Cow::Owned(ret))
+}
+
+/// Finds where a comment starts, skipping over string literals. `#` and `//` always start
+/// one; `;` does too unless it separates statements.
+fn comment_start(line: &str, semicolon_comments: bool) -> Option<usize> {
+    let bytes = line.as_bytes();
+    let mut in_string = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if in_string => i += 1,
+            b'"' => in_string = !in_string,
+            b'\'' if !in_string => i = char_literal_end(bytes, i).unwrap_or(i),
+            b'#' if !in_string => return Some(i),
+            b';' if !in_string && semicolon_comments => return Some(i),
+            b'/' if !in_string && bytes.get(i + 1) == Some(&b'/') => return Some(i),
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Splits the source into trimmed statements with comments removed, each tagged with the
+/// index of its source line. Comments are removed first, so a `;` inside a comment never
+/// starts a statement.
+fn split_statements<'a>(asm: &'a str, config: &AssemblerConfig) -> Vec<(usize, &'a str)> {
+    let mut ret = Vec::new();
+
+    for (line_idx, line) in asm.lines().enumerate() {
+        let line = line.trim();
+        let code = &line[..comment_start(line, !config.semicolon_statements).unwrap_or(line.len())];
+        if !config.semicolon_statements {
+            ret.push((line_idx, code.trim_end()));
+            continue;
+        }
+
+        let bytes = code.as_bytes();
+        let mut in_string = false;
+        let mut start = 0;
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' if in_string => i += 1,
+                b'"' => in_string = !in_string,
+                b'\'' if !in_string => i = char_literal_end(bytes, i).unwrap_or(i),
+                b';' if !in_string => {
+                    ret.push((line_idx, code[start..i].trim()));
+                    start = i + 1;
                 }
-            } else if let Some(x) = try_parse_number(token) {
-                Token::Number {
-                    text: token,
-                    num: x,
+                _ => {}
+            }
+            i += 1;
+        }
+        ret.push((line_idx, code[start..].trim()));
+    }
+
+    ret
+}
+
+const MAX_REPT_DEPTH: usize = 8;
+const MAX_REPT_STATEMENTS: usize = 1 << 16;
+
+/// Most bytes a program may assemble to. `MAX_REPT_STATEMENTS` doesn't bound this alone,
+/// since one statement may emit a long string.
+const MAX_PROGRAM_BYTES: usize = 16 << 20;
+
+/// A `.rept` block whose `.endr` hasn't come yet.
+struct OpenRept<'a> {
+    line: &'a str,
+    count: usize,
+    body: Vec<(usize, &'a str)>,
+}
+
+/// Expands `.rept N` ... `.endr` blocks, which may nest, by repeating their statements.
+/// Repeated statements keep their line. Labels inside a block are rejected, since each
+/// repetition would define them again. The whole program may expand to at most
+/// `MAX_REPT_STATEMENTS` statements; `parse` bounds the bytes they emit.
+fn expand_repeats<'a>(
+    statements: Vec<(usize, &'a str)>,
+) -> Result<Vec<(usize, &'a str)>, AssemblerError> {
+    let mut stack: Vec<OpenRept<'a>> = Vec::new();
+    let mut ret = Vec::new();
+
+    for statement in statements {
+        let lowered = statement.1.to_ascii_lowercase();
+        let mut tokens = lowered.split_whitespace();
+
+        match tokens.next() {
+            Some(".rept") => {
+                if stack.len() == MAX_REPT_DEPTH {
+                    return ReptTooDeepSnafu {
+                        max: MAX_REPT_DEPTH,
+                    }
+                    .fail();
                 }
-            } else if token.ends_with(':') {
-                Token::LabelDef { text: token }
-            } else {
-                Token::Text { text: token }
+
+                let count = match tokens.collect::<Vec<_>>()[..] {
+                    [x] => try_parse_number(x)
+                        .filter(|&x| x >= 0)
+                        .map(|x| x as usize)
+                        .ok_or_else(|| InvalidTokenSnafu { token: x }.build())?,
+                    ref x => {
+                        return InvalidNumberOfOperandsSnafu {
+                            mnemonic: ".rept",
+                            expected: 1usize,
+                            actual: x.len(),
+                            line: statement.1,
+                        }
+                        .fail()
+                    }
+                };
+                stack.push(OpenRept {
+                    line: statement.1,
+                    count,
+                    body: Vec::new(),
+                });
             }
-        })
-        .collect()
+            Some(".endr") => {
+                let OpenRept { count, body, .. } =
+                    stack.pop().ok_or_else(|| UnmatchedEndrSnafu.build())?;
+                let out = stack.last_mut().map_or(&mut ret, |x| &mut x.body);
+
+                let room = MAX_REPT_STATEMENTS.saturating_sub(out.len());
+                if body.len().saturating_mul(count) > room {
+                    return ReptTooLargeSnafu {
+                        max: MAX_REPT_STATEMENTS,
+                    }
+                    .fail();
+                }
+                // an empty body passes any count, and repeating it adds nothing
+                if !body.is_empty() {
+                    for _ in 0..count {
+                        out.extend_from_slice(&body);
+                    }
+                }
+            }
+            _ => match stack.last_mut() {
+                Some(x) => {
+                    if let Some((label, _)) = split_leading_label(&lowered) {
+                        return LabelInReptSnafu { label }.fail();
+                    }
+                    x.body.push(statement);
+                }
+                None => ret.push(statement),
+            },
+        }
+    }
+
+    if let Some(x) = stack.pop() {
+        return UnterminatedReptSnafu { line: x.line }.fail();
+    }
+
+    Ok(ret)
+}
+
+/// Splits `label:` off the start of a statement, returning the label and the rest.
+fn split_leading_label(line: &str) -> Option<(&str, &str)> {
+    let end = line.find(|c: char| c.is_whitespace()).unwrap_or(line.len());
+    let label = line[..end].strip_suffix(':')?;
+    if label.is_empty() {
+        return None;
+    }
+
+    Some((label, line[end..].trim_start()))
 }
 
 fn try_parse_number(text: &str) -> Option<i64> {
@@ -172,30 +568,65 @@ fn try_parse_number(text: &str) -> Option<i64> {
     }
 }
 
+/// Parses a `.float` or `.double` operand. Infinity and NaN must be spelled `inf` and `nan`;
+/// other spellings, and literals that overflow to infinity, are rejected.
+fn try_parse_float<T: FromStr + Copy + Into<f64>>(text: &str) -> Option<T> {
+    let val: T = text.parse().ok()?;
+    let explicit = matches!(text.trim_start_matches(['+', '-']), "inf" | "nan");
+
+    if val.into().is_finite() || explicit {
+        Some(val)
+    } else {
+        None
+    }
+}
+
 fn try_parse_reg(name: &str) -> Option<RegisterName> {
     name.strip_prefix('$').and_then(RegisterName::try_from_name)
 }
 
+fn try_parse_fpreg(name: &str) -> Option<FpRegisterName> {
+    name.strip_prefix('$')
+        .and_then(FpRegisterName::try_from_name)
+}
+
 fn expect_args_count(ctx: &LineContext<'_>, expect_len: usize) -> Result<(), AssemblerError> {
     if ctx.args.len() == expect_len {
         Ok(())
     } else {
-        InvalidNumberOfOperandsSnafu { line: ctx.line }.fail()
+        InvalidNumberOfOperandsSnafu {
+            mnemonic: ctx.mnemonic,
+            expected: expect_len,
+            actual: ctx.args.len(),
+            line: ctx.line,
+        }
+        .fail()
+    }
+}
+
+fn expect_in_range(val: i64, min: i64, max: i64) -> Result<i64, AssemblerError> {
+    if (min..=max).contains(&val) {
+        Ok(val)
+    } else {
+        ImmediateOutOfRangeSnafu {
+            value: val,
+            min,
+            max,
+        }
+        .fail()
     }
 }
 
+/// Checks that `val` fits a 16-bit immediate: `-32768..=32767` if the instruction
+/// sign-extends it, `0..=65535` otherwise.
 fn expect_extendable(val: i64, sign_ext: bool) -> Result<u16, AssemblerError> {
-    let interpreted = if sign_ext {
-        val as i16 as i64
+    let (min, max) = if sign_ext {
+        (i16::MIN as i64, i16::MAX as i64)
     } else {
-        val as u16 as u64 as i64
+        (0, u16::MAX as i64)
     };
 
-    if interpreted == val {
-        Ok(val as u16)
-    } else {
-        ImmediateTooLargeSnafu { imm: val }.fail()
-    }
+    expect_in_range(val, min, max).map(|x| x as u16)
 }
 
 fn try_parse_ins_3arg(ctx: &mut LineContext<'_>) -> Result<TypeR, AssemblerError> {
@@ -209,6 +640,16 @@ fn try_parse_ins_3arg(ctx: &mut LineContext<'_>) -> Result<TypeR, AssemblerError
     })
 }
 
+fn try_parse_ins_fp_3arg(ctx: &mut LineContext<'_>) -> Result<TypeFR, AssemblerError> {
+    expect_args_count(ctx, 3)?;
+
+    Ok(TypeFR {
+        fd: ctx.args[0].as_fp_register()?,
+        fs: ctx.args[1].as_fp_register()?,
+        ft: ctx.args[2].as_fp_register()?,
+    })
+}
+
 fn try_parse_ins_shift_reg(ctx: &mut LineContext<'_>) -> Result<TypeR, AssemblerError> {
     expect_args_count(ctx, 3)?;
 
@@ -228,7 +669,7 @@ fn try_parse_ins_imm(ctx: &mut LineContext<'_>, sign_ext: bool) -> Result<TypeI,
     Ok(TypeI {
         rt: ctx.args[0].as_register()?,
         rs: ctx.args[1].as_register()?,
-        imm: expect_extendable(ctx.args[2].as_number()?, sign_ext)?,
+        imm: expect_extendable(ctx.eval(&ctx.args[2])?.num, sign_ext)?,
     })
 }
 
@@ -238,7 +679,7 @@ fn try_parse_ins_lui(ctx: &mut LineContext<'_>) -> Result<TypeI, AssemblerError>
     Ok(TypeI {
         rt: ctx.args[0].as_register()?,
         rs: RegisterName::new(0),
-        imm: expect_extendable(ctx.args[1].as_number()?, false)?,
+        imm: expect_extendable(ctx.eval(&ctx.args[1])?.num, false)?,
     })
 }
 
@@ -247,11 +688,7 @@ fn try_parse_ins_shift_imm(ctx: &mut LineContext<'_>) -> Result<TypeR, Assembler
 
     let rd = ctx.args[0].as_register()?;
     let rt = ctx.args[1].as_register()?;
-    let imm = ctx.args[2].as_number()?;
-
-    if 32 <= imm {
-        return ImmediateTooLargeSnafu { imm: imm as i64 }.fail();
-    }
+    let imm = expect_in_range(ctx.eval(&ctx.args[2])?.num, 0, 31)?;
 
     Ok(TypeR {
         rs: RegisterName::new(0),
@@ -261,28 +698,84 @@ fn try_parse_ins_shift_imm(ctx: &mut LineContext<'_>) -> Result<TypeR, Assembler
     })
 }
 
-fn try_parse_ins_memory(ctx: &mut LineContext<'_>) -> Result<TypeI, AssemblerError> {
+/// Splits `$rt, offset($rs)` into its parts.
+fn parse_memory_operands(
+    ctx: &LineContext<'_>,
+) -> Result<(RegisterName, Expr, RegisterName), AssemblerError> {
+    expect_args_count(ctx, 2)?;
+    let rt = ctx.args[0].as_register()?;
+    let (offset, rs) = parse_memory_address(ctx, &ctx.args[1])?;
+
+    Ok((rt, offset, rs))
+}
+
+/// The `offset(base)` operand of a load, store or `pref`.
+fn parse_memory_address(
+    ctx: &LineContext<'_>,
+    token: &Token,
+) -> Result<(Expr, RegisterName), AssemblerError> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"(\$.+)\s*,\s*([^ ]+?)\((\$.+)\)").unwrap();
+        static ref RE: Regex = Regex::new(r"^(.+)\((.+)\)$").unwrap();
     }
 
-    let caps = match RE.captures(ctx.args_raw) {
+    let addr = token.as_text();
+    let caps = match RE.captures(addr) {
         Some(x) => x,
-        None => InvalidNumberOfOperandsSnafu { line: ctx.line }.fail()?,
+        None => InvalidTokenSnafu { token: addr }.fail()?,
     };
 
-    let imm = expect_extendable(
-        try_parse_number(&caps[2]).ok_or_else(|| InvalidTokenSnafu { token: &caps[2] }.build())?,
-        true,
-    )?;
+    let offset = eval_expr(&caps[1], ctx.labels, ctx.constants)?;
     let rs =
-        try_parse_reg(&caps[3]).ok_or_else(|| InvalidTokenSnafu { token: &caps[2] }.build())?;
-    let rt =
-        try_parse_reg(&caps[1]).ok_or_else(|| InvalidTokenSnafu { token: &caps[2] }.build())?;
+        try_parse_reg(&caps[2]).ok_or_else(|| InvalidTokenSnafu { token: &caps[2] }.build())?;
+
+    Ok((offset, rs))
+}
+
+fn try_parse_ins_memory(ctx: &mut LineContext<'_>) -> Result<TypeI, AssemblerError> {
+    let (rt, offset, rs) = parse_memory_operands(ctx)?;
+    let imm = expect_extendable(offset.num, true)?;
 
     Ok(TypeI { rs, rt, imm })
 }
 
+/// `lwc1 $ft, offset(base)` and `swc1`, with `ft` in `rt`.
+fn try_parse_ins_fp_memory(ctx: &mut LineContext<'_>) -> Result<TypeI, AssemblerError> {
+    expect_args_count(ctx, 2)?;
+    let ft = ctx.args[0].as_fp_register()?;
+    let (offset, rs) = parse_memory_address(ctx, &ctx.args[1])?;
+
+    Ok(TypeI {
+        rs,
+        rt: RegisterName::new(ft.num()),
+        imm: expect_extendable(offset.num, true)?,
+    })
+}
+
+/// `mfc1 $rt, $fs` and `mtc1`, with `fs` in `rd`.
+fn try_parse_ins_fp_move(ctx: &mut LineContext<'_>) -> Result<TypeR, AssemblerError> {
+    expect_args_count(ctx, 2)?;
+
+    Ok(TypeR {
+        rs: RegisterName::new(0),
+        rt: ctx.args[0].as_register()?,
+        rd: RegisterName::new(ctx.args[1].as_fp_register()?.num()),
+        shamt: 0,
+    })
+}
+
+/// `pref hint, offset(base)`, with the hint in `rt`.
+fn try_parse_ins_pref(ctx: &mut LineContext<'_>) -> Result<TypeI, AssemblerError> {
+    expect_args_count(ctx, 2)?;
+    let hint = expect_in_range(ctx.eval(&ctx.args[0])?.num, 0, 31)?;
+    let (offset, rs) = parse_memory_address(ctx, &ctx.args[1])?;
+
+    Ok(TypeI {
+        rs,
+        rt: RegisterName::new(hint as u8),
+        imm: expect_extendable(offset.num, true)?,
+    })
+}
+
 fn try_parse_ins_branch(ctx: &mut LineContext<'_>) -> Result<TypeI, AssemblerError> {
     expect_args_count(ctx, 3)?;
 
@@ -313,6 +806,47 @@ fn try_parse_ins_branch_complex(ctx: &mut LineContext) -> Result<TypeI, Assemble
     })
 }
 
+/// `b target` and `bal target`: a branch that compares `$zero` against itself.
+fn try_parse_ins_branch_always(ctx: &mut LineContext) -> Result<TypeI, AssemblerError> {
+    expect_args_count(ctx, 1)?;
+
+    let rt = match ctx.mnemonic {
+        "b" => 0x00,   // beq $zero, $zero
+        "bal" => 0x11, // bgezal $zero
+        _ => unreachable!(),
+    };
+
+    Ok(TypeI {
+        rs: RegisterName::new(0),
+        rt: RegisterName::new(rt),
+        imm: ctx.resolve_branch(&ctx.args[0])? as u16,
+    })
+}
+
+/// `move $rd, $rs` as `addu $rd, $rs, $zero`, `not $rd, $rs` as `nor $rd, $rs, $zero`,
+/// and `neg $rd, $rs` as `sub $rd, $zero, $rs`.
+fn try_parse_ins_unary(ctx: &mut LineContext) -> Result<TypeR, AssemblerError> {
+    expect_args_count(ctx, 2)?;
+
+    let rd = ctx.args[0].as_register()?;
+    let rs = ctx.args[1].as_register()?;
+    let zero = RegisterName::new(0);
+
+    let (rs, rt) = match ctx.mnemonic {
+        "move" | "not" => (rs, zero),
+        "neg" => (zero, rs),
+        "clo" | "clz" => (rs, rd),
+        _ => unreachable!(),
+    };
+
+    Ok(TypeR {
+        rs,
+        rt,
+        rd,
+        shamt: 0,
+    })
+}
+
 fn try_parse_ins_jump(ctx: &mut LineContext) -> Result<TypeJ, AssemblerError> {
     expect_args_count(ctx, 1)?;
 
@@ -328,11 +862,10 @@ fn try_parse_ins_jump_reg_linked(ctx: &mut LineContext) -> Result<TypeR, Assembl
     if ctx.args.len() == 1 {
         rd = RegisterName::new(31);
         rs = ctx.args[0].as_register()?;
-    } else if ctx.args.len() == 2 {
+    } else {
+        expect_args_count(ctx, 2)?;
         rd = ctx.args[0].as_register()?;
         rs = ctx.args[1].as_register()?;
-    } else {
-        return InvalidNumberOfOperandsSnafu { line: ctx.line }.fail();
     }
 
     Ok(TypeR {
@@ -361,152 +894,552 @@ fn try_parse_ins_syscall(ctx: &mut LineContext) -> Result<TypeR, AssemblerError>
     Ok(Default::default())
 }
 
-fn try_parse_ins(ctx: &mut LineContext) -> Result<Instruction, AssemblerError> {
-    use Instruction::*;
+/// `sync`, optionally with its type.
+fn try_parse_ins_sync(ctx: &mut LineContext) -> Result<TypeR, AssemblerError> {
+    let shamt = if ctx.args.is_empty() {
+        0
+    } else {
+        expect_args_count(ctx, 1)?;
+        expect_in_range(ctx.eval(&ctx.args[0])?.num, 0, 31)? as u8
+    };
 
-    Ok(match ctx.mnemonic {
-        "nop" => sll(Default::default()),
-
-        "add" => add(try_parse_ins_3arg(ctx)?),
-        "addu" => addu(try_parse_ins_3arg(ctx)?),
-        "and" => and(try_parse_ins_3arg(ctx)?),
-        "nor" => nor(try_parse_ins_3arg(ctx)?),
-        "or" => or(try_parse_ins_3arg(ctx)?),
-        "slt" => slt(try_parse_ins_3arg(ctx)?),
-        "sltu" => sltu(try_parse_ins_3arg(ctx)?),
-        "sub" => sub(try_parse_ins_3arg(ctx)?),
-        "subu" => subu(try_parse_ins_3arg(ctx)?),
-        "xor" => xor(try_parse_ins_3arg(ctx)?),
-
-        "sll" => sll(try_parse_ins_shift_imm(ctx)?),
-        "sllv" => sllv(try_parse_ins_shift_reg(ctx)?),
-        "sra" => sra(try_parse_ins_shift_imm(ctx)?),
-        "srav" => srav(try_parse_ins_shift_reg(ctx)?),
-        "srl" => srl(try_parse_ins_shift_imm(ctx)?),
-        "srlv" => srlv(try_parse_ins_shift_reg(ctx)?),
-
-        "addi" => addi(try_parse_ins_imm(ctx, true)?),
-        "addiu" => addiu(try_parse_ins_imm(ctx, true)?),
-        "andi" => andi(try_parse_ins_imm(ctx, false)?),
-        "lui" => lui(try_parse_ins_lui(ctx)?),
-        "ori" => ori(try_parse_ins_imm(ctx, false)?),
-        "slti" => slti(try_parse_ins_imm(ctx, true)?),
-        "sltiu" => sltiu(try_parse_ins_imm(ctx, false)?),
-        "xori" => xori(try_parse_ins_imm(ctx, false)?),
-
-        "beq" => beq(try_parse_ins_branch(ctx)?),
-        "bgez" => bgez(try_parse_ins_branch_complex(ctx)?),
-        "bgezal" => bgezal(try_parse_ins_branch_complex(ctx)?),
-        "bgtz" => bgtz(try_parse_ins_branch_complex(ctx)?),
-        "blez" => blez(try_parse_ins_branch_complex(ctx)?),
-        "bltz" => bltz(try_parse_ins_branch_complex(ctx)?),
-        "bltzal" => bltzal(try_parse_ins_branch_complex(ctx)?),
-        "bne" => bne(try_parse_ins_branch(ctx)?),
-
-        "lb" => lb(try_parse_ins_memory(ctx)?),
-        "lbu" => lbu(try_parse_ins_memory(ctx)?),
-        "lh" => lh(try_parse_ins_memory(ctx)?),
-        "lhu" => lhu(try_parse_ins_memory(ctx)?),
-        "lw" => lw(try_parse_ins_memory(ctx)?),
-        "sb" => sb(try_parse_ins_memory(ctx)?),
-        "sh" => sh(try_parse_ins_memory(ctx)?),
-        "sw" => sw(try_parse_ins_memory(ctx)?),
-
-        "j" => j(try_parse_ins_jump(ctx)?),
-        "jal" => jal(try_parse_ins_jump(ctx)?),
-        "jalr" => jalr(try_parse_ins_jump_reg_linked(ctx)?),
-        "jr" => jr(try_parse_ins_jump_reg(ctx)?),
-        "syscall" => syscall(try_parse_ins_syscall(ctx)?),
-
-        _ => return UnknownInstructionSnafu { ins: ctx.mnemonic }.fail(),
+    Ok(TypeR {
+        shamt,
+        ..Default::default()
     })
 }
 
-fn parse(
-    endian: EndianMode,
-    asm: &str,
-    labels: &Option<HashMap<String, u32>>,
-) -> Result<Vec<Segment>, AssemblerError> {
-    let mut segs = vec![];
-    let mut curr_seg: Option<Segment> = None;
-    let mut global_labels = HashSet::new();
-    let mut is_text_seg = false;
+/// `teq $rs, $rt` and the other traps, with a trap code of 0.
+fn try_parse_ins_trap(ctx: &mut LineContext) -> Result<TypeR, AssemblerError> {
+    expect_args_count(ctx, 2)?;
 
-    const TEXT_SEGMENT: RangeInclusive<u32> = 0x00400000..=0x0fffffff;
-    const DATA_SEGMENT: RangeInclusive<u32> = 0x10000000..=0x7fffffff;
+    Ok(TypeR {
+        rs: ctx.args[0].as_register()?,
+        rt: ctx.args[1].as_register()?,
+        rd: RegisterName::new(0),
+        shamt: 0,
+    })
+}
 
-    let mut next_data_addr = 0x10000000;
-    let mut next_text_addr = 0x00400024;
+fn try_parse_pseudo_li(ctx: &mut LineContext) -> Result<Vec<Instruction>, AssemblerError> {
+    use Instruction::*;
 
-    for line_raw in asm.lines() {
-        let line = line_raw.trim().to_ascii_lowercase();
+    expect_args_count(ctx, 2)?;
+    let rt = ctx.args[0].as_register()?;
+    let Expr {
+        num: imm,
+        has_label,
+    } = ctx.eval(&ctx.args[1])?;
+
+    // a label's value is unknown on the first pass, so always use the long form for it
+    if !has_label {
+        if let Ok(x) = expect_extendable(imm, true) {
+            let rs = RegisterName::new(0);
+            return Ok(vec![addiu(TypeI { rs, rt, imm: x })]);
+        }
+        if let Ok(x) = expect_extendable(imm, false) {
+            let rs = RegisterName::new(0);
+            return Ok(vec![ori(TypeI { rs, rt, imm: x })]);
+        }
+    }
 
-        let line = if let Some(comment_pos) = line.find('#') {
-            &line[..comment_pos]
-        } else {
-            &line
-        };
+    if imm < i32::MIN as i64 || imm > u32::MAX as i64 {
+        return ImmediateTooLargeSnafu { imm }.fail();
+    }
 
-        let mut tokens = RE_SEPARATOR.splitn(line, 2);
+    let imm = imm as u32;
+    Ok(vec![
+        lui(TypeI {
+            rs: RegisterName::new(0),
+            rt,
+            imm: (imm >> 16) as u16,
+        }),
+        ori(TypeI {
+            rs: rt,
+            rt,
+            imm: imm as u16,
+        }),
+    ])
+}
 
-        let first_token = match tokens.next() {
-            Some(x) => x,
-            None => continue,
-        };
+/// `abs $rd, $rs`: `sra $at, $rs, 31; xor $rd, $rs, $at; subu $rd, $rd, $at`.
+/// `$at` is all ones for a negative value, so this flips and adds one only then.
+fn try_parse_pseudo_abs(ctx: &mut LineContext) -> Result<Vec<Instruction>, AssemblerError> {
+    use Instruction::*;
 
-        if first_token.is_empty() {
-            continue;
-        }
+    expect_args_count(ctx, 2)?;
+    let rd = ctx.args[0].as_register()?;
+    let rs = ctx.args[1].as_register()?;
+    let at = RegisterName::new(1);
+
+    Ok(vec![
+        sra(TypeR {
+            rs: RegisterName::new(0),
+            rt: rs,
+            rd: at,
+            shamt: 31,
+        }),
+        xor(TypeR {
+            rs,
+            rt: at,
+            rd,
+            shamt: 0,
+        }),
+        subu(TypeR {
+            rs: rd,
+            rt: at,
+            rd,
+            shamt: 0,
+        }),
+    ])
+}
 
-        let args_raw = tokens.next().unwrap_or("");
-        let tokens = tokenize(RE_SEPARATOR.split(args_raw));
+/// A load or store whose offset names a label, e.g. `lw $t0, array+4($gp)`. The address
+/// won't fit in 16 bits, so it goes through `$at`:
+/// `lui $at, hi; addu $at, $at, $rs; lw $rt, lo($at)`.
+/// Returns `None` for plain numeric offsets.
+fn try_parse_pseudo_memory(
+    ctx: &mut LineContext,
+) -> Result<Option<Vec<Instruction>>, AssemblerError> {
+    use Instruction::*;
 
-        if first_token == ".text" || first_token == ".data" {
-            if let Some(x) = curr_seg {
-                if is_text_seg {
-                    next_text_addr = x.next_address();
-                } else {
-                    next_data_addr = x.next_address();
-                }
+    let op: fn(TypeI) -> Instruction = match ctx.mnemonic {
+        "lb" => lb,
+        "lbu" => lbu,
+        "lh" => lh,
+        "lhu" => lhu,
+        "lw" => lw,
+        "sb" => sb,
+        "sh" => sh,
+        "sw" => sw,
+        _ => unreachable!(),
+    };
 
-                segs.push(x);
-            }
+    if ctx.args.len() == 2 && !ctx.args[1].as_text().contains('(') {
+        return try_parse_pseudo_memory_absolute(ctx, op).map(Some);
+    }
 
-            let base_addr = if tokens.is_empty() {
-                if first_token == ".text" {
-                    next_text_addr
-                } else {
-                    next_data_addr
-                }
-            } else if tokens.len() == 1 {
-                let addr = tokens[0].as_number()?;
+    let (rt, offset, rs) = parse_memory_operands(ctx)?;
+    if !offset.has_label {
+        return Ok(None);
+    }
+
+    let at = RegisterName::new(1);
+    let addr = offset.num as u32;
+    // the low half is sign-extended by the load/store, so round the high half to match
+    let hi = (addr.wrapping_add(0x8000) >> 16) as u16;
+
+    Ok(Some(vec![
+        lui(TypeI {
+            rs: RegisterName::new(0),
+            rt: at,
+            imm: hi,
+        }),
+        addu(TypeR {
+            rs: at,
+            rt: rs,
+            rd: at,
+            shamt: 0,
+        }),
+        op(TypeI {
+            rs: at,
+            rt,
+            imm: addr as u16,
+        }),
+    ]))
+}
+
+/// A load or store of an address with no base register, e.g. `lw $t0, count`. Within
+/// reach of `$gp` it is one instruction relative to it; elsewhere it goes through `$at`:
+/// `lui $at, hi; lw $rt, lo($at)`.
+fn try_parse_pseudo_memory_absolute(
+    ctx: &mut LineContext,
+    op: fn(TypeI) -> Instruction,
+) -> Result<Vec<Instruction>, AssemblerError> {
+    use Instruction::*;
+
+    let rt = ctx.args[0].as_register()?;
+    let addr = ctx.eval(&ctx.args[1])?;
+
+    if let Some(imm) = ctx.gp_offset(addr) {
+        let rs = RegisterName::new(28);
+        return Ok(vec![op(TypeI { rs, rt, imm })]);
+    }
+
+    let at = RegisterName::new(1);
+    let addr = addr.num as u32;
+    let hi = (addr.wrapping_add(0x8000) >> 16) as u16;
+    Ok(vec![
+        lui(TypeI {
+            rs: RegisterName::new(0),
+            rt: at,
+            imm: hi,
+        }),
+        op(TypeI {
+            rs: at,
+            rt,
+            imm: addr as u16,
+        }),
+    ])
+}
+
+/// `la $rd, label`: `addiu $rd, $gp, offset` within reach of `$gp`, and
+/// `lui $rd, hi; ori $rd, $rd, lo` elsewhere.
+fn try_parse_pseudo_la(ctx: &mut LineContext) -> Result<Vec<Instruction>, AssemblerError> {
+    use Instruction::*;
+
+    expect_args_count(ctx, 2)?;
+    let rt = ctx.args[0].as_register()?;
+    let addr = ctx.eval(&ctx.args[1])?;
+
+    if let Some(imm) = ctx.gp_offset(addr) {
+        let rs = RegisterName::new(28);
+        return Ok(vec![addiu(TypeI { rs, rt, imm })]);
+    }
+
+    let addr = addr.num as u32;
+    Ok(vec![
+        lui(TypeI {
+            rs: RegisterName::new(0),
+            rt,
+            imm: (addr >> 16) as u16,
+        }),
+        ori(TypeI {
+            rs: rt,
+            rt,
+            imm: addr as u16,
+        }),
+    ])
+}
+
+type InsParser = fn(&mut LineContext) -> Result<Instruction, AssemblerError>;
+type PseudoParser = fn(&mut LineContext) -> Result<Option<Vec<Instruction>>, AssemblerError>;
+
+/// Pseudo-instructions that may expand to more than one word. A parser returns `None` to
+/// fall back to the instruction of the same name.
+static PSEUDO_INSTRUCTIONS: &[(&str, PseudoParser)] = &[
+    ("li", |ctx| Ok(Some(try_parse_pseudo_li(ctx)?))),
+    ("la", |ctx| Ok(Some(try_parse_pseudo_la(ctx)?))),
+    ("abs", |ctx| Ok(Some(try_parse_pseudo_abs(ctx)?))),
+    ("lb", try_parse_pseudo_memory),
+    ("lbu", try_parse_pseudo_memory),
+    ("lh", try_parse_pseudo_memory),
+    ("lhu", try_parse_pseudo_memory),
+    ("lw", try_parse_pseudo_memory),
+    ("sb", try_parse_pseudo_memory),
+    ("sh", try_parse_pseudo_memory),
+    ("sw", try_parse_pseudo_memory),
+];
+
+/// Everything that assembles to a single word, by mnemonic.
+static INSTRUCTIONS: &[(&str, InsParser)] = {
+    use Instruction::*;
+
+    &[
+        ("nop", |_| Ok(sll(Default::default()))),
+        ("add", |ctx| Ok(add(try_parse_ins_3arg(ctx)?))),
+        ("addu", |ctx| Ok(addu(try_parse_ins_3arg(ctx)?))),
+        ("and", |ctx| Ok(and(try_parse_ins_3arg(ctx)?))),
+        ("nor", |ctx| Ok(nor(try_parse_ins_3arg(ctx)?))),
+        ("or", |ctx| Ok(or(try_parse_ins_3arg(ctx)?))),
+        ("slt", |ctx| Ok(slt(try_parse_ins_3arg(ctx)?))),
+        ("sltu", |ctx| Ok(sltu(try_parse_ins_3arg(ctx)?))),
+        ("sub", |ctx| Ok(sub(try_parse_ins_3arg(ctx)?))),
+        ("subu", |ctx| Ok(subu(try_parse_ins_3arg(ctx)?))),
+        ("xor", |ctx| Ok(xor(try_parse_ins_3arg(ctx)?))),
+        ("mul", |ctx| Ok(mul(try_parse_ins_3arg(ctx)?))),
+        ("movn", |ctx| Ok(movn(try_parse_ins_3arg(ctx)?))),
+        ("movz", |ctx| Ok(movz(try_parse_ins_3arg(ctx)?))),
+        ("move", |ctx| Ok(addu(try_parse_ins_unary(ctx)?))),
+        ("not", |ctx| Ok(nor(try_parse_ins_unary(ctx)?))),
+        ("neg", |ctx| Ok(sub(try_parse_ins_unary(ctx)?))),
+        ("clo", |ctx| Ok(clo(try_parse_ins_unary(ctx)?))),
+        ("clz", |ctx| Ok(clz(try_parse_ins_unary(ctx)?))),
+        ("sll", |ctx| Ok(sll(try_parse_ins_shift_imm(ctx)?))),
+        ("sllv", |ctx| Ok(sllv(try_parse_ins_shift_reg(ctx)?))),
+        ("sra", |ctx| Ok(sra(try_parse_ins_shift_imm(ctx)?))),
+        ("srav", |ctx| Ok(srav(try_parse_ins_shift_reg(ctx)?))),
+        ("srl", |ctx| Ok(srl(try_parse_ins_shift_imm(ctx)?))),
+        ("srlv", |ctx| Ok(srlv(try_parse_ins_shift_reg(ctx)?))),
+        ("addi", |ctx| Ok(addi(try_parse_ins_imm(ctx, true)?))),
+        ("addiu", |ctx| Ok(addiu(try_parse_ins_imm(ctx, true)?))),
+        ("andi", |ctx| Ok(andi(try_parse_ins_imm(ctx, false)?))),
+        ("lui", |ctx| Ok(lui(try_parse_ins_lui(ctx)?))),
+        ("ori", |ctx| Ok(ori(try_parse_ins_imm(ctx, false)?))),
+        ("slti", |ctx| Ok(slti(try_parse_ins_imm(ctx, true)?))),
+        ("sltiu", |ctx| Ok(sltiu(try_parse_ins_imm(ctx, false)?))),
+        ("xori", |ctx| Ok(xori(try_parse_ins_imm(ctx, false)?))),
+        ("beq", |ctx| Ok(beq(try_parse_ins_branch(ctx)?))),
+        ("bgez", |ctx| Ok(bgez(try_parse_ins_branch_complex(ctx)?))),
+        ("bgezal", |ctx| {
+            Ok(bgezal(try_parse_ins_branch_complex(ctx)?))
+        }),
+        ("bgtz", |ctx| Ok(bgtz(try_parse_ins_branch_complex(ctx)?))),
+        ("blez", |ctx| Ok(blez(try_parse_ins_branch_complex(ctx)?))),
+        ("bltz", |ctx| Ok(bltz(try_parse_ins_branch_complex(ctx)?))),
+        ("bltzal", |ctx| {
+            Ok(bltzal(try_parse_ins_branch_complex(ctx)?))
+        }),
+        ("bne", |ctx| Ok(bne(try_parse_ins_branch(ctx)?))),
+        ("b", |ctx| Ok(beq(try_parse_ins_branch_always(ctx)?))),
+        ("bal", |ctx| Ok(bgezal(try_parse_ins_branch_always(ctx)?))),
+        ("lb", |ctx| Ok(lb(try_parse_ins_memory(ctx)?))),
+        ("lbu", |ctx| Ok(lbu(try_parse_ins_memory(ctx)?))),
+        ("lh", |ctx| Ok(lh(try_parse_ins_memory(ctx)?))),
+        ("lhu", |ctx| Ok(lhu(try_parse_ins_memory(ctx)?))),
+        ("lw", |ctx| Ok(lw(try_parse_ins_memory(ctx)?))),
+        ("sb", |ctx| Ok(sb(try_parse_ins_memory(ctx)?))),
+        ("sh", |ctx| Ok(sh(try_parse_ins_memory(ctx)?))),
+        ("sw", |ctx| Ok(sw(try_parse_ins_memory(ctx)?))),
+        ("pref", |ctx| Ok(pref(try_parse_ins_pref(ctx)?))),
+        ("j", |ctx| Ok(j(try_parse_ins_jump(ctx)?))),
+        ("jal", |ctx| Ok(jal(try_parse_ins_jump(ctx)?))),
+        ("jalr", |ctx| Ok(jalr(try_parse_ins_jump_reg_linked(ctx)?))),
+        ("jr", |ctx| Ok(jr(try_parse_ins_jump_reg(ctx)?))),
+        ("syscall", |ctx| Ok(syscall(try_parse_ins_syscall(ctx)?))),
+        ("sync", |ctx| Ok(sync(try_parse_ins_sync(ctx)?))),
+        ("teq", |ctx| Ok(teq(try_parse_ins_trap(ctx)?))),
+        ("tge", |ctx| Ok(tge(try_parse_ins_trap(ctx)?))),
+        ("tgeu", |ctx| Ok(tgeu(try_parse_ins_trap(ctx)?))),
+        ("tlt", |ctx| Ok(tlt(try_parse_ins_trap(ctx)?))),
+        ("tltu", |ctx| Ok(tltu(try_parse_ins_trap(ctx)?))),
+        ("tne", |ctx| Ok(tne(try_parse_ins_trap(ctx)?))),
+        ("add.s", |ctx| Ok(add_s(try_parse_ins_fp_3arg(ctx)?))),
+        ("sub.s", |ctx| Ok(sub_s(try_parse_ins_fp_3arg(ctx)?))),
+        ("mul.s", |ctx| Ok(mul_s(try_parse_ins_fp_3arg(ctx)?))),
+        ("div.s", |ctx| Ok(div_s(try_parse_ins_fp_3arg(ctx)?))),
+        ("lwc1", |ctx| Ok(lwc1(try_parse_ins_fp_memory(ctx)?))),
+        ("swc1", |ctx| Ok(swc1(try_parse_ins_fp_memory(ctx)?))),
+        ("mfc1", |ctx| Ok(mfc1(try_parse_ins_fp_move(ctx)?))),
+        ("mtc1", |ctx| Ok(mtc1(try_parse_ins_fp_move(ctx)?))),
+    ]
+};
+
+/// Returns `None` when the mnemonic is not a pseudo-instruction.
+fn try_parse_pseudo(ctx: &mut LineContext) -> Result<Option<Vec<Instruction>>, AssemblerError> {
+    match PSEUDO_INSTRUCTIONS.iter().find(|x| x.0 == ctx.mnemonic) {
+        Some((_, parse)) => parse(ctx),
+        None => Ok(None),
+    }
+}
+
+fn try_parse_ins(ctx: &mut LineContext) -> Result<Instruction, AssemblerError> {
+    match INSTRUCTIONS.iter().find(|x| x.0 == ctx.mnemonic) {
+        Some((_, parse)) => parse(ctx),
+        None => UnknownInstructionSnafu { ins: ctx.mnemonic }.fail(),
+    }
+}
+
+/// Every mnemonic the assembler accepts, instructions and pseudo-instructions alike, sorted.
+pub fn supported_mnemonics() -> Vec<&'static str> {
+    let names = INSTRUCTIONS.iter().map(|x| x.0);
+    let mut ret: Vec<_> = names
+        .chain(PSEUDO_INSTRUCTIONS.iter().map(|x| x.0))
+        .collect();
+    ret.sort_unstable();
+    ret.dedup();
+    ret
+}
+
+pub const TEXT_SEGMENT: RangeInclusive<u32> = 0x00400000..=0x0fffffff;
+pub const DATA_SEGMENT: RangeInclusive<u32> = 0x10000000..=0x7fffffff;
+
+/// Everything produced by [`assemble_with`].
+#[derive(Debug, Clone)]
+pub struct Assembly {
+    pub segments: Vec<Segment>,
+    /// Labels named by `.globl`, with their resolved addresses.
+    pub globals: HashMap<String, u32>,
+    /// Every label, exported or not, with its resolved address.
+    pub labels: HashMap<String, u32>,
+    /// Found by [`AssemblerConfig::lint`]; always empty without it.
+    pub warnings: Vec<Warning>,
+    /// Addresses filled by data directives such as `.word` or `.align`, in any segment.
+    pub data: Vec<Range<u32>>,
+}
+
+/// One source line of an assembly listing.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ListingLine {
+    /// Where the first byte of this line went, if it emitted any.
+    pub addr: Option<u32>,
+    /// Everything the line emitted, in memory order.
+    pub bytes: Vec<u8>,
+    pub source: String,
+}
+
+/// Bytes `addr..addr + len` were emitted by source line `line`.
+struct Span {
+    line: usize,
+    addr: u32,
+    len: u32,
+}
+
+struct Parsed {
+    segments: Vec<Segment>,
+    global_labels: HashSet<String>,
+    spans: Vec<Span>,
+    data: Vec<Range<u32>>,
+    /// Where the next `.text` and `.data` without an address would start.
+    next: (u32, u32),
+}
+
+/// The statement that started at `start` is done; record what it emitted. Statements which
+/// switched to another segment emitted nothing into the old one.
+fn close_span(
+    pending: Option<(usize, usize, u32)>,
+    segs: &[Segment],
+    curr_seg: &Option<Segment>,
+    spans: &mut Vec<Span>,
+) {
+    if let (Some((line, segs_len, addr)), Some(seg)) = (pending, curr_seg) {
+        let len = seg.next_address().wrapping_sub(addr);
+        if segs_len == segs.len() && len != 0 {
+            spans.push(Span { line, addr, len });
+        }
+    }
+}
+
+/// Assembles `asm` once. `start` holds where `.text` and `.data` without an address begin.
+fn parse(
+    endian: EndianMode,
+    asm: &str,
+    labels: &Option<HashMap<String, u32>>,
+    config: &AssemblerConfig,
+    start: (u32, u32),
+) -> Result<Parsed, AssemblerError> {
+    let mut segs = vec![];
+    let mut curr_seg: Option<Segment> = None;
+    let mut global_labels = HashSet::new();
+    let mut is_text_seg = false;
+    let mut spans = Vec::new();
+    let mut data = Vec::new();
+    let mut pending = None;
+    let mut constants = HashMap::new();
+    // bytes in `segs`, which are done
+    let mut emitted = 0;
+
+    let (mut next_text_addr, mut next_data_addr) = start;
+
+    for (line_idx, statement) in expand_repeats(split_statements(asm, config))? {
+        if emitted + curr_seg.as_ref().map_or(0, |x| x.data.len()) > MAX_PROGRAM_BYTES {
+            return ProgramTooLargeSnafu {
+                max: MAX_PROGRAM_BYTES,
+            }
+            .fail();
+        }
+
+        close_span(pending, &segs, &curr_seg, &mut spans);
+        pending = curr_seg
+            .as_ref()
+            .map(|x| (line_idx, segs.len(), x.next_address()));
+
+        let statement = expand_char_literals(statement)?;
+        let statement = statement.as_ref();
+        let lowered = statement.to_ascii_lowercase();
+
+        let mut line = lowered.as_str();
+        // same as `line`, but keeps the case; used by string directives
+        let mut line_raw = statement;
+
+        // any number of labels may lead the statement, all naming the same address
+        while let Some((label, rest)) = split_leading_label(line) {
+            let seg = curr_seg
+                .as_mut()
+                .ok_or_else(|| SegmentRequiredSnafu { line }.build())?;
+            seg.append_label(label);
+
+            let consumed = line.len() - rest.len();
+            line_raw = &line_raw[consumed..];
+            line = rest;
+        }
+
+        let mut tokens = RE_SEPARATOR.splitn(line, 2);
+
+        let first_token = match tokens.next() {
+            Some(x) => x,
+            None => continue,
+        };
+
+        if first_token.is_empty() {
+            continue;
+        }
+
+        let args_raw = normalize_operands(tokens.next().unwrap_or(""));
+        let args_raw = args_raw.as_ref();
+        let tokens = tokenize(split_operands(args_raw));
+
+        if first_token == ".text" || first_token == ".data" {
+            if let Some(x) = curr_seg {
+                if is_text_seg {
+                    next_text_addr = x.next_address();
+                } else {
+                    next_data_addr = x.next_address();
+                }
+
+                emitted += x.data.len();
+                segs.push(x);
+            }
+
+            // without an address, continue where the last segment of this type ended
+            let base_addr = if tokens.is_empty() {
+                if first_token == ".text" {
+                    next_text_addr
+                } else {
+                    next_data_addr
+                }
+            } else if tokens.len() == 1 {
+                // a base that was written but isn't an address must not fall back to the default
+                let addr = match tokens[0] {
+                    Token::Number { num, .. } if num >= 0 => num,
+                    _ => {
+                        return InvalidTokenSnafu {
+                            token: tokens[0].as_text(),
+                        }
+                        .fail()
+                    }
+                };
                 addr.try_into()
                     .map_err(|_| BaseAddressTooLargeSnafu { addr: addr as u64 }.build())?
             } else {
-                return InvalidNumberOfOperandsSnafu { line }.fail();
+                return InvalidNumberOfOperandsSnafu {
+                    mnemonic: first_token,
+                    expected: 1usize,
+                    actual: tokens.len(),
+                    line,
+                }
+                .fail();
             };
 
-            let seg_type = if first_token == ".text" {
-                is_text_seg = true;
-                Some(TEXT_SEGMENT)
-            } else if first_token == ".data" {
-                is_text_seg = false;
-                Some(DATA_SEGMENT)
+            is_text_seg = first_token == ".text";
+            let range = if is_text_seg {
+                &config.text_range
             } else {
-                None
+                &config.data_range
             };
 
-            if let Some(x) = seg_type {
-                if !x.contains(&base_addr) {
-                    return Err(BaseAddressOutOfRangeSnafu {
-                        addr: base_addr,
-                        range: x,
-                    }
-                    .build());
+            if !range.contains(&base_addr) {
+                return Err(BaseAddressOutOfRangeSnafu {
+                    addr: base_addr,
+                    range: range.clone(),
                 }
+                .build());
             }
 
             curr_seg = Some(Segment::new(base_addr, endian));
+        } else if first_token == ".equ" {
+            if tokens.len() != 2 {
+                return InvalidNumberOfOperandsSnafu {
+                    mnemonic: first_token,
+                    expected: 2usize,
+                    actual: tokens.len(),
+                    line,
+                }
+                .fail();
+            }
+
+            let value = eval_expr(tokens[1].as_text(), labels, &constants)?;
+            constants.insert(tokens[0].as_text().to_owned(), value);
         } else if first_token == ".globl" {
             if curr_seg.is_none() {
                 SegmentRequiredSnafu { line }.fail()?;
@@ -519,6 +1452,20 @@ fn parse(
             let seg = curr_seg
                 .as_mut()
                 .ok_or_else(|| SegmentRequiredSnafu { line }.build())?;
+            let start = seg.next_address();
+
+            if matches!(keyword, "word" | "byte" | "float" | "double") {
+                check_value_list(line_raw, &line[first_token.len()..])?;
+            }
+
+            let is_data = matches!(keyword, "byte" | "ascii" | "asciiz" | "float" | "double");
+            if config.strict_segments && is_text_seg && is_data {
+                return DataInTextSegmentSnafu {
+                    directive: first_token,
+                    line: line_raw,
+                }
+                .fail();
+            }
 
             match keyword {
                 "word" => {
@@ -533,16 +1480,14 @@ fn parse(
                 }
                 "ascii" => {
                     line_raw
-                        .trim()
                         .strip_prefix(".ascii")
                         .and_then(|x| x.trim().strip_prefix('"'))
                         .and_then(|x| x.strip_suffix('"'))
                         .map(|x| seg.append_bytes(x.as_bytes()))
-                        .ok_or_else(|| InvalidNumberOfOperandsSnafu { line: line_raw }.build())?;
+                        .ok_or_else(|| InvalidTokenSnafu { token: args_raw }.build())?;
                 }
                 "asciiz" => {
                     line_raw
-                        .trim()
                         .strip_prefix(".asciiz")
                         .and_then(|x| x.trim().strip_prefix('"'))
                         .and_then(|x| x.strip_suffix('"'))
@@ -550,24 +1495,37 @@ fn parse(
                             seg.append_bytes(x.as_bytes());
                             seg.append_u8(0);
                         })
-                        .ok_or_else(|| InvalidNumberOfOperandsSnafu { line: line_raw }.build())?;
+                        .ok_or_else(|| InvalidTokenSnafu { token: args_raw }.build())?;
                 }
                 "float" => {
                     for token in &tokens {
-                        let data: f32 = token.as_text().parse().map_err(|_| {
+                        let data: f32 = try_parse_float(token.as_text()).ok_or_else(|| {
+                            InvalidTokenSnafu {
+                                token: token.as_text(),
+                            }
+                            .build()
+                        })?;
+                        seg.append_u32(data.to_bits());
+                    }
+                }
+                "double" => {
+                    for token in &tokens {
+                        let data: f64 = try_parse_float(token.as_text()).ok_or_else(|| {
                             InvalidTokenSnafu {
                                 token: token.as_text(),
                             }
                             .build()
                         })?;
-                        let conv = data.to_bits();
-                        seg.append_u32(conv);
+                        seg.append_u64(data.to_bits());
                     }
                 }
                 "align" => {
                     if tokens.len() != 1 {
                         return InvalidNumberOfOperandsSnafu {
-                            line: line_raw.trim(),
+                            mnemonic: first_token,
+                            expected: 1usize,
+                            actual: tokens.len(),
+                            line: line_raw,
                         }
                         .fail();
                     }
@@ -575,24 +1533,23 @@ fn parse(
                     seg.zero_align(tokens[0].as_number()? as usize);
                 }
                 _ => {
-                    if let Some(label) = first_token.strip_suffix(':') {
-                        seg.append_label(label);
-                    } else {
-                        return InvalidTokenSnafu { token: first_token }.fail();
-                    }
+                    return InvalidTokenSnafu { token: first_token }.fail();
                 }
             }
-        } else if let Some(label) = first_token.strip_suffix(':') {
-            let seg = curr_seg
-                .as_mut()
-                .ok_or_else(|| SegmentRequiredSnafu { line }.build())?;
 
-            seg.append_label(label);
+            let end = seg.next_address();
+            if end != start {
+                data.push(start..end);
+            }
         } else {
             let seg = curr_seg
                 .as_mut()
                 .ok_or_else(|| SegmentRequiredSnafu { line }.build())?;
 
+            if config.strict_segments && !is_text_seg {
+                return InstructionInDataSegmentSnafu { line }.fail();
+            }
+
             let mut start_idx = 0;
             for token in &tokens {
                 if let Token::LabelDef { text } = token {
@@ -610,54 +1567,333 @@ fn parse(
                 line,
                 mnemonic: first_token,
                 args: &tokens[start_idx..],
-                args_raw,
                 pc: seg.next_address(),
+                gp: config.gp(),
                 labels,
+                constants: &constants,
             };
 
-            let ins = try_parse_ins(&mut ctx)?;
+            let ins = match try_parse_pseudo(&mut ctx)? {
+                Some(x) => x,
+                None => vec![try_parse_ins(&mut ctx)?],
+            };
 
-            seg.append_u32(ins.encode());
+            if !ctx.pc.is_multiple_of(4) {
+                return UnalignedInstructionSnafu { addr: ctx.pc }.fail();
+            }
+            for x in ins {
+                seg.append_u32(x.encode());
+                if config.fill_delay_slots && x.has_delay_slot() {
+                    seg.append_u32(Instruction::sll(Default::default()).encode());
+                }
+            }
         }
     }
 
+    close_span(pending, &segs, &curr_seg, &mut spans);
     if let Some(x) = curr_seg {
+        if emitted + x.data.len() > MAX_PROGRAM_BYTES {
+            return ProgramTooLargeSnafu {
+                max: MAX_PROGRAM_BYTES,
+            }
+            .fail();
+        }
+
+        if is_text_seg {
+            next_text_addr = x.next_address();
+        } else {
+            next_data_addr = x.next_address();
+        }
+
         segs.push(x);
     }
 
-    Ok(segs)
+    Ok(Parsed {
+        segments: segs,
+        global_labels,
+        spans,
+        data,
+        next: (next_text_addr, next_data_addr),
+    })
 }
 
-pub fn assemble(endian: EndianMode, asm: &str) -> Result<Vec<Segment>, AssemblerError> {
-    // assemble
-    let segments = parse(endian, asm, &None)?;
-
-    // collect labels
+fn collect_labels(segments: &[Segment]) -> HashMap<String, u32> {
     let mut labels = HashMap::new();
-    for seg in &segments {
+    for seg in segments {
         for (k, v) in seg.labels() {
             labels.insert(k.clone(), seg.base_addr + v);
         }
     }
+    labels
+}
+
+/// Only for use in unit tests
+#[cfg(test)]
+pub fn assemble(endian: EndianMode, asm: &str) -> Result<Vec<Segment>, AssemblerError> {
+    assemble_with(endian, asm, &AssemblerConfig::default()).map(|x| x.segments)
+}
+
+pub fn assemble_with(
+    endian: EndianMode,
+    asm: &str,
+    config: &AssemblerConfig,
+) -> Result<Assembly, AssemblerError> {
+    assemble_parsed(endian, asm, config).map(|(x, _)| x)
+}
+
+/// Assembles `asm` and lists, for every source line, the address and bytes it produced.
+pub fn assemble_listing(
+    endian: EndianMode,
+    asm: &str,
+    config: &AssemblerConfig,
+) -> Result<Vec<ListingLine>, AssemblerError> {
+    let (assembly, spans) = assemble_parsed(endian, asm, config)?;
+
+    let mut listing: Vec<ListingLine> = asm
+        .lines()
+        .map(|x| ListingLine {
+            addr: None,
+            bytes: Vec::new(),
+            source: x.to_owned(),
+        })
+        .collect();
+
+    for span in spans {
+        let seg = assembly
+            .segments
+            .iter()
+            .find(|x| x.contains(span.addr))
+            .expect("span should lie in a segment");
+        let offset = (span.addr - seg.base_addr) as usize;
+
+        let entry = &mut listing[span.line];
+        entry.addr.get_or_insert(span.addr);
+        entry
+            .bytes
+            .extend_from_slice(&seg.data[offset..offset + span.len as usize]);
+    }
+
+    Ok(listing)
+}
+
+fn assemble_parsed(
+    endian: EndianMode,
+    asm: &str,
+    config: &AssemblerConfig,
+) -> Result<(Assembly, Vec<Span>), AssemblerError> {
+    let start = (config.text_base, config.data_base);
+
+    // assemble
+    let Parsed { segments, .. } = parse(endian, asm, &None, config, start)?;
+
+    // collect labels
+    let labels = collect_labels(&segments);
 
     // reassemble with label
     drop(segments);
-    let segments = parse(endian, asm, &Some(labels))?;
+    let mut parsed = parse(endian, asm, &Some(labels.clone()), config, start)?;
 
-    // check overlap
-    for a in &segments {
-        for b in &segments {
-            if std::ptr::eq(a, b) {
-                continue;
-            }
+    // `la` and bare-label loads are sized by where their label is, which the first pass
+    // guessed. Data labels were right regardless, so one more pass settles the text.
+    let settled = collect_labels(&parsed.segments);
+    if settled != labels {
+        parsed = parse(endian, asm, &Some(settled), config, start)?;
+    }
+
+    let Parsed {
+        segments,
+        global_labels,
+        spans,
+        data,
+        ..
+    } = parsed;
+
+    check_overlap(&segments)?;
+
+    // resolve exported symbols
+    let labels = collect_labels(&segments);
+    let mut globals = HashMap::new();
+    for name in global_labels {
+        match labels.get(&name) {
+            Some(addr) => globals.insert(name, *addr),
+            None => return UndefinedLabelSnafu { label: name }.fail(),
+        };
+    }
+
+    let warnings = if config.lint {
+        lint(&text_words(endian, &segments, &spans, config))
+    } else {
+        Vec::new()
+    };
+
+    let assembly = Assembly {
+        segments,
+        globals,
+        labels,
+        warnings,
+        data,
+    };
+    Ok((assembly, spans))
+}
 
+/// Fails on the first two segments that overlap, reporting the lower one first.
+fn check_overlap(segments: &[Segment]) -> Result<(), AssemblerError> {
+    for (i, a) in segments.iter().enumerate() {
+        for b in &segments[i + 1..] {
             if a.overlaps_with(b) {
-                return SegmentOverlapSnafu {}.fail();
+                let (a, b) = if a.base_addr <= b.base_addr {
+                    (a.base_addr, b.base_addr)
+                } else {
+                    (b.base_addr, a.base_addr)
+                };
+                return SegmentOverlapSnafu { a, b }.fail();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// How many times [`assemble_units`] assembles every unit before giving up on the layout
+/// settling. Two passes find the labels; more only follow `la` and friends changing size.
+const MAX_UNIT_PASSES: usize = 4;
+
+/// Assembles several source files, `(name, source)`, into one program. Each unit is laid
+/// out after the previous one, and sees its own labels plus those exported with `.globl`
+/// by any unit. A unit's own label hides a global of the same name. Errors inside a unit
+/// name it. Lint warnings are not collected.
+pub fn assemble_units(
+    endian: EndianMode,
+    units: &[(&str, &str)],
+    config: &AssemblerConfig,
+) -> Result<Assembly, AssemblerError> {
+    let mut labels: Vec<Option<HashMap<String, u32>>> = vec![None; units.len()];
+    let mut pass = 1;
+
+    let (mut parsed, locals, globals) = loop {
+        let mut parsed = Vec::new();
+        let mut next = (config.text_base, config.data_base);
+        for (&(name, asm), labels) in units.iter().zip(&labels) {
+            let unit =
+                parse(endian, asm, labels, config, next).context(InUnitSnafu { unit: name })?;
+            next = unit.next;
+            parsed.push(unit);
+        }
+
+        let locals: Vec<_> = parsed.iter().map(|x| collect_labels(&x.segments)).collect();
+        let globals = link_globals(units, &parsed, &locals)?;
+        let linked: Vec<_> = locals
+            .iter()
+            .map(|x| {
+                let mut visible = globals.clone();
+                visible.extend(x.iter().map(|(k, v)| (k.clone(), *v)));
+                Some(visible)
+            })
+            .collect();
+
+        if linked == labels {
+            break (parsed, locals, globals);
+        }
+        if pass == MAX_UNIT_PASSES {
+            return LayoutNotSettledSnafu { passes: pass }.fail();
+        }
+        labels = linked;
+        pass += 1;
+    };
+
+    let segments: Vec<_> = parsed
+        .iter_mut()
+        .flat_map(|x| x.segments.drain(..))
+        .collect();
+    check_overlap(&segments)?;
+
+    let mut all_labels = globals.clone();
+    for x in locals {
+        for (k, v) in x {
+            all_labels.entry(k).or_insert(v);
+        }
+    }
+
+    Ok(Assembly {
+        segments,
+        globals,
+        labels: all_labels,
+        warnings: Vec::new(),
+        data: parsed.into_iter().flat_map(|x| x.data).collect(),
+    })
+}
+
+/// Every label a unit exports, with its address. A unit may also name a label defined by
+/// another unit in `.globl`.
+fn link_globals(
+    units: &[(&str, &str)],
+    parsed: &[Parsed],
+    locals: &[HashMap<String, u32>],
+) -> Result<HashMap<String, u32>, AssemblerError> {
+    let mut globals = HashMap::new();
+    let mut owners: HashMap<&str, &str> = HashMap::new();
+
+    for ((&(name, _), unit), locals) in units.iter().zip(parsed).zip(locals) {
+        for label in &unit.global_labels {
+            if let Some(&addr) = locals.get(label) {
+                if let Some(first) = owners.insert(label, name) {
+                    return DuplicateGlobalSnafu {
+                        label: label.as_str(),
+                        first,
+                        second: name,
+                    }
+                    .fail();
+                }
+                globals.insert(label.clone(), addr);
+            }
+        }
+    }
+
+    for (&(name, _), unit) in units.iter().zip(parsed) {
+        if let Some(label) = unit
+            .global_labels
+            .iter()
+            .find(|x| !globals.contains_key(*x))
+        {
+            return Err(UndefinedLabelSnafu {
+                label: label.as_str(),
             }
+            .build())
+            .context(InUnitSnafu { unit: name });
+        }
+    }
+
+    Ok(globals)
+}
+
+/// Every word emitted into text segments, as `(line, addr, instruction)` in address order.
+fn text_words(
+    endian: EndianMode,
+    segments: &[Segment],
+    spans: &[Span],
+    config: &AssemblerConfig,
+) -> Vec<(usize, u32, Instruction)> {
+    let mut words = Vec::new();
+
+    for span in spans {
+        let seg = segments
+            .iter()
+            .find(|x| x.contains(span.addr))
+            .expect("span should lie in a segment");
+        if !config.text_range.contains(&seg.base_addr) {
+            continue;
+        }
+
+        let offset = (span.addr - seg.base_addr) as usize;
+        let bytes = &seg.data[offset..offset + span.len as usize];
+        for (i, x) in bytes.chunks_exact(4).enumerate() {
+            let ins = Instruction::decode(endian.read_u32(x));
+            words.push((span.line, span.addr + i as u32 * 4, ins));
         }
     }
 
-    Ok(segments)
+    words.sort_by_key(|x| x.1);
+    words
 }
 
 #[cfg(test)]
@@ -702,6 +1938,31 @@ mod test {
         assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x02001022);
     }
 
+    #[test]
+    fn assemble_logical() {
+        let code = ".text\nnor $t0, $t1, $t2\nxor $t0, $t1, $t2";
+        let segs = assemble(*NE, code).unwrap();
+        assert_eq!(segs.len(), 1);
+        assert_eq!(segs[0].data.len(), 8);
+
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x012a4027);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x012a4026);
+    }
+
+    #[test]
+    fn assemble_endian() {
+        let code = ".data\n.word 0x01020304\n.text\naddi $t0, $t1, -4";
+
+        let segs = assemble(EndianMode::Little, code).unwrap();
+        assert_eq!(segs[0].data, [0x04, 0x03, 0x02, 0x01]);
+        assert_eq!(segs[1].data, [0xfc, 0xff, 0x28, 0x21]);
+
+        let segs = assemble(EndianMode::Big, code).unwrap();
+        assert_eq!(segs[0].data, [0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(segs[1].data, [0x21, 0x28, 0xff, 0xfc]);
+    }
+
     #[test]
     fn assemble_data() {
         let code = ".data\n.word 123, 0x123, 0o123\n.word 0xffffffff";
@@ -718,6 +1979,442 @@ mod test {
         assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0xffffffff);
     }
 
+    #[test]
+    fn assemble_strict_segments() {
+        let strict = AssemblerConfig {
+            strict_segments: true,
+            ..Default::default()
+        };
+
+        let err = assemble_with(*NE, ".data\nadd $t0, $t1, $t2", &strict).unwrap_err();
+        assert!(matches!(
+            err,
+            AssemblerError::InstructionInDataSegment { .. }
+        ));
+
+        let err = assemble_with(*NE, ".text\n.asciiz \"hi\"", &strict).unwrap_err();
+        assert!(matches!(err, AssemblerError::DataInTextSegment { .. }));
+
+        let code = ".text\nadd $t0, $t1, $t2\n.word 0\n.data\n.asciiz \"hi\"\n.word 1";
+        assert!(assemble_with(*NE, code, &strict).is_ok());
+    }
+
+    #[test]
+    fn assemble_lenient_segments() {
+        let code = ".data\nadd $t0, $t1, $t2\n.text\n.byte 1, 2, 3, 4\n.asciiz \"hi\"";
+        let segs = assemble(*NE, code).unwrap();
+        assert_eq!(segs[0].data.len(), 4);
+        assert_eq!(segs[1].data.len(), 7);
+    }
+
+    #[test]
+    fn assemble_unaligned_instruction() {
+        let err = assemble(*NE, ".text\n.byte 1\nadd $t0, $t1, $t2").unwrap_err();
+        if let AssemblerError::UnalignedInstruction { addr, .. } = err {
+            assert_eq!(addr, 0x00400025);
+        } else {
+            panic!("expected UnalignedInstruction, got {:?}", err);
+        }
+
+        let segs = assemble(*NE, ".text\n.byte 1\n.align 4\nadd $t0, $t1, $t2").unwrap();
+        assert_eq!(segs[0].data.len(), 8);
+    }
+
+    #[test]
+    fn assemble_multiple_labels() {
+        let code =
+            ".text\nnop\nstart:\nmain:  # entry\nfirst: second: add $t0, $t1, $t2\nloop: j loop";
+        let segs = assemble(*NE, code).unwrap();
+        assert_eq!(segs[0].data.len(), 12);
+
+        let labels = segs[0].labels();
+        for name in ["start", "main", "first", "second"] {
+            assert_eq!(labels[name], 4, "label {}", name);
+        }
+        assert_eq!(labels["loop"], 8);
+    }
+
+    #[test]
+    fn assemble_label_before_directive() {
+        let code = ".data\nmsg: .asciiz \"Hi\"\nnum: .word 7";
+        let segs = assemble(*NE, code).unwrap();
+        assert_eq!(&segs[0].data[..3], b"Hi\0");
+        assert_eq!(segs[0].labels()["msg"], 0);
+        assert_eq!(segs[0].labels()["num"], 3);
+    }
+
+    #[test]
+    fn assemble_globals() {
+        let code = ".text\n.globl main\nnop\nmain:\nnop\n.data\n.globl counter\ncounter: .word 0";
+        let asm = assemble_with(*NE, code, &Default::default()).unwrap();
+
+        assert_eq!(asm.globals.len(), 2);
+        assert_eq!(asm.globals["main"], 0x00400028);
+        assert_eq!(asm.globals["counter"], 0x10000000);
+
+        let err = assemble(*NE, ".text\n.globl main\nnop").unwrap_err();
+        assert!(matches!(err, AssemblerError::UndefinedLabel { .. }));
+    }
+
+    #[test]
+    fn assemble_comment_styles() {
+        let plain = ".text\nadd $t0, $t1, $t2\nsub $t3, $t4, $t5\n.data\n.asciiz \"a#b;c//d\"";
+        let commented = "# hash\n.text // slashes\nadd $t0, $t1, $t2 ; semicolon\n\
+            ; whole line\nsub $t3, $t4, $t5# tight\n// another\n\
+            .data\n.asciiz \"a#b;c//d\" // trailing";
+
+        let expected = assemble(*NE, plain).unwrap();
+        let segs = assemble(*NE, commented).unwrap();
+        assert_eq!(segs.len(), 2);
+        assert_eq!(segs[0].data, expected[0].data);
+        assert_eq!(segs[1].data, b"a#b;c//d\0");
+    }
+
+    #[test]
+    fn assemble_reenter_segment() {
+        let code = ".text\nnop\n.data\n.word 1\n.text\nafter: nop\n.data\n.word 2";
+        let asm = assemble_with(*NE, code, &Default::default()).unwrap();
+
+        // each block is its own segment, chained onto the previous one of its type
+        let bases: Vec<_> = asm.segments.iter().map(|x| x.base_addr).collect();
+        assert_eq!(bases, [0x00400024, 0x10000000, 0x00400028, 0x10000004]);
+        assert_eq!(asm.labels["after"], 0x00400028);
+
+        let mem = crate::memory::create_memory(*NE, &asm.segments);
+        assert_eq!(mem.read_u32(0x10000000), 1);
+        assert_eq!(mem.read_u32(0x10000004), 2);
+    }
+
+    #[test]
+    fn assemble_segment_overlap() {
+        let err = assemble(*NE, ".text 0x00400000\nnop\nnop\n.text 0x00400004\nnop").unwrap_err();
+        assert!(matches!(
+            err,
+            AssemblerError::SegmentOverlap {
+                a: 0x00400000,
+                b: 0x00400004,
+                ..
+            }
+        ));
+
+        // data growing into text
+        let config = AssemblerConfig {
+            data_range: 0x00400000..=0x7fffffff,
+            ..Default::default()
+        };
+        let code = ".data 0x00400020\n.word 1 2\n.text\nnop";
+        let err = assemble_with(*NE, code, &config).unwrap_err();
+        assert!(matches!(
+            err,
+            AssemblerError::SegmentOverlap {
+                a: 0x00400020,
+                b: 0x00400024,
+                ..
+            }
+        ));
+
+        assert!(assemble(*NE, ".text 0x00400000\nnop\n.text 0x00400004\nnop").is_ok());
+    }
+
+    #[test]
+    fn assemble_rept() {
+        let segs = assemble(*NE, ".data\n.rept 4\n.word 0\n.endr").unwrap();
+        assert_eq!(segs[0].data.len(), 16);
+
+        let code = ".data\n.rept 2\n.byte 1\n.rept 3\n.byte 2\n.endr\n.endr\n.byte 3";
+        let segs = assemble(*NE, code).unwrap();
+        assert_eq!(segs[0].data, [1, 2, 2, 2, 1, 2, 2, 2, 3]);
+
+        // few statements, but long ones
+        let long = format!(".ascii \"{}\"", "x".repeat(1024));
+        let one_segment = format!(".data\n.rept 20000\n{}\n.endr", long);
+        let many_segments = format!(".rept 20000\n.data\n{}\n.endr", long);
+
+        let err = |code| assemble(*NE, code).unwrap_err();
+        assert!(matches!(
+            err(".data\n.rept 2\nx: .word 0\n.endr"),
+            AssemblerError::LabelInRept { .. }
+        ));
+        assert!(matches!(
+            err(".data\n.rept 2\n.word 0"),
+            AssemblerError::UnterminatedRept { .. }
+        ));
+        assert!(matches!(
+            err(".data\n.endr"),
+            AssemblerError::UnmatchedEndr { .. }
+        ));
+        assert!(matches!(
+            err(".data\n.rept 1000\n.rept 1000\n.word 0\n.endr\n.endr"),
+            AssemblerError::ReptTooLarge { .. }
+        ));
+        assert!(matches!(
+            err(&one_segment),
+            AssemblerError::ProgramTooLarge { .. }
+        ));
+        assert!(matches!(
+            err(&many_segments),
+            AssemblerError::ProgramTooLarge { .. }
+        ));
+        assert!(matches!(
+            err(&".rept 1\n".repeat(9)),
+            AssemblerError::ReptTooDeep { .. }
+        ));
+    }
+
+    #[test]
+    fn assemble_rept_limits() {
+        // statements before the block count toward the limit too
+        let code = format!(".text\n{}.rept 1\nnop\n.endr", "nop\n".repeat(70000));
+        assert!(matches!(
+            assemble(*NE, &code).unwrap_err(),
+            AssemblerError::ReptTooLarge { .. }
+        ));
+
+        let segs = assemble(*NE, ".data\n.rept 4000000000\n.endr\n.word 1").unwrap();
+        assert_eq!(segs[0].data.len(), 4);
+    }
+
+    #[test]
+    fn assemble_lint() {
+        let config = AssemblerConfig {
+            lint: true,
+            ..Default::default()
+        };
+
+        let asm = assemble_with(*NE, ".text\nnop\nmove $t0, $t0\nnop", &config).unwrap();
+        assert_eq!(
+            asm.warnings,
+            [Warning {
+                line: 3,
+                message: "moving $t0 to itself has no effect".into()
+            }]
+        );
+
+        let code = ".text\nlw $t0, 0($gp)\nli $t0, 1\nbeq $t0, $t1, next\nnext: j end\nend: nop";
+        let asm = assemble_with(*NE, code, &config).unwrap();
+        let lines: Vec<_> = asm.warnings.iter().map(|x| x.line).collect();
+        assert_eq!(lines, [2, 4, 5]);
+
+        let code = ".text\nlw $t0, 0($gp)\naddi $t0, $t0, 1\nmove $t1, $t0\nbeq $t0, $t1, end\nnop\nend: nop";
+        assert!(assemble_with(*NE, code, &config)
+            .unwrap()
+            .warnings
+            .is_empty());
+
+        // off unless asked for
+        let asm = assemble_with(*NE, ".text\nmove $t0, $t0", &Default::default()).unwrap();
+        assert!(asm.warnings.is_empty());
+    }
+
+    #[test]
+    fn assemble_custom_layout() {
+        let config = AssemblerConfig {
+            text_base: 0x80000000,
+            data_base: 0x90000000,
+            text_range: 0x80000000..=0x8fffffff,
+            data_range: 0x90000000..=0x9fffffff,
+            ..Default::default()
+        };
+
+        let segs = assemble_with(*NE, ".text\nnop\n.data\n.word 1", &config)
+            .unwrap()
+            .segments;
+        assert_eq!(segs[0].base_addr, 0x80000000);
+        assert_eq!(segs[1].base_addr, 0x90000000);
+
+        let err = assemble_with(*NE, ".text 0x00400000\nnop", &config).unwrap_err();
+        assert!(matches!(
+            err,
+            AssemblerError::BaseAddressOutOfRange {
+                addr: 0x00400000,
+                ..
+            }
+        ));
+        assert!(assemble_with(*NE, ".data 0x88000000\n.word 1", &config).is_err());
+    }
+
+    #[test]
+    fn assemble_segment_base() {
+        let segs = assemble(*NE, ".text 0x00400100\nnop\n.data\n.word 1").unwrap();
+        assert_eq!(segs[0].base_addr, 0x00400100);
+        assert_eq!(segs[1].base_addr, 0x10000000);
+
+        for code in [
+            ".text 0xZZZ\nnop",
+            ".data -4\n.word 1",
+            ".data foo\n.word 1",
+        ] {
+            let err = assemble(*NE, code).unwrap_err();
+            assert!(
+                matches!(err, AssemblerError::InvalidToken { .. }),
+                "{}",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn data_ranges() {
+        let code = r"
+        .text
+        nop
+        .word 1 2
+        .data
+        .byte 1
+        .align 4
+        .text
+        nop";
+        let data = assemble_with(*NE, code, &AssemblerConfig::default())
+            .unwrap()
+            .data;
+        assert_eq!(
+            data,
+            [
+                0x00400028..0x00400030,
+                0x10000000..0x10000001,
+                0x10000001..0x10000004
+            ]
+        );
+    }
+
+    #[test]
+    fn error_messages() {
+        let message = |code| assemble(*NE, code).unwrap_err().to_string();
+
+        assert_eq!(
+            message(".text\naddu $t0, $t1"),
+            "`addu` takes 3 operand(s) but got 2 in line `addu $t0, $t1`"
+        );
+        assert_eq!(
+            message(".data\n.align 2 4"),
+            "`.align` takes 1 operand(s) but got 2 in line `.align 2 4`"
+        );
+        assert_eq!(
+            message(".text 0x10000000"),
+            "base address 0x10000000 is outside 0x00400000..=0x0fffffff"
+        );
+        assert_eq!(message(".text\nfoo $t0"), "unknown instruction `foo`");
+
+        // composes with `?` into the usual boxed error
+        let boxed = || -> Result<(), Box<dyn std::error::Error>> {
+            assemble(*NE, ".text\njr")?;
+            Ok(())
+        };
+        assert!(boxed().unwrap_err().to_string().starts_with("`jr` takes 1"));
+    }
+
+    #[test]
+    fn fp_registers() {
+        for i in 0..32u8 {
+            let name = format!("$f{}", i);
+            match tokenize(std::iter::once(name.as_str()))[0] {
+                Token::FpRegister { reg, .. } => assert_eq!(reg.num(), i),
+                ref x => panic!("{} gave `{}`", name, x.as_text()),
+            }
+        }
+
+        // `$4` is still the integer register
+        let tokens = tokenize(split_operands("$4, $f4"));
+        assert_eq!(tokens[0].as_register().unwrap(), RegisterName::new(4));
+        assert!(matches!(tokens[1], Token::FpRegister { .. }));
+
+        let err = assemble(
+            *NE,
+            ".text
+add $t0, $f4, $t1",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            AssemblerError::FpRegisterNotAllowed { reg: 4, .. }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "`$f4` is a floating-point register, but an integer one is expected"
+        );
+        // nor does it read as a label
+        assert!(matches!(
+            assemble(*NE, ".text\naddi $t0, $t0, $f1").unwrap_err(),
+            AssemblerError::TokenNotNumber { .. }
+        ));
+    }
+
+    #[test]
+    fn assemble_semicolon_statements() {
+        let config = AssemblerConfig {
+            semicolon_statements: true,
+            ..Default::default()
+        };
+
+        let code = ".text\nadd $t0, $t1, $t2; sub $t3, $t4, $t5 # ; nop\n\
+            loop: nop ;; j loop\n.data; .asciiz \"a;b\"";
+        let segs = assemble_with(*NE, code, &config).unwrap().segments;
+
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x012a4020);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x018d5822);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x00000000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x0810000b);
+        assert_eq!(segs[0].data.len(), 16);
+        assert_eq!(segs[0].labels()["loop"], 8);
+        assert_eq!(segs[1].data, b"a;b\0");
+
+        // without the flag, `;` starts a comment
+        let segs = assemble(*NE, ".text\nadd $t0, $t1, $t2; sub $t3, $t4, $t5").unwrap();
+        assert_eq!(segs[0].data.len(), 4);
+    }
+
+    #[test]
+    fn mnemonics_assemble() {
+        let mnemonics = supported_mnemonics();
+        for x in &mnemonics {
+            // without operands most fail, but none as unknown
+            let result = assemble(*NE, &format!(".text\n{}", x));
+            assert!(
+                !matches!(result, Err(AssemblerError::UnknownInstruction { .. })),
+                "{}",
+                x
+            );
+        }
+
+        assert!(mnemonics.windows(2).all(|x| x[0] < x[1]));
+        for x in ["li", "la", "lw", "div.s", "nop"] {
+            assert!(mnemonics.contains(&x), "{}", x);
+        }
+    }
+
+    #[test]
+    fn assemble_fp_arith() {
+        let code = ".text\nadd.s $f1, $f1, $f2\nsub.s $f30, $f15, $f31\ndiv.s $f4, $f4, $f6";
+        let segs = assemble(*NE, code).unwrap();
+
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x46020840);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x461f7f81);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x46062103);
+
+        let code = ".text\nlwc1 $f2, 4($t0)\nswc1 $f4, -4($sp)";
+        let segs = assemble(*NE, code).unwrap();
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0xc5020004);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0xe7a4fffc);
+
+        let segs = assemble(*NE, ".text\nmfc1 $t0, $f5\nmtc1 $t1, $f6").unwrap();
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x44082800);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x44893000);
+
+        let err = assemble(*NE, ".text\nmul.s $f0, $t0, $f1").unwrap_err();
+        assert!(matches!(
+            err,
+            AssemblerError::IntRegisterNotAllowed { reg: 8, .. }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "`$8` is an integer register, but a floating-point one is expected"
+        );
+    }
+
     #[test]
     fn assemble_memory() {
         let code = ".text\nlw $3, 1234($5)\nsw $s1, -12($gp)\nlw $7, 0x7fff($4)";
@@ -738,7 +2435,7 @@ mod test {
         let code = ".text\nlw $7, 0x8000($4)";
         let result = assemble(*NE, code);
         assert!(result.is_err());
-        if let AssemblerError::ImmediateTooLarge { .. } = result.unwrap_err() {
+        if let AssemblerError::ImmediateOutOfRange { .. } = result.unwrap_err() {
             // ok
         } else {
             panic!("expected OffsetTooLarge error");
@@ -841,21 +2538,61 @@ mod test {
     fn assemble_imm_fails() {
         let err1 = assemble(*NE, ".text\naddi $0, $0, 32768").expect_err("must result in error");
 
-        if let AssemblerError::ImmediateTooLarge { .. } = &err1 {
+        if let AssemblerError::ImmediateOutOfRange { .. } = &err1 {
             // ok
         } else {
-            panic!("expected ImmediateTooLarge, got {:?}", err1);
+            panic!("expected ImmediateOutOfRange, got {:?}", err1);
         }
 
         let err2 = assemble(*NE, ".text\naddi $0, $0, -32769").expect_err("must result in error");
 
-        if let AssemblerError::ImmediateTooLarge { .. } = &err2 {
+        if let AssemblerError::ImmediateOutOfRange { .. } = &err2 {
             // ok
         } else {
-            panic!("expected ImmediateTooLarge, got {:?}", err2);
+            panic!("expected ImmediateOutOfRange, got {:?}", err2);
         }
     }
 
+    #[test]
+    fn assemble_imm_ranges() {
+        let expect_range = |line: &str, expect_min: i64, expect_max: i64| {
+            let err = assemble(*NE, &format!(".text\n{}", line)).expect_err(line);
+            match err {
+                AssemblerError::ImmediateOutOfRange { min, max, .. } => {
+                    assert_eq!((min, max), (expect_min, expect_max), "{}", line);
+                }
+                _ => panic!("expected ImmediateOutOfRange for `{}`, got {:?}", line, err),
+            }
+        };
+
+        // arithmetic: signed
+        assert!(assemble(*NE, ".text\naddi $t0, $t1, -32768\nslti $t0, $t1, 32767").is_ok());
+        expect_range("addi $t0, $t1, 70000", -32768, 32767);
+        expect_range("addiu $t0, $t1, 32768", -32768, 32767);
+        expect_range("slti $t0, $t1, -32769", -32768, 32767);
+
+        // logical: unsigned
+        assert!(assemble(*NE, ".text\nandi $t0, $t1, 0\nori $t0, $t1, 65535").is_ok());
+        assert!(assemble(*NE, ".text\nxori $t0, $t1, 65535\nlui $t0, 65535").is_ok());
+        expect_range("andi $t0, $t1, -1", 0, 65535);
+        expect_range("ori $t0, $t1, 65536", 0, 65535);
+        expect_range("lui $t0, 65536", 0, 65535);
+
+        // memory offsets: signed
+        assert!(assemble(*NE, ".text\nlw $t0, -32768($t1)\nsw $t0, 32767($t1)").is_ok());
+        expect_range("lw $t0, -32769($t1)", -32768, 32767);
+
+        // branches: signed word offset
+        assert!(assemble(*NE, ".text\nbne $t0, $t1, -131072\nbgez $t0, 131068").is_ok());
+        expect_range("bne $t0, $t1, -131076", -32768, 32767);
+        expect_range("bgez $t0, 131072", -32768, 32767);
+
+        // shift amounts
+        assert!(assemble(*NE, ".text\nsll $t0, $t1, 0\nsra $t0, $t1, 31").is_ok());
+        expect_range("srl $t0, $t1, 32", 0, 31);
+        expect_range("sll $t0, $t1, -1", 0, 31);
+    }
+
     #[test]
     fn assemble_shifts() {
         let code = r"
@@ -920,6 +2657,321 @@ mod test {
         assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x1ba0fff5);
     }
 
+    #[test]
+    fn assemble_branch_always() {
+        let pseudo = assemble(*NE, ".text\nloop:\nb loop\nbal loop").unwrap();
+        let plain = assemble(
+            *NE,
+            ".text\nloop:\nbeq $zero, $zero, loop\nbgezal $zero, loop",
+        )
+        .unwrap();
+        assert_eq!(pseudo[0].data, plain[0].data);
+
+        assert!(assemble(*NE, ".text\nloop:\nb $zero, loop").is_err());
+    }
+
+    #[test]
+    fn assemble_li() {
+        let code = ".text\nli $t0, -4\nli $t0, 0xffff\nli $t0, 0x12345678";
+        let segs = assemble(*NE, code).unwrap();
+
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x2408fffc);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x3408ffff);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x3c081234);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x35085678);
+        assert_eq!(data.position(), segs[0].data.len() as u64);
+
+        assert!(assemble(*NE, ".text\nli $t0, 0x100000000").is_err());
+    }
+
+    #[test]
+    fn assemble_not_neg_abs() {
+        let code = ".text\nnot $t0, $t1\nneg $t0, $t1\nabs $t0, $t1\nafter: nop";
+        let asm = assemble_with(*NE, code, &Default::default()).unwrap();
+
+        let mut data = Cursor::new(&asm.segments[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x01204027);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x00094022);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x00090fc3);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x01214026);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x01014023);
+        assert_eq!(asm.labels["after"], 0x00400024 + 20);
+    }
+
+    #[test]
+    fn assemble_irregular_whitespace() {
+        let pairs = [
+            ("add\t$t0 ,\t$t1, $t2", "add $t0, $t1, $t2"),
+            ("addi  $t0,$t1 ,\t-1", "addi $t0, $t1, -1"),
+            ("lw $t0 , 8($t1)", "lw $t0, 8($t1)"),
+            ("sw\t$t0,\t-4 ( $sp )", "sw $t0, -4($sp)"),
+            ("lw $t0, lbl + 4 ($zero)", "lw $t0, lbl+4($zero)"),
+        ];
+
+        for (irregular, regular) in pairs {
+            let a = assemble(*NE, &format!(".text\nlbl: {}", irregular)).unwrap();
+            let b = assemble(*NE, &format!(".text\nlbl: {}", regular)).unwrap();
+            assert_eq!(a[0].data, b[0].data, "{}", irregular);
+        }
+    }
+
+    #[test]
+    fn assemble_empty_operand() {
+        let cases = [(".word 1,,2", 2), (".word ,1", 1), (".byte 1, 2,", 3)];
+        for (code, index) in cases {
+            let err = assemble(*NE, &format!(".data\n{}", code)).unwrap_err();
+            assert!(
+                matches!(err, AssemblerError::EmptyOperand { index: x, .. } if x == index),
+                "{}: {}",
+                code,
+                err
+            );
+        }
+
+        assemble(*NE, ".data\n.word 1, 2 3\n.word").unwrap();
+    }
+
+    #[test]
+    fn assemble_clz_clo() {
+        let segs = assemble(*NE, ".text\nclz $s0, $t0\nclo $s0, $t0").unwrap();
+
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x71108020);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x71108021);
+    }
+
+    #[test]
+    fn assemble_units_link_globals() {
+        let a = r"
+        .text
+        .globl main
+        main: jal helper
+        la $t0, shared
+        lw $t1, shared
+        loop: j loop";
+        let b = r"
+        .text
+        .globl helper
+        helper: jr $ra
+        loop: nop
+        .data
+        .globl shared
+        shared: .word 7";
+        let config = AssemblerConfig::default();
+        let assembly = assemble_units(*NE, &[("a.s", a), ("b.s", b)], &config).unwrap();
+
+        assert_eq!(assembly.globals["main"], 0x00400024);
+        assert_eq!(assembly.globals["helper"], 0x00400034);
+        assert_eq!(assembly.globals["shared"], 0x10000000);
+        // a's own `loop` wins over b's
+        assert_eq!(assembly.labels["loop"], 0x00400030);
+
+        let mut data = Cursor::new(&assembly.segments[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x0c10000d);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x27888000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x8f898000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x0810000c);
+    }
+
+    #[test]
+    fn assemble_units_errors() {
+        let config = AssemblerConfig::default();
+        let def = ".text\n.globl f\nf: nop";
+
+        let err = assemble_units(*NE, &[("a.s", def), ("b.s", def)], &config).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "global `f` is defined in both `a.s` and `b.s`"
+        );
+
+        let err = assemble_units(*NE, &[("a.s", def), ("b.s", ".text\nj g")], &config).unwrap_err();
+        assert_eq!(err.to_string(), "in `b.s`: label `g` was not found");
+
+        // not exported, so the other unit can't see it
+        let local = ".text\ng: nop";
+        assert!(assemble_units(*NE, &[("a.s", local), ("b.s", ".text\nj g")], &config).is_err());
+    }
+
+    #[test]
+    fn assemble_fill_delay_slots() {
+        let config = AssemblerConfig {
+            fill_delay_slots: true,
+            ..Default::default()
+        };
+        let code = r"
+        .text
+        beq $t0, $t1, end
+        addi $t0, $t0, 1
+        end: jr $ra";
+        let assembly = assemble_with(*NE, code, &config).unwrap();
+        assert_eq!(assembly.labels["end"], 0x00400030);
+
+        let mut data = Cursor::new(&assembly.segments[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x11090002);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x00000000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x21080001);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x03e00008);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x00000000);
+        assert_eq!(data.position(), assembly.segments[0].data.len() as u64);
+    }
+
+    #[test]
+    fn assemble_sync_pref() {
+        let segs = assemble(
+            *NE,
+            ".text\nsync\nsync 16\npref 0, 4($sp)\npref 30, -8($t0)",
+        )
+        .unwrap();
+
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x0000000f);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x0000040f);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0xcfa00004);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0xcd1efff8);
+
+        assert!(assemble(*NE, ".text\npref 32, 0($sp)").is_err());
+    }
+
+    #[test]
+    fn assemble_label_offset() {
+        let code = r"
+        .data
+        pad: .word 0
+        array: .word 1 2
+        .text
+        lw $t0, array+4($zero)
+        sw $t0, array - 4($gp)";
+        let segs = assemble(*NE, code).unwrap();
+
+        let mut data = Cursor::new(&segs[1].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x3c011000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x00200821);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x8c280008);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x3c011000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x003c0821);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0xac280000);
+        assert_eq!(data.position(), segs[1].data.len() as u64);
+    }
+
+    #[test]
+    fn assemble_gp_relative() {
+        let code = r"
+        .text
+        lw $t0, x
+        la $t1, x
+        sw $t0, far
+        la $t2, far
+        .data
+        x: .word 42
+        .data 0x10020000
+        far: .word 0";
+        let segs = assemble(*NE, code).unwrap();
+
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x8f888000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x27898000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x3c011002);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0xac280000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x3c0a1002);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x354a0000);
+        assert_eq!(data.position(), segs[0].data.len() as u64);
+    }
+
+    #[test]
+    fn assemble_expressions() {
+        let code = r"
+        .equ four, 4
+        .text
+        start: li $t0, end - start
+        lw $t1, four+4($t2)
+        addi $t1, $t1, -four-1
+        end:";
+        let segs = assemble(*NE, code).unwrap();
+
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x3c080000);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x35080010);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x8d490008);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x2129fffb);
+        assert_eq!(data.position(), segs[0].data.len() as u64);
+
+        assert!(assemble(*NE, ".text\nli $t0, nowhere+4").is_err());
+        assert!(assemble(*NE, ".text\nli $t0, 4+").is_err());
+    }
+
+    #[test]
+    fn assemble_char_literals() {
+        let code = r"
+        .data
+        .byte 'A', 10, '\n', '0' ' ' ',' '#' '\'' '\\' '\0'
+        .text
+        li $t0, 'Z'
+        addi $t0, $t0, '\t'";
+        let segs = assemble(*NE, code).unwrap();
+
+        assert_eq!(segs[0].data, b"A\n\n0 ,#'\\\0");
+
+        let mut data = Cursor::new(&segs[1].data);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x2408005a);
+        assert_eq!(data.read_u32::<NativeEndian>().unwrap(), 0x21080009);
+
+        // strings keep their quotes
+        let segs = assemble(*NE, ".data\n.ascii \"it's\"").unwrap();
+        assert_eq!(segs[0].data, b"it's");
+
+        for bad in [".byte 'ab'", ".byte ''", ".byte 'a", ".byte '\\q'"] {
+            let err = assemble(*NE, &format!(".data\n{}", bad)).expect_err(bad);
+            assert!(
+                matches!(err, AssemblerError::InvalidCharLiteral { .. }),
+                "{}: {:?}",
+                bad,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn assemble_listing_lines() {
+        let code = ".text\nstart: li $t0, 0x12345678 # big\n\n.data\n.word 1 2";
+        let listing = assemble_listing(EndianMode::Big, code, &Default::default()).unwrap();
+        assert_eq!(listing.len(), 5);
+
+        assert_eq!(listing[0].addr, None);
+        assert_eq!(listing[1].addr, Some(0x00400024));
+        assert_eq!(
+            listing[1].bytes,
+            [0x3c, 0x08, 0x12, 0x34, 0x35, 0x08, 0x56, 0x78]
+        );
+        assert_eq!(listing[1].source, "start: li $t0, 0x12345678 # big");
+        assert_eq!(listing[2].addr, None);
+        assert!(listing[3].bytes.is_empty());
+        assert_eq!(listing[4].addr, Some(0x10000000));
+        assert_eq!(listing[4].bytes, [0, 0, 0, 1, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn assemble_float_double() {
+        let code = ".data\n.float 1.0 -2.5e-1 inf\n.double 1.0 nan";
+
+        let segs = assemble(EndianMode::Big, code).unwrap();
+        assert_eq!(segs[0].data.len(), 28);
+        let mut data = Cursor::new(&segs[0].data);
+        assert_eq!(data.read_u32::<BigEndian>().unwrap(), 0x3f800000);
+        assert_eq!(data.read_u32::<BigEndian>().unwrap(), 0xbe800000);
+        assert_eq!(data.read_u32::<BigEndian>().unwrap(), 0x7f800000);
+        assert_eq!(data.read_u64::<BigEndian>().unwrap(), 0x3ff0000000000000);
+        assert!(f64::from_bits(data.read_u64::<BigEndian>().unwrap()).is_nan());
+
+        let segs = assemble(EndianMode::Little, code).unwrap();
+        assert_eq!(segs[0].data[..4], [0x00, 0x00, 0x80, 0x3f]);
+        assert_eq!(segs[0].data[12..20], [0, 0, 0, 0, 0, 0, 0xf0, 0x3f]);
+
+        assert!(assemble(*NE, ".data\n.float 1e39").is_err());
+        assert!(assemble(*NE, ".data\n.double infinity").is_err());
+        assert!(assemble(*NE, ".data\n.double 1.0.0").is_err());
+    }
+
     #[test]
     fn extra_data_directives() {
         let code = r#"
@@ -0,0 +1,59 @@
+use super::assemble::{DATA_SEGMENT, TEXT_SEGMENT};
+use std::ops::RangeInclusive;
+
+/// Options for [`assemble_with`](super::assemble_with).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AssemblerConfig {
+    /// Rejects instructions in `.data`, and byte, string or float data in `.text`.
+    /// `.word` stays allowed in `.text`, since it is how raw instructions are written.
+    pub strict_segments: bool,
+    /// Treats `;` as a statement separator instead of a comment, so one line can hold
+    /// several statements. `#` and `//` still start comments, even after a `;`.
+    pub semicolon_statements: bool,
+    /// Looks for redundant code, like a register moved to itself, and reports it in
+    /// [`Assembly::warnings`](super::Assembly::warnings). Never fails assembly.
+    pub lint: bool,
+    /// Puts a `nop` after every branch and jump, so programs behave the same with or
+    /// without delay slots. Labels after them move to make room.
+    pub fill_delay_slots: bool,
+    /// Where the first `.text` without an address starts.
+    pub text_base: u32,
+    /// Where the first `.data` without an address starts.
+    pub data_base: u32,
+    /// Addresses a text segment may start at.
+    pub text_range: RangeInclusive<u32>,
+    /// Addresses a data segment may start at.
+    pub data_range: RangeInclusive<u32>,
+}
+
+impl AssemblerConfig {
+    /// The `$gp` programs start with, 32KiB into the data segment. Signed 16-bit offsets
+    /// from it reach `data_base..data_base + 0x10000`, where `la` and loads or stores of a
+    /// bare label take one instruction instead of two.
+    pub fn gp(&self) -> u32 {
+        self.data_base.wrapping_add(0x8000)
+    }
+
+    /// Everything from the lowest text address to the highest data one, stack included.
+    /// Loads and stores outside of it are address errors.
+    pub fn user_space(&self) -> RangeInclusive<u32> {
+        let start = u32::min(*self.text_range.start(), *self.data_range.start());
+        let end = u32::max(*self.text_range.end(), *self.data_range.end());
+        start..=end
+    }
+}
+
+impl Default for AssemblerConfig {
+    fn default() -> Self {
+        AssemblerConfig {
+            strict_segments: false,
+            semicolon_statements: false,
+            lint: false,
+            fill_delay_slots: false,
+            text_base: 0x00400024,
+            data_base: 0x10000000,
+            text_range: TEXT_SEGMENT,
+            data_range: DATA_SEGMENT,
+        }
+    }
+}
@@ -15,16 +15,32 @@ pub enum AssemblerError {
     #[snafu(display("expected number but got token `{token}`"))]
     TokenNotNumber { token: String, backtrace: Backtrace },
 
+    #[snafu(display("invalid character literal `{literal}` (expected exactly one character)"))]
+    InvalidCharLiteral {
+        literal: String,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("expected register but got token `{token}`"))]
     TokenNotRegister { token: String, backtrace: Backtrace },
 
+    #[snafu(display("`$f{reg}` is a floating-point register, but an integer one is expected"))]
+    FpRegisterNotAllowed { reg: u8, backtrace: Backtrace },
+
+    #[snafu(display("`${reg}` is an integer register, but a floating-point one is expected"))]
+    IntRegisterNotAllowed { reg: u8, backtrace: Backtrace },
+
     #[snafu(display("invalid token `{token}`"))]
     InvalidToken { token: String, backtrace: Backtrace },
 
     #[snafu(display("segment declaration required for line `{line}`"))]
     SegmentRequired { line: String, backtrace: Backtrace },
 
-    #[snafu(display("base address `{addr}` out of expected range `{range:?}`"))]
+    #[snafu(display(
+        "base address 0x{addr:08x} is outside 0x{:08x}..=0x{:08x}",
+        range.start(),
+        range.end()
+    ))]
     BaseAddressOutOfRange {
         addr: u32,
         range: RangeInclusive<u32>,
@@ -40,15 +56,57 @@ pub enum AssemblerError {
     #[snafu(display("invalid register name `{reg}`"))]
     InvalidRegisterName { reg: String, backtrace: Backtrace },
 
-    #[snafu(display("invalid number of operands in line `{line}`"))]
-    InvalidNumberOfOperands { line: String, backtrace: Backtrace },
+    #[snafu(display("instruction in data segment: `{line}`"))]
+    InstructionInDataSegment { line: String, backtrace: Backtrace },
 
-    #[snafu(display("segment address overlaps"))]
-    SegmentOverlap { backtrace: Backtrace },
+    #[snafu(display("`{directive}` is not allowed in text segment: `{line}`"))]
+    DataInTextSegment {
+        directive: String,
+        line: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "instruction at 0x{addr:08x} is not word-aligned (add `.align 4` before it)"
+    ))]
+    UnalignedInstruction { addr: u32, backtrace: Backtrace },
+
+    #[snafu(display(
+        "`{mnemonic}` takes {expected} operand(s) but got {actual} in line `{line}`"
+    ))]
+    InvalidNumberOfOperands {
+        mnemonic: String,
+        expected: usize,
+        actual: usize,
+        line: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("operand {index} is empty in line `{line}`"))]
+    EmptyOperand {
+        index: usize,
+        line: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("segment at `{a:#010x}` overlaps segment at `{b:#010x}`"))]
+    SegmentOverlap {
+        a: u32,
+        b: u32,
+        backtrace: Backtrace,
+    },
 
     #[snafu(display("immediate {imm} is too large to encode"))]
     ImmediateTooLarge { imm: i64, backtrace: Backtrace },
 
+    #[snafu(display("immediate {value} is out of range {min}..={max}"))]
+    ImmediateOutOfRange {
+        value: i64,
+        min: i64,
+        max: i64,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("branch offset {offset} is unaligned"))]
     BranchOffsetUnaligned { offset: i64, backtrace: Backtrace },
 
@@ -62,6 +120,52 @@ pub enum AssemblerError {
     #[snafu(display("jump target 0x{target:08x} is unaligned"))]
     JumpTargetUnaligned { target: u32, backtrace: Backtrace },
 
+    #[snafu(display("`{line}` has no matching `.endr`"))]
+    UnterminatedRept { line: String, backtrace: Backtrace },
+
+    #[snafu(display("`.endr` without a matching `.rept`"))]
+    UnmatchedEndr { backtrace: Backtrace },
+
+    #[snafu(display("`.rept` blocks nest deeper than {max}"))]
+    ReptTooDeep { max: usize, backtrace: Backtrace },
+
+    #[snafu(display("`.rept` blocks expand to more than {max} statements"))]
+    ReptTooLarge { max: usize, backtrace: Backtrace },
+
+    #[snafu(display("program assembles to more than {max} bytes"))]
+    ProgramTooLarge { max: usize, backtrace: Backtrace },
+
+    #[snafu(display("segments are {gap} bytes apart, more than the {max} a flat image fills"))]
+    ImageGapTooLarge {
+        gap: u64,
+        max: u64,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("label `{label}` inside `.rept` would be defined more than once"))]
+    LabelInRept { label: String, backtrace: Backtrace },
+
+    #[snafu(display("`.globl` label `{label}` is never defined"))]
+    UndefinedLabel { label: String, backtrace: Backtrace },
+
     #[snafu(display("label `{label}` was not found"))]
     LabelNotFound { label: String, backtrace: Backtrace },
+
+    #[snafu(display("global `{label}` is defined in both `{first}` and `{second}`"))]
+    DuplicateGlobal {
+        label: String,
+        first: String,
+        second: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("label addresses still move after {passes} passes over the units"))]
+    LayoutNotSettled { passes: usize, backtrace: Backtrace },
+
+    #[snafu(display("in `{unit}`: {source}"))]
+    InUnit {
+        unit: String,
+        #[snafu(source(from(AssemblerError, Box::new)))]
+        source: Box<AssemblerError>,
+    },
 }
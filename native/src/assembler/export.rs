@@ -0,0 +1,209 @@
+use crate::assembler::error::{AssemblerError, ImageGapTooLargeSnafu};
+use crate::memory::{Crc32, Segment};
+
+/// Largest gap between segments that [`segments_to_binary`] fills with zeros. The default
+/// text and data segments are far further apart; Intel HEX suits such layouts.
+pub const MAX_IMAGE_GAP: u64 = 1 << 20;
+
+/// Lays out `segments` as one contiguous image starting at the lowest base
+/// address. Gaps between segments are zero-filled, and fail past `MAX_IMAGE_GAP`.
+pub fn segments_to_binary(segments: &[Segment]) -> Result<Vec<u8>, AssemblerError> {
+    let mut used: Vec<&Segment> = segments.iter().filter(|x| !x.data.is_empty()).collect();
+    used.sort_by_key(|x| x.base_addr);
+
+    let base = match used.first() {
+        Some(x) => x.base_addr as u64,
+        None => return Ok(vec![]),
+    };
+
+    let mut end = base;
+    for seg in &used {
+        let gap = (seg.base_addr as u64).saturating_sub(end);
+        if gap > MAX_IMAGE_GAP {
+            return ImageGapTooLargeSnafu {
+                gap,
+                max: MAX_IMAGE_GAP,
+            }
+            .fail();
+        }
+        end = end.max(seg.base_addr as u64 + seg.data.len() as u64);
+    }
+
+    let mut image = vec![0; (end - base) as usize];
+    for seg in used {
+        let offset = (seg.base_addr as u64 - base) as usize;
+        image[offset..offset + seg.data.len()].copy_from_slice(&seg.data);
+    }
+    Ok(image)
+}
+
+/// CRC-32 of the image [`segments_to_binary`] makes, without making it. Segments must not
+/// overlap.
+pub fn segments_checksum(segments: &[Segment]) -> u32 {
+    let mut used: Vec<&Segment> = segments.iter().filter(|x| !x.data.is_empty()).collect();
+    used.sort_by_key(|x| x.base_addr);
+
+    let mut crc = Crc32::new();
+    let mut next = used.first().map_or(0, |x| x.base_addr as u64);
+    for seg in used {
+        crc.update_zeros(seg.base_addr as u64 - next);
+        crc.update(&seg.data);
+        next = seg.base_addr as u64 + seg.data.len() as u64;
+    }
+    crc.finish()
+}
+
+const HEX_RECORD_LEN: usize = 16;
+
+/// Encodes `segments` as Intel HEX. Each segment is written at its own address, with an
+/// extended linear address record whenever the upper 16 bits change.
+pub fn to_intel_hex(segments: &[Segment]) -> String {
+    let mut out = String::new();
+    let mut upper = 0;
+
+    for seg in segments {
+        let mut addr = seg.base_addr as u64;
+        let mut rest = seg.data.as_slice();
+
+        while !rest.is_empty() {
+            if (addr >> 16) != upper {
+                upper = addr >> 16;
+                push_hex_record(&mut out, 0, 0x04, &(upper as u16).to_be_bytes());
+            }
+
+            // a record must not cross a 64KiB boundary
+            let room = 0x10000 - (addr & 0xffff) as usize;
+            let len = rest.len().min(HEX_RECORD_LEN).min(room);
+            push_hex_record(&mut out, addr as u16, 0x00, &rest[..len]);
+
+            addr += len as u64;
+            rest = &rest[len..];
+        }
+    }
+
+    push_hex_record(&mut out, 0, 0x01, &[]);
+    out
+}
+
+fn push_hex_record(out: &mut String, addr: u16, kind: u8, data: &[u8]) {
+    let mut bytes = vec![data.len() as u8];
+    bytes.extend_from_slice(&addr.to_be_bytes());
+    bytes.push(kind);
+    bytes.extend_from_slice(data);
+
+    let checksum = bytes.iter().fold(0u8, |acc, x| acc.wrapping_add(*x));
+    bytes.push(checksum.wrapping_neg());
+
+    out.push(':');
+    for x in bytes {
+        out.push_str(&format!("{:02X}", x));
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::memory::{crc32, EndianMode};
+
+    #[test]
+    fn binary_with_gap() {
+        let mut text = Segment::new(0x1000, EndianMode::Big);
+        text.append_u32(0x11223344);
+        let mut data = Segment::new(0x100c, EndianMode::Big);
+        data.append_bytes(&[0xaa, 0xbb]);
+
+        let image = segments_to_binary(&[data, text]).unwrap();
+        assert_eq!(
+            image,
+            [0x11, 0x22, 0x33, 0x44, 0, 0, 0, 0, 0, 0, 0, 0, 0xaa, 0xbb]
+        );
+    }
+
+    #[test]
+    fn binary_default_layout() {
+        let segs = assemble(EndianMode::Big, ".data\n.word 1\n.text\nnop").unwrap();
+
+        // text and data are about 252MB apart
+        assert!(matches!(
+            segments_to_binary(&segs).unwrap_err(),
+            AssemblerError::ImageGapTooLarge { .. }
+        ));
+
+        let text: Vec<_> = segs
+            .into_iter()
+            .filter(|x| x.base_addr < 0x1000_0000)
+            .collect();
+        assert_eq!(segments_to_binary(&text).unwrap(), [0; 4]);
+    }
+
+    #[test]
+    fn checksum_with_gap() {
+        let mut text = Segment::new(0x1000, EndianMode::Big);
+        text.append_u32(0x11223344);
+        let mut data = Segment::new(0x100c, EndianMode::Big);
+        data.append_bytes(&[0xaa, 0xbb]);
+        let segs = [data, text, Segment::new(0x2000, EndianMode::Big)];
+
+        assert_eq!(
+            segments_checksum(&segs),
+            crc32(&segments_to_binary(&segs).unwrap())
+        );
+        assert_eq!(segments_checksum(&[]), crc32(&[]));
+    }
+
+    #[test]
+    fn hex_checksum() {
+        let mut seg = Segment::new(0x00400000, EndianMode::Big);
+        seg.append_u32(0x20080001);
+
+        assert_eq!(
+            to_intel_hex(&[seg]),
+            ":020000040040BA\n:0400000020080001D3\n:00000001FF\n"
+        );
+    }
+
+    #[test]
+    fn hex_crosses_64k() {
+        let mut seg = Segment::new(0x0000fffc, EndianMode::Big);
+        seg.append_bytes(&[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        let hex = to_intel_hex(&[seg]);
+
+        // parse it back, checking every record's checksum on the way
+        let mut upper = 0u32;
+        let mut image = Vec::new();
+        for line in hex.lines() {
+            let bytes: Vec<u8> = (1..line.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&line[i..i + 2], 16).unwrap())
+                .collect();
+            assert_eq!(bytes.iter().fold(0u8, |acc, x| acc.wrapping_add(*x)), 0);
+
+            let data = &bytes[4..bytes.len() - 1];
+            let addr = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+            match bytes[3] {
+                0x00 => image.push(((upper << 16) | addr, data.to_vec())),
+                0x04 => upper = u16::from_be_bytes([data[0], data[1]]) as u32,
+                0x01 => break,
+                x => panic!("unexpected record type {}", x),
+            }
+        }
+
+        assert_eq!(
+            image,
+            [
+                (0x0000fffc, vec![1, 2, 3, 4]),
+                (0x00010000, vec![5, 6, 7, 8])
+            ]
+        );
+        assert!(hex.ends_with(":00000001FF\n"));
+    }
+
+    #[test]
+    fn binary_empty() {
+        let empty = Segment::new(0x1000, EndianMode::Big);
+        assert!(segments_to_binary(&[empty]).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,125 @@
+use crate::component::{Instruction, RegisterName};
+
+/// Code that assembles fine but likely doesn't do what was meant.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Warning {
+    /// 1-based source line.
+    pub line: usize,
+    pub message: String,
+}
+
+/// The register an instruction writes, if any. Writes to `$zero` don't count.
+fn dest(ins: Instruction) -> Option<RegisterName> {
+    use Instruction::*;
+
+    let reg = match ins {
+        add(x) | addu(x) | and(x) | nor(x) | or(x) | slt(x) | sltu(x) | sub(x) | subu(x)
+        | xor(x) | mul(x) | clo(x) | clz(x) | sll(x) | sllv(x) | sra(x) | srav(x) | srl(x)
+        | srlv(x) | movn(x) | movz(x) | jalr(x) => x.rd,
+        addi(x) | addiu(x) | andi(x) | lui(x) | ori(x) | slti(x) | sltiu(x) | xori(x) | lb(x)
+        | lbu(x) | lh(x) | lhu(x) | lw(x) => x.rt,
+        mfc1(x) => x.rt,
+        jal(_) | bgezal(_) | bltzal(_) => RegisterName::new(31),
+        _ => return None,
+    };
+
+    Some(reg).filter(|x| !x.is_zero())
+}
+
+/// Whether an instruction may read `reg`. Unknown instructions are assumed to.
+fn reads(ins: Instruction, reg: RegisterName) -> bool {
+    use Instruction::*;
+
+    match ins {
+        sll(x) | sra(x) | srl(x) => x.rt == reg,
+        add(x) | addu(x) | and(x) | nor(x) | or(x) | slt(x) | sltu(x) | sub(x) | subu(x)
+        | xor(x) | mul(x) | sllv(x) | srav(x) | srlv(x) | teq(x) | tge(x) | tgeu(x) | tlt(x)
+        | tltu(x) | tne(x) => x.rs == reg || x.rt == reg,
+        clo(x) | clz(x) | jalr(x) | jr(x) => x.rs == reg,
+        // a move not taken keeps the old `rd`
+        movn(x) | movz(x) => x.rs == reg || x.rt == reg || x.rd == reg,
+        addi(x) | addiu(x) | andi(x) | ori(x) | slti(x) | sltiu(x) | xori(x) | lb(x) | lbu(x)
+        | lh(x) | lhu(x) | lw(x) | bgez(x) | bgezal(x) | bgtz(x) | blez(x) | bltz(x)
+        | bltzal(x) | lwc1(x) | swc1(x) => x.rs == reg,
+        beq(x) | bne(x) | sb(x) | sh(x) | sw(x) => x.rs == reg || x.rt == reg,
+        lui(_) | j(_) | jal(_) | sync(_) | pref(_) => false,
+        // FP registers are a separate file
+        add_s(_) | sub_s(_) | mul_s(_) | div_s(_) | mfc1(_) => false,
+        mtc1(x) => x.rt == reg,
+        syscall(_) | invalid(_) => true,
+    }
+}
+
+fn is_self_move(ins: Instruction) -> bool {
+    use Instruction::*;
+
+    match ins {
+        addu(x) | or(x) => {
+            !x.rd.is_zero()
+                && ((x.rs == x.rd && x.rt.is_zero()) || (x.rt == x.rd && x.rs.is_zero()))
+        }
+        _ => false,
+    }
+}
+
+/// Where a branch or jump at `addr` goes, if it is one.
+fn branch_target(ins: Instruction, addr: u32) -> Option<u32> {
+    use Instruction::*;
+
+    let next = addr.wrapping_add(4);
+    match ins {
+        beq(x) | bgez(x) | bgezal(x) | bgtz(x) | blez(x) | bltz(x) | bltzal(x) | bne(x) => {
+            Some(next.wrapping_add((x.imm as i16 as i32 as u32) << 2))
+        }
+        j(x) => Some((next & 0xf000_0000) | (x.target << 2)),
+        _ => None,
+    }
+}
+
+/// Looks for redundant code in text. `words` holds `(line, addr, instruction)` in address
+/// order, with `line` 0-based.
+pub(super) fn lint(words: &[(usize, u32, Instruction)]) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut warn = |line: usize, message: String| {
+        warnings.push(Warning {
+            line: line + 1,
+            message,
+        })
+    };
+
+    for (i, &(line, addr, ins)) in words.iter().enumerate() {
+        if is_self_move(ins) {
+            let rd = dest(ins).unwrap();
+            warn(
+                line,
+                format!("moving ${} to itself has no effect", rd.name()),
+            );
+        }
+
+        if branch_target(ins, addr) == Some(addr.wrapping_add(4)) {
+            warn(line, "branch to the next instruction has no effect".into());
+        }
+
+        let next = match words.get(i + 1) {
+            Some(&(_, x, next)) if x == addr.wrapping_add(4) => next,
+            _ => continue,
+        };
+
+        let is_load = matches!(
+            ins,
+            Instruction::lb(_)
+                | Instruction::lbu(_)
+                | Instruction::lh(_)
+                | Instruction::lhu(_)
+                | Instruction::lw(_)
+        );
+        if let (true, Some(rt)) = (is_load, dest(ins)) {
+            if dest(next) == Some(rt) && !reads(next, rt) {
+                let message = format!("value loaded into ${} is overwritten before use", rt.name());
+                warn(line, message);
+            }
+        }
+    }
+
+    warnings
+}
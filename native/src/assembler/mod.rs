@@ -1,5 +1,16 @@
 mod assemble;
+mod config;
 mod error;
+mod export;
+mod lint;
 
-pub use assemble::assemble;
+pub use assemble::{
+    assemble_listing, assemble_units, assemble_with, supported_mnemonics, Assembly, ListingLine,
+};
+pub use config::AssemblerConfig;
 pub use error::AssemblerError;
+pub use export::{segments_checksum, segments_to_binary, to_intel_hex};
+pub use lint::Warning;
+
+#[cfg(test)]
+pub use assemble::assemble;
@@ -1,4 +1,4 @@
-use super::register_name::RegisterName;
+use super::register_name::{FpRegisterName, RegisterName};
 use std::fmt::Debug;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
@@ -146,6 +146,44 @@ impl TypeJ {
     }
 }
 
+/// Coprocessor-1 register format. The operand format (`fmt`) is implied by the instruction.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct TypeFR {
+    pub ft: FpRegisterName,
+    pub fs: FpRegisterName,
+    pub fd: FpRegisterName,
+}
+
+impl TypeFR {
+    pub fn encode(&self, fmt: u8, funct: u8) -> u32 {
+        0x11 << 26
+            | (fmt as u32) << 21
+            | (self.ft.num() as u32) << 16
+            | (self.fs.num() as u32) << 11
+            | (self.fd.num() as u32) << 6
+            | (funct as u32)
+    }
+
+    fn decode_unchecked(ins: u32) -> (u8, TypeFR) {
+        let ft = ((ins >> 16) & 0x1f) as u8;
+        let fs = ((ins >> 11) & 0x1f) as u8;
+        let fd = ((ins >> 6) & 0x1f) as u8;
+        let funct = (ins & 0x3f) as u8;
+
+        (
+            funct,
+            TypeFR {
+                ft: FpRegisterName::new(ft),
+                fs: FpRegisterName::new(fs),
+                fd: FpRegisterName::new(fd),
+            },
+        )
+    }
+}
+
+/// `fmt` of single-precision coprocessor-1 instructions.
+const FMT_S: u8 = 0x10;
+
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Instruction {
@@ -162,6 +200,20 @@ pub enum Instruction {
     subu(TypeR),
     xor(TypeR),
 
+    // Arithmetic - MIPS32 `mul`, which writes the low word of the product to `rd`
+    // and leaves HI and LO alone
+    mul(TypeR),
+
+    // Arithmetic - MIPS IV conditional moves of `rs` into `rd`, taken when `rt` is
+    // non-zero (movn) or zero (movz)
+    movn(TypeR),
+    movz(TypeR),
+
+    // Arithmetic - MIPS32 count leading zeros/ones of `rs` into `rd`. The encoding
+    // repeats `rd` in `rt`
+    clo(TypeR),
+    clz(TypeR),
+
     // Arithmetic - Shifts
     sll(TypeR),
     sllv(TypeR),
@@ -206,6 +258,33 @@ pub enum Instruction {
     jalr(TypeR),
     jr(TypeR),
     syscall(TypeR),
+
+    // MIPS32 memory ordering and prefetch, both no-ops here. `sync` keeps its type in
+    // `shamt`, `pref` its hint in `rt`
+    sync(TypeR),
+    pref(TypeI),
+
+    // Traps. The trap code, if any, sits in `rd` and `shamt`
+    teq(TypeR),
+    tge(TypeR),
+    tgeu(TypeR),
+    tlt(TypeR),
+    tltu(TypeR),
+    tne(TypeR),
+
+    // Coprocessor 1 - single-precision arithmetic, `fd = fs op ft`
+    add_s(TypeFR),
+    sub_s(TypeFR),
+    mul_s(TypeFR),
+    div_s(TypeFR),
+
+    // Coprocessor 1 - word loads and stores, with the FP register number in `rt`
+    lwc1(TypeI),
+    swc1(TypeI),
+
+    // Coprocessor 1 - bit copies between `rt` and the FP register numbered by `rd`
+    mfc1(TypeR),
+    mtc1(TypeR),
     invalid(u32),
 }
 
@@ -217,6 +296,35 @@ impl Instruction {
             None
         }
     }
+
+    /// Whether this is a branch or jump, and so is followed by a delay slot.
+    pub fn has_delay_slot(self) -> bool {
+        use Instruction::*;
+
+        matches!(
+            self,
+            beq(_)
+                | bgez(_)
+                | bgezal(_)
+                | bgtz(_)
+                | blez(_)
+                | bltz(_)
+                | bltzal(_)
+                | bne(_)
+                | j(_)
+                | jal(_)
+                | jalr(_)
+                | jr(_)
+        )
+    }
+}
+
+/// Whether `word` is the canonical NOP, `sll $zero, $zero, 0`.
+///
+/// Other encodings without an effect, like `add $zero, $zero, $zero` (0x00000020), are not
+/// treated as NOPs, so they show up as what they really are.
+pub fn is_nop(word: u32) -> bool {
+    word == 0
 }
 
 enum TypeGroup {
@@ -242,6 +350,12 @@ impl Instruction {
             sub(x) => (0x22, R(x)),
             subu(x) => (0x23, R(x)),
             xor(x) => (0x26, R(x)),
+            movn(x) => (0x0b, R(x)),
+            movz(x) => (0x0a, R(x)),
+            // SPECIAL2 rather than SPECIAL, so the opcode goes in by hand
+            mul(x) => return 0x1c << 26 | x.encode(0x02),
+            clo(x) => return 0x1c << 26 | x.encode(0x21),
+            clz(x) => return 0x1c << 26 | x.encode(0x20),
             sll(x) => (0x00, R(x)),
             sllv(x) => (0x04, R(x)),
             sra(x) => (0x03, R(x)),
@@ -317,6 +431,29 @@ impl Instruction {
             jalr(x) => (0x09, R(x)),
             jr(x) => (0x08, R(x)),
             syscall(x) => (0x0c, R(x)),
+            sync(x) => (0x0f, R(x)),
+            pref(x) => (0x33, I(x)),
+            teq(x) => (0x34, R(x)),
+            tge(x) => (0x30, R(x)),
+            tgeu(x) => (0x31, R(x)),
+            tlt(x) => (0x32, R(x)),
+            tltu(x) => (0x33, R(x)),
+            tne(x) => (0x36, R(x)),
+            add_s(x) => return x.encode(FMT_S, 0x00),
+            sub_s(x) => return x.encode(FMT_S, 0x01),
+            mul_s(x) => return x.encode(FMT_S, 0x02),
+            div_s(x) => return x.encode(FMT_S, 0x03),
+            lwc1(x) => (0x31, I(x)),
+            swc1(x) => (0x39, I(x)),
+            // the move direction sits in `rs`
+            mfc1(x) => return 0x11 << 26 | x.rs_zeroed().encode(0),
+            mtc1(x) => {
+                let x = TypeR {
+                    rs: RegisterName::new(0x04),
+                    ..x
+                };
+                return 0x11 << 26 | x.encode(0);
+            }
             invalid(x) => return x,
         };
 
@@ -335,6 +472,7 @@ impl Instruction {
         let r = TypeR::decode_unchecked(ins).1;
         let i = TypeI::decode_unchecked(ins).1;
         let tj = TypeJ::decode_unchecked(ins).1;
+        let fr = TypeFR::decode_unchecked(ins).1;
 
         match opcode {
             0x00 => {
@@ -359,9 +497,36 @@ impl Instruction {
                     0x09 => jalr(r),
                     0x08 => jr(r),
                     0x0c => syscall(r),
+                    0x0f => sync(r),
+                    0x0b => movn(r),
+                    0x0a => movz(r),
+                    0x34 => teq(r),
+                    0x30 => tge(r),
+                    0x31 => tgeu(r),
+                    0x32 => tlt(r),
+                    0x33 => tltu(r),
+                    0x36 => tne(r),
                     _ => invalid(ins),
                 }
             }
+            0x11 if (ins >> 21) & 0x1f == FMT_S as u32 => match ins & 0x3f {
+                0x00 => add_s(fr),
+                0x01 => sub_s(fr),
+                0x02 => mul_s(fr),
+                0x03 => div_s(fr),
+                _ => invalid(ins),
+            },
+            0x11 if ins & 0x7ff == 0 => match r.rs.num() {
+                0x00 => mfc1(r),
+                0x04 => mtc1(r),
+                _ => invalid(ins),
+            },
+            0x1c => match ins & 0x3f {
+                0x02 => mul(r),
+                0x21 => clo(r),
+                0x20 => clz(r),
+                _ => invalid(ins),
+            },
             0x08 => addi(i),
             0x09 => addiu(i),
             0x0c => andi(i),
@@ -401,6 +566,9 @@ impl Instruction {
             0x28 => sb(i),
             0x29 => sh(i),
             0x2b => sw(i),
+            0x33 => pref(i),
+            0x31 => lwc1(i),
+            0x39 => swc1(i),
             2 => j(tj),
             3 => jal(tj),
             _ => invalid(ins),
@@ -421,6 +589,11 @@ impl Instruction {
             sub(x) => sub(x.shamt_zeroed()),
             subu(x) => subu(x.shamt_zeroed()),
             xor(x) => xor(x.shamt_zeroed()),
+            mul(x) => mul(x.shamt_zeroed()),
+            movn(x) => movn(x.shamt_zeroed()),
+            movz(x) => movz(x.shamt_zeroed()),
+            clo(x) => clo(x.shamt_zeroed()),
+            clz(x) => clz(x.shamt_zeroed()),
             sll(x) => sll(x.rs_zeroed()),
             sllv(x) => sllv(x.shamt_zeroed()),
             sra(x) => sra(x.rs_zeroed()),
@@ -456,6 +629,22 @@ impl Instruction {
             jalr(x) => jalr(x.rt_zeroed().shamt_zeroed()),
             jr(x) => jr(x.rt_zeroed().rd_zeroed().shamt_zeroed()),
             syscall(_) => syscall(Default::default()),
+            sync(x) => sync(x.rs_zeroed().rt_zeroed().rd_zeroed()),
+            pref(_) => self,
+            teq(_) => self,
+            tge(_) => self,
+            tgeu(_) => self,
+            tlt(_) => self,
+            tltu(_) => self,
+            tne(_) => self,
+            add_s(_) => self,
+            sub_s(_) => self,
+            mul_s(_) => self,
+            div_s(_) => self,
+            lwc1(_) => self,
+            swc1(_) => self,
+            mfc1(x) => mfc1(x.rs_zeroed()),
+            mtc1(x) => mtc1(x.rs_zeroed()),
             invalid(_) => self,
         }
     }
@@ -466,6 +655,16 @@ mod test {
     use super::*;
     use rayon::prelude::*;
 
+    #[test]
+    fn nop_encodings() {
+        assert!(is_nop(0x00000000));
+        assert!(is_nop(Instruction::sll(Default::default()).encode()));
+
+        assert!(!is_nop(0x00000020)); // add $zero, $zero, $zero
+        assert!(!is_nop(0x00000040)); // sll $zero, $zero, 1
+        assert!(!is_nop(0x00200000)); // sll $zero, $zero, 0 with a stray rs
+    }
+
     #[test]
     #[ignore] // very expensive and parallelized test. would take an hour in a weak machine
     fn decode_then_encode() {
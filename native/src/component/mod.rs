@@ -1,5 +1,5 @@
 mod instruction;
 mod register_name;
 
-pub use instruction::{Instruction, TypeI, TypeJ, TypeR};
-pub use register_name::RegisterName;
+pub use instruction::{is_nop, Instruction, TypeFR, TypeI, TypeJ, TypeR};
+pub use register_name::{FpRegisterName, RegisterName};
@@ -1,5 +1,13 @@
 use std::str::FromStr;
 
+/// ABI names of the registers, indexed by register number.
+/// Shared by the assembler and the disassembler so the two can't drift apart.
+const ABI_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6",
+    "t7", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "s8",
+    "ra",
+];
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
 pub struct RegisterName(u8);
 
@@ -26,41 +34,7 @@ impl RegisterName {
     }
 
     pub fn name(&self) -> &'static str {
-        match self.0 {
-            0 => "zero",
-            1 => "at",
-            2 => "v0",
-            3 => "v1",
-            4 => "a0",
-            5 => "a1",
-            6 => "a2",
-            7 => "a3",
-            8 => "t0",
-            9 => "t1",
-            10 => "t2",
-            11 => "t3",
-            12 => "t4",
-            13 => "t5",
-            14 => "t6",
-            15 => "t7",
-            16 => "s0",
-            17 => "s1",
-            18 => "s2",
-            19 => "s3",
-            20 => "s4",
-            21 => "s5",
-            22 => "s6",
-            23 => "s7",
-            24 => "t8",
-            25 => "t9",
-            26 => "k0",
-            27 => "k1",
-            28 => "gp",
-            29 => "sp",
-            30 => "s8",
-            31 => "ra",
-            _ => unreachable!(),
-        }
+        ABI_NAMES[self.0 as usize]
     }
 
     pub fn try_from_name(name: &str) -> Option<Self> {
@@ -70,42 +44,78 @@ impl RegisterName {
         }
 
         // Try parsing as textual form (e.g. $v1)
-        let val = match name {
-            "r0" | "zero" => 0,
-            "at" => 1,
-            "v0" => 2,
-            "v1" => 3,
-            "a0" => 4,
-            "a1" => 5,
-            "a2" => 6,
-            "a3" => 7,
-            "t0" => 8,
-            "t1" => 9,
-            "t2" => 10,
-            "t3" => 11,
-            "t4" => 12,
-            "t5" => 13,
-            "t6" => 14,
-            "t7" => 15,
-            "s0" => 16,
-            "s1" => 17,
-            "s2" => 18,
-            "s3" => 19,
-            "s4" => 20,
-            "s5" => 21,
-            "s6" => 22,
-            "s7" => 23,
-            "t8" => 24,
-            "t9" => 25,
-            "k0" => 26,
-            "k1" => 27,
-            "gp" => 28,
-            "sp" => 29,
-            "s8" => 30,
-            "ra" => 31,
-            _ => return None,
-        };
-
-        RegisterName::try_from_num(val)
+        if name == "r0" {
+            return Some(RegisterName(0));
+        }
+
+        ABI_NAMES
+            .iter()
+            .position(|x| *x == name)
+            .map(|x| RegisterName(x as u8))
+    }
+}
+
+/// A coprocessor-1 register, `$f0` through `$f31`. Kept apart from [`RegisterName`] so that
+/// `$f4` and `$4` can't be mixed up.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct FpRegisterName(u8);
+
+impl FpRegisterName {
+    pub fn new(val: u8) -> Self {
+        assert!(val < 32);
+        FpRegisterName(val)
+    }
+
+    pub fn num(&self) -> u8 {
+        self.0
+    }
+
+    /// Parses `f0` through `f31`, without the `$`.
+    pub fn try_from_name(name: &str) -> Option<Self> {
+        let num = name.strip_prefix('f')?;
+        // `u8` would also take `f+1`
+        if !num.bytes().all(|x| x.is_ascii_digit()) {
+            return None;
+        }
+
+        match u8::from_str(num) {
+            Ok(x) if x < 32 => Some(FpRegisterName(x)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn name_roundtrip() {
+        for i in 0..32 {
+            let reg = RegisterName::new(i);
+            assert_eq!(RegisterName::try_from_name(reg.name()), Some(reg));
+            assert_eq!(RegisterName::try_from_name(&i.to_string()), Some(reg));
+        }
+
+        assert_eq!(RegisterName::new(0).name(), "zero");
+        assert_eq!(RegisterName::new(8).name(), "t0");
+        assert_eq!(RegisterName::new(31).name(), "ra");
+        assert_eq!(
+            RegisterName::try_from_name("r0"),
+            Some(RegisterName::new(0))
+        );
+        assert_eq!(RegisterName::try_from_name("32"), None);
+        assert_eq!(RegisterName::try_from_name("t10"), None);
+    }
+    #[test]
+    fn fp_names() {
+        for i in 0..32 {
+            let reg = FpRegisterName::try_from_name(&format!("f{}", i)).unwrap();
+            assert_eq!(reg.num(), i);
+        }
+
+        for name in ["f32", "f", "f+1", "4", "t0", "fp"] {
+            assert_eq!(FpRegisterName::try_from_name(name), None, "{}", name);
+        }
     }
 }
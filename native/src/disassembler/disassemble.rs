@@ -1,154 +1,863 @@
-use crate::component::{Instruction, TypeI, TypeJ, TypeR};
+use crate::component::{
+    is_nop, FpRegisterName, Instruction, RegisterName, TypeFR, TypeI, TypeJ, TypeR,
+};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use Operand::Register;
 
-fn format_type_r(mnemonic: &str, x: TypeR) -> String {
-    format!(
-        "{} ${}, ${}, ${}",
-        mnemonic,
-        x.rd.name(),
-        x.rs.name(),
-        x.rt.name()
-    )
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DisassembleOptions {
+    /// Print registers by their ABI name (`$t0`) rather than by number (`$8`).
+    pub abi_names: bool,
+    /// Show common idioms as the pseudo-instruction they came from (`move`, `li`, `nop`).
+    /// Otherwise the word is decoded exactly.
+    pub pseudo: bool,
 }
 
-fn format_type_shift(mnemonic: &str, x: TypeR) -> String {
-    format!(
-        "{} ${}, ${}, {}",
-        mnemonic,
-        x.rd.name(),
-        x.rt.name(),
-        x.shamt
-    )
+impl Default for DisassembleOptions {
+    fn default() -> Self {
+        DisassembleOptions {
+            abi_names: true,
+            pseudo: false,
+        }
+    }
+}
+
+struct Reg(RegisterName, bool);
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.1 {
+            write!(f, "${}", self.0.name())
+        } else {
+            write!(f, "${}", self.0.num())
+        }
+    }
+}
+
+impl DisassembleOptions {
+    fn reg(&self, r: RegisterName) -> Reg {
+        Reg(r, self.abi_names)
+    }
+}
+
+/// One operand of a [`DecodedInstruction`], tagged with what it means.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Operand {
+    Register(RegisterName),
+    /// Always shown as `$fN`; FP registers have no ABI names.
+    FpRegister(FpRegisterName),
+    /// A number as the instruction reads it. `hex` is `Some(width)` when it reads better in
+    /// hex, zero-padded to `width` digits.
+    Immediate {
+        value: i64,
+        hex: Option<usize>,
+    },
+    /// An absolute jump target.
+    Address(u32),
+    /// A branch offset in bytes from the delay slot, and where it lands if the branch's own
+    /// address is known.
+    Branch {
+        offset: i32,
+        target: Option<u32>,
+    },
+    /// The upper or lower half of an address built by `lui` and `ori`.
+    AddressHalf {
+        addr: u32,
+        high: bool,
+    },
+    /// `offset(base)` of a load or store.
+    Memory {
+        base: RegisterName,
+        offset: i16,
+    },
+}
+
+impl Operand {
+    fn imm(value: impl Into<i64>) -> Self {
+        Operand::Immediate {
+            value: value.into(),
+            hex: None,
+        }
+    }
+
+    fn hex(value: impl Into<i64>, width: usize) -> Self {
+        Operand::Immediate {
+            value: value.into(),
+            hex: Some(width),
+        }
+    }
+}
+
+/// The fields of a word, split by the format its opcode implies.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RawFields {
+    R {
+        opcode: u8,
+        rs: u8,
+        rt: u8,
+        rd: u8,
+        shamt: u8,
+        funct: u8,
+    },
+    I {
+        opcode: u8,
+        rs: u8,
+        rt: u8,
+        imm: u16,
+    },
+    J {
+        opcode: u8,
+        target: u32,
+    },
 }
 
-fn format_type_shift_reg(mnemonic: &str, x: TypeR) -> String {
+impl RawFields {
+    pub fn split(ins: u32) -> Self {
+        let opcode = (ins >> 26) as u8;
+        let rs = ((ins >> 21) & 0x1f) as u8;
+        let rt = ((ins >> 16) & 0x1f) as u8;
+
+        match opcode {
+            // coprocessor 1 is laid out like R-type: fmt, ft, fs, fd, funct
+            0 | 0x11 => RawFields::R {
+                opcode,
+                rs,
+                rt,
+                rd: ((ins >> 11) & 0x1f) as u8,
+                shamt: ((ins >> 6) & 0x1f) as u8,
+                funct: (ins & 0x3f) as u8,
+            },
+            2 | 3 => RawFields::J {
+                opcode,
+                target: ins & 0x03ff_ffff,
+            },
+            _ => RawFields::I {
+                opcode,
+                rs,
+                rt,
+                imm: ins as u16,
+            },
+        }
+    }
+}
+
+/// A disassembled word, for callers that want more than text.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DecodedInstruction {
+    /// `.word` for words that don't decode.
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+    pub fields: RawFields,
+}
+
+impl DecodedInstruction {
+    /// Formats like the assembler's syntax.
+    pub fn format(&self, o: &DisassembleOptions) -> String {
+        self.format_with_symbols(o, &HashMap::new())
+    }
+
+    /// Like [`format`](Self::format), but shows jump and branch targets and address halves
+    /// found in `symbols` by name.
+    pub fn format_with_symbols(
+        &self,
+        o: &DisassembleOptions,
+        symbols: &HashMap<u32, String>,
+    ) -> String {
+        let operands: Vec<String> = self
+            .operands
+            .iter()
+            .map(|x| match *x {
+                Operand::Register(r) => o.reg(r).to_string(),
+                Operand::FpRegister(r) => format!("$f{}", r.num()),
+                Operand::Immediate { value, hex: None } => value.to_string(),
+                Operand::Immediate {
+                    value,
+                    hex: Some(width),
+                } => format!("0x{:0width$x}", value, width = width),
+                Operand::Address(x) => match symbols.get(&x) {
+                    Some(name) => name.clone(),
+                    None => format!("0x{:08x}", x),
+                },
+                Operand::Branch { offset, target } => match target.and_then(|x| symbols.get(&x)) {
+                    Some(name) => name.clone(),
+                    None => offset.to_string(),
+                },
+                Operand::AddressHalf { addr, high } => match (symbols.get(&addr), high) {
+                    (Some(name), true) => format!("%hi({})", name),
+                    (Some(name), false) => format!("%lo({})", name),
+                    (None, true) => format!("0x{:x}", addr >> 16),
+                    (None, false) => format!("0x{:x}", addr & 0xffff),
+                },
+                Operand::Memory { base, offset } => format!("{}({})", offset, o.reg(base)),
+            })
+            .collect();
+
+        if operands.is_empty() {
+            self.mnemonic.into()
+        } else {
+            format!("{} {}", self.mnemonic, operands.join(", "))
+        }
+    }
+}
+
+fn operands_type_r(x: TypeR) -> Vec<Operand> {
+    vec![Register(x.rd), Register(x.rs), Register(x.rt)]
+}
+
+fn operands_type_shift(x: TypeR) -> Vec<Operand> {
+    vec![Register(x.rd), Register(x.rt), Operand::imm(x.shamt)]
+}
+
+fn operands_type_shift_reg(x: TypeR) -> Vec<Operand> {
     // Shift instructions use $d, $t, $s order,
     // where normal ones use $d, $s, $t order.
+    vec![Register(x.rd), Register(x.rt), Register(x.rs)]
+}
 
-    format!(
-        "{} ${}, ${}, ${}",
-        mnemonic,
-        x.rd.name(),
-        x.rt.name(),
-        x.rs.name()
-    )
+fn operands_type_i(x: TypeI) -> Vec<Operand> {
+    vec![Register(x.rt), Register(x.rs), Operand::imm(x.imm)]
 }
 
-fn format_type_i(mnemonic: &str, x: TypeI) -> String {
-    format!("{} ${}, ${}, {}", mnemonic, x.rt.name(), x.rs.name(), x.imm)
+fn operands_type_i_hex(x: TypeI) -> Vec<Operand> {
+    // logical operations zero-extend, so hex reads better
+    vec![Register(x.rt), Register(x.rs), Operand::hex(x.imm, 0)]
 }
 
-fn format_type_i_signed(mnemonic: &str, x: TypeI) -> String {
-    format!(
-        "{} ${}, ${}, {}",
-        mnemonic,
-        x.rt.name(),
-        x.rs.name(),
-        x.imm as i16
-    )
+fn operands_type_i_signed(x: TypeI) -> Vec<Operand> {
+    vec![Register(x.rt), Register(x.rs), Operand::imm(x.imm as i16)]
 }
 
-fn format_type_branch_2arg(mnemonic: &str, x: TypeI) -> String {
-    format!(
-        "{} ${}, ${}, {}",
-        mnemonic,
-        x.rs.name(),
-        x.rt.name(),
-        x.imm as i16 as i32 * 4
-    )
+fn branch_operand(x: TypeI, addr: Option<u32>) -> Operand {
+    let offset = x.imm as i16 as i32 * 4;
+    Operand::Branch {
+        offset,
+        target: addr.map(|x| x.wrapping_add(4).wrapping_add(offset as u32)),
+    }
 }
 
-fn format_type_branch_1arg(mnemonic: &str, x: TypeI) -> String {
-    format!("{} ${}, {}", mnemonic, x.rs.name(), x.imm as i16 as i32 * 4)
+fn operands_type_branch_2arg(x: TypeI, addr: Option<u32>) -> Vec<Operand> {
+    vec![Register(x.rs), Register(x.rt), branch_operand(x, addr)]
 }
 
-fn format_type_memory(mnemonic: &str, x: TypeI) -> String {
-    format!(
-        "{} ${}, {}(${})",
-        mnemonic,
-        x.rt.name(),
-        x.imm as i16,
-        x.rs.name()
-    )
+fn operands_type_branch_1arg(x: TypeI, addr: Option<u32>) -> Vec<Operand> {
+    vec![Register(x.rs), branch_operand(x, addr)]
+}
+
+fn operands_type_memory(x: TypeI) -> Vec<Operand> {
+    vec![
+        Register(x.rt),
+        Operand::Memory {
+            base: x.rs,
+            offset: x.imm as i16,
+        },
+    ]
+}
+
+fn operands_type_fp_memory(x: TypeI) -> Vec<Operand> {
+    vec![
+        Operand::FpRegister(FpRegisterName::new(x.rt.num())),
+        Operand::Memory {
+            base: x.rs,
+            offset: x.imm as i16,
+        },
+    ]
+}
+
+fn operands_type_fp_move(x: TypeR) -> Vec<Operand> {
+    vec![
+        Register(x.rt),
+        Operand::FpRegister(FpRegisterName::new(x.rd.num())),
+    ]
 }
 
-fn format_type_jump_imm(mnemonic: &str, x: TypeJ) -> String {
-    format!("{} 0x{:08x}", mnemonic, x.target * 4)
+fn operands_type_fr(x: TypeFR) -> Vec<Operand> {
+    vec![
+        Operand::FpRegister(x.fd),
+        Operand::FpRegister(x.fs),
+        Operand::FpRegister(x.ft),
+    ]
 }
 
-fn format_type_jump_reg(mnemonic: &str, x: TypeR) -> String {
-    format!("{} ${}", mnemonic, x.rs.name())
+fn operands_type_jump_imm(x: TypeJ, addr: Option<u32>) -> Vec<Operand> {
+    // The upper 4 bits come from the address of the delay slot, if we know it.
+    let region = addr.map_or(0, |x| x.wrapping_add(4) & 0xf000_0000);
+    vec![Operand::Address(region | (x.target << 2))]
 }
 
-fn format_type_jump_reg_linked(mnemonic: &str, x: TypeR) -> String {
-    format!("{} ${}, ${}", mnemonic, x.rd.name(), x.rs.name())
+fn try_decode_pseudo(ins: Instruction) -> Option<(&'static str, Vec<Operand>)> {
+    match ins {
+        Instruction::addu(x) | Instruction::or(x) if x.rs.is_zero() => {
+            Some(("move", vec![Register(x.rd), Register(x.rt)]))
+        }
+        Instruction::addu(x) | Instruction::or(x) if x.rt.is_zero() => {
+            Some(("move", vec![Register(x.rd), Register(x.rs)]))
+        }
+        Instruction::ori(x) if x.rs.is_zero() => {
+            Some(("li", vec![Register(x.rt), Operand::imm(x.imm)]))
+        }
+        Instruction::addiu(x) if x.rs.is_zero() => {
+            Some(("li", vec![Register(x.rt), Operand::imm(x.imm as i16)]))
+        }
+        _ => None,
+    }
+}
+
+/// An instruction the decoder knows, with one word that decodes to it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct OpcodeInfo {
+    pub mnemonic: &'static str,
+    pub example: u32,
+}
+
+/// Every instruction the decoder knows, sorted by mnemonic. Found by decoding each opcode
+/// with each function code and each selector in `rs` or `rt`, so it follows the decoder.
+pub fn supported_opcodes() -> Vec<OpcodeInfo> {
+    let mut seen = HashSet::new();
+    let mut ret = Vec::new();
+
+    for opcode in 0..64u32 {
+        for low in 0..64u32 {
+            for sel in 0..32u32 {
+                for word in [
+                    opcode << 26 | sel << 21 | low,
+                    opcode << 26 | sel << 16 | low,
+                ] {
+                    let decoded = Instruction::decode(word);
+                    if decoded.as_invalid().is_none()
+                        && seen.insert(std::mem::discriminant(&decoded))
+                    {
+                        ret.push(OpcodeInfo {
+                            mnemonic: disassemble_fields(word).mnemonic,
+                            example: word,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    ret.sort_unstable_by_key(|x| x.mnemonic);
+    ret
 }
 
+/// Disassembles a word without knowing where it lives.
+/// Jump targets only contain the lower 28 bits of the address.
+/// Any word is accepted; one that doesn't decode comes out as `.word 0x...`.
 pub fn disassemble(ins: u32) -> String {
-    if ins == 0 {
-        return "nop".into();
+    disassemble_with(ins, None, &Default::default())
+}
+
+/// Disassembles a word located at `addr`, so that jump targets can be shown in full.
+pub fn disassemble_at(addr: u32, ins: u32) -> String {
+    disassemble_with(ins, Some(addr), &Default::default())
+}
+
+/// Disassembles consecutive words starting at `base`, pairing each with its address.
+pub fn disassemble_region(base: u32, words: &[u32]) -> Vec<(u32, String)> {
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, &ins)| {
+            let addr = base.wrapping_add(i as u32 * 4);
+            (addr, disassemble_at(addr, ins))
+        })
+        .collect()
+}
+
+pub fn disassemble_with(ins: u32, addr: Option<u32>, o: &DisassembleOptions) -> String {
+    disassemble_fields_with(ins, addr, o).format(o)
+}
+
+/// Like [`disassemble_with`], but names jump and branch targets by the labels in `symbols`,
+/// which maps addresses to names. `around` holds the words before and after `ins` if known,
+/// so an address built by `lui` and `ori`, as `la` does, shows as `%hi(name)` and
+/// `%lo(name)`.
+pub fn disassemble_with_symbols(
+    ins: u32,
+    addr: Option<u32>,
+    o: &DisassembleOptions,
+    symbols: &HashMap<u32, String>,
+    around: (Option<u32>, Option<u32>),
+) -> String {
+    let mut decoded = disassemble_fields_with(ins, addr, o);
+
+    let (prev, next) = around;
+    let half = match (
+        next.and_then(|x| la_address(ins, x)),
+        prev.and_then(|x| la_address(x, ins)),
+    ) {
+        (Some(addr), _) => Some(Operand::AddressHalf { addr, high: true }),
+        (None, Some(addr)) => Some(Operand::AddressHalf { addr, high: false }),
+        (None, None) => None,
+    };
+    if let (Some(x), Some(Operand::Immediate { .. })) = (half, decoded.operands.last()) {
+        *decoded.operands.last_mut().unwrap() = x;
     }
 
+    decoded.format_with_symbols(o, symbols)
+}
+
+/// The address loaded by `lui` then `ori` into the same register, as `la` emits them.
+fn la_address(hi: u32, lo: u32) -> Option<u32> {
+    match (Instruction::decode(hi), Instruction::decode(lo)) {
+        (Instruction::lui(x), Instruction::ori(y)) if y.rs == x.rt && y.rt == x.rt => {
+            Some((x.imm as u32) << 16 | y.imm as u32)
+        }
+        _ => None,
+    }
+}
+
+/// Like [`disassemble`], but keeps the mnemonic, operands and fields apart.
+pub fn disassemble_fields(ins: u32) -> DecodedInstruction {
+    disassemble_fields_with(ins, None, &Default::default())
+}
+
+pub fn disassemble_fields_with(
+    ins: u32,
+    addr: Option<u32>,
+    o: &DisassembleOptions,
+) -> DecodedInstruction {
     let decoded = Instruction::decode(ins);
+    let fields = RawFields::split(ins);
+
+    if o.pseudo {
+        let pseudo = if is_nop(ins) {
+            Some(("nop", vec![]))
+        } else {
+            try_decode_pseudo(decoded)
+        };
+
+        if let Some((mnemonic, operands)) = pseudo {
+            return DecodedInstruction {
+                mnemonic,
+                operands,
+                fields,
+            };
+        }
+    }
+
+    let (mnemonic, operands) = match decoded {
+        Instruction::add(x) => ("add", operands_type_r(x)),
+        Instruction::addu(x) => ("addu", operands_type_r(x)),
+        Instruction::and(x) => ("and", operands_type_r(x)),
+        Instruction::nor(x) => ("nor", operands_type_r(x)),
+        Instruction::or(x) => ("or", operands_type_r(x)),
+        Instruction::slt(x) => ("slt", operands_type_r(x)),
+        Instruction::sltu(x) => ("sltu", operands_type_r(x)),
+        Instruction::sub(x) => ("sub", operands_type_r(x)),
+        Instruction::subu(x) => ("subu", operands_type_r(x)),
+        Instruction::xor(x) => ("xor", operands_type_r(x)),
+        Instruction::mul(x) => ("mul", operands_type_r(x)),
+        Instruction::movn(x) => ("movn", operands_type_r(x)),
+        Instruction::movz(x) => ("movz", operands_type_r(x)),
+        Instruction::clo(x) => ("clo", vec![Register(x.rd), Register(x.rs)]),
+        Instruction::clz(x) => ("clz", vec![Register(x.rd), Register(x.rs)]),
+        Instruction::sll(x) => ("sll", operands_type_shift(x)),
+        Instruction::sllv(x) => ("sllv", operands_type_shift_reg(x)),
+        Instruction::sra(x) => ("sra", operands_type_shift(x)),
+        Instruction::srav(x) => ("srav", operands_type_shift_reg(x)),
+        Instruction::srl(x) => ("srl", operands_type_shift(x)),
+        Instruction::srlv(x) => ("srlv", operands_type_shift_reg(x)),
+        Instruction::addi(x) => ("addi", operands_type_i_signed(x)),
+        Instruction::addiu(x) => ("addiu", operands_type_i_signed(x)),
+        Instruction::andi(x) => ("andi", operands_type_i_hex(x)),
+        Instruction::lui(x) => ("lui", vec![Register(x.rt), Operand::hex(x.imm, 0)]),
+        Instruction::ori(x) => ("ori", operands_type_i_hex(x)),
+        Instruction::slti(x) => ("slti", operands_type_i_signed(x)),
+        Instruction::sltiu(x) => ("sltiu", operands_type_i(x)),
+        Instruction::xori(x) => ("xori", operands_type_i_hex(x)),
+        Instruction::beq(x) => ("beq", operands_type_branch_2arg(x, addr)),
+        Instruction::bgez(x) => ("bgez", operands_type_branch_1arg(x, addr)),
+        Instruction::bgezal(x) => ("bgezal", operands_type_branch_1arg(x, addr)),
+        Instruction::bgtz(x) => ("bgtz", operands_type_branch_1arg(x, addr)),
+        Instruction::blez(x) => ("blez", operands_type_branch_1arg(x, addr)),
+        Instruction::bltz(x) => ("bltz", operands_type_branch_1arg(x, addr)),
+        Instruction::bltzal(x) => ("bltzal", operands_type_branch_1arg(x, addr)),
+        Instruction::bne(x) => ("bne", operands_type_branch_2arg(x, addr)),
+        Instruction::lb(x) => ("lb", operands_type_memory(x)),
+        Instruction::lbu(x) => ("lbu", operands_type_memory(x)),
+        Instruction::lh(x) => ("lh", operands_type_memory(x)),
+        Instruction::lhu(x) => ("lhu", operands_type_memory(x)),
+        Instruction::lw(x) => ("lw", operands_type_memory(x)),
+        Instruction::sb(x) => ("sb", operands_type_memory(x)),
+        Instruction::sh(x) => ("sh", operands_type_memory(x)),
+        Instruction::sw(x) => ("sw", operands_type_memory(x)),
+        Instruction::j(x) => ("j", operands_type_jump_imm(x, addr)),
+        Instruction::jal(x) => ("jal", operands_type_jump_imm(x, addr)),
+        Instruction::jalr(x) => ("jalr", vec![Register(x.rd), Register(x.rs)]),
+        Instruction::jr(x) => ("jr", vec![Register(x.rs)]),
+        Instruction::syscall(_) => ("syscall", vec![]),
+        Instruction::sync(x) if x.shamt == 0 => ("sync", vec![]),
+        Instruction::sync(x) => ("sync", vec![Operand::imm(x.shamt)]),
+        Instruction::pref(x) => (
+            "pref",
+            vec![
+                Operand::imm(x.rt.num()),
+                Operand::Memory {
+                    base: x.rs,
+                    offset: x.imm as i16,
+                },
+            ],
+        ),
+        Instruction::teq(x) => ("teq", vec![Register(x.rs), Register(x.rt)]),
+        Instruction::tge(x) => ("tge", vec![Register(x.rs), Register(x.rt)]),
+        Instruction::tgeu(x) => ("tgeu", vec![Register(x.rs), Register(x.rt)]),
+        Instruction::tlt(x) => ("tlt", vec![Register(x.rs), Register(x.rt)]),
+        Instruction::tltu(x) => ("tltu", vec![Register(x.rs), Register(x.rt)]),
+        Instruction::tne(x) => ("tne", vec![Register(x.rs), Register(x.rt)]),
+        Instruction::add_s(x) => ("add.s", operands_type_fr(x)),
+        Instruction::sub_s(x) => ("sub.s", operands_type_fr(x)),
+        Instruction::mul_s(x) => ("mul.s", operands_type_fr(x)),
+        Instruction::div_s(x) => ("div.s", operands_type_fr(x)),
+        Instruction::lwc1(x) => ("lwc1", operands_type_fp_memory(x)),
+        Instruction::swc1(x) => ("swc1", operands_type_fp_memory(x)),
+        Instruction::mfc1(x) => ("mfc1", operands_type_fp_move(x)),
+        Instruction::mtc1(x) => ("mtc1", operands_type_fp_move(x)),
+        Instruction::invalid(_) => (".word", vec![Operand::hex(ins, 8)]),
+    };
 
-    match decoded {
-        Instruction::add(x) => format_type_r("add", x),
-        Instruction::addu(x) => format_type_r("addu", x),
-        Instruction::and(x) => format_type_r("and", x),
-        Instruction::nor(x) => format_type_r("nor", x),
-        Instruction::or(x) => format_type_r("or", x),
-        Instruction::slt(x) => format_type_r("slt", x),
-        Instruction::sltu(x) => format_type_r("sltu", x),
-        Instruction::sub(x) => format_type_r("sub", x),
-        Instruction::subu(x) => format_type_r("subu", x),
-        Instruction::xor(x) => format_type_r("xor", x),
-        Instruction::sll(x) => format_type_shift("sll", x),
-        Instruction::sllv(x) => format_type_shift_reg("sllv", x),
-        Instruction::sra(x) => format_type_shift("sra", x),
-        Instruction::srav(x) => format_type_shift_reg("srav", x),
-        Instruction::srl(x) => format_type_shift("srl", x),
-        Instruction::srlv(x) => format_type_shift_reg("srlv", x),
-        Instruction::addi(x) => format_type_i_signed("addi", x),
-        Instruction::addiu(x) => format_type_i_signed("addiu", x),
-        Instruction::andi(x) => format_type_i("andi", x),
-        Instruction::lui(x) => format!("lui ${}, {}", x.rt.name(), x.imm),
-        Instruction::ori(x) => format_type_i("ori", x),
-        Instruction::slti(x) => format_type_i_signed("slti", x),
-        Instruction::sltiu(x) => format_type_i("sltiu", x),
-        Instruction::xori(x) => format_type_i("xori", x),
-        Instruction::beq(x) => format_type_branch_2arg("beq", x),
-        Instruction::bgez(x) => format_type_branch_1arg("bgez", x),
-        Instruction::bgezal(x) => format_type_branch_1arg("bgezal", x),
-        Instruction::bgtz(x) => format_type_branch_1arg("bgtz", x),
-        Instruction::blez(x) => format_type_branch_1arg("blez", x),
-        Instruction::bltz(x) => format_type_branch_1arg("bltz", x),
-        Instruction::bltzal(x) => format_type_branch_1arg("bltzal", x),
-        Instruction::bne(x) => format_type_branch_2arg("bne", x),
-        Instruction::lb(x) => format_type_memory("lb", x),
-        Instruction::lbu(x) => format_type_memory("lbu", x),
-        Instruction::lh(x) => format_type_memory("lh", x),
-        Instruction::lhu(x) => format_type_memory("lhu", x),
-        Instruction::lw(x) => format_type_memory("lw", x),
-        Instruction::sb(x) => format_type_memory("sb", x),
-        Instruction::sh(x) => format_type_memory("sh", x),
-        Instruction::sw(x) => format_type_memory("sw", x),
-        Instruction::j(x) => format_type_jump_imm("j", x),
-        Instruction::jal(x) => format_type_jump_imm("jal", x),
-        Instruction::jalr(x) => format_type_jump_reg_linked("jalr", x),
-        Instruction::jr(x) => format_type_jump_reg("jr", x),
-        Instruction::syscall(_) => "syscall".into(),
-        Instruction::invalid(_) => format!("<invalid instruction 0x{:08x}>", ins),
+    DecodedInstruction {
+        mnemonic,
+        operands,
+        fields,
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::assembler::assemble;
+    use crate::assembler::{assemble, assemble_with};
     use crate::memory::EndianMode;
-    use byteorder::ReadBytesExt;
+    use byteorder::{NativeEndian, ReadBytesExt};
     use rayon::prelude::*;
     use std::io::Cursor;
 
+    #[test]
+    fn type_i() {
+        assert_eq!(disassemble(0x2128fffc), "addi $t0, $t1, -4");
+        assert_eq!(disassemble(0x3251ffff), "andi $s1, $s2, 0xffff");
+        assert_eq!(disassemble(0x354604d2), "ori $a2, $t2, 0x4d2");
+        assert_eq!(disassemble(0x3c031234), "lui $v1, 0x1234");
+        assert_eq!(disassemble(0x2c000005), "sltiu $zero, $zero, 5");
+        assert_eq!(disassemble(0x8ca304d2), "lw $v1, 1234($a1)");
+        assert_eq!(disassemble(0xaf91fff4), "sw $s1, -12($gp)");
+        assert_eq!(disassemble(0x121cffff), "beq $s0, $gp, -4");
+        assert_eq!(disassemble(0x15090002), "bne $t0, $t1, 8");
+    }
+
+    #[test]
+    fn mul() {
+        assert_eq!(disassemble(0x71098002), "mul $s0, $t0, $t1");
+        assert_eq!(disassemble(0x71098003), ".word 0x71098003");
+    }
+
+    #[test]
+    fn movn_movz() {
+        assert_eq!(disassemble(0x0109800b), "movn $s0, $t0, $t1");
+        assert_eq!(disassemble(0x0109800a), "movz $s0, $t0, $t1");
+    }
+
+    #[test]
+    fn sync_pref() {
+        assert_eq!(disassemble(0x0000000f), "sync");
+        assert_eq!(disassemble(0x0000040f), "sync 16");
+        assert_eq!(disassemble(0xcfa00004), "pref 0, 4($sp)");
+    }
+
+    #[test]
+    fn fp_arith_s() {
+        assert_eq!(disassemble(0x46020840), "add.s $f1, $f1, $f2");
+        assert_eq!(disassemble(0x461f7f81), "sub.s $f30, $f15, $f31");
+        assert_eq!(disassemble(0x46000002), "mul.s $f0, $f0, $f0");
+        assert_eq!(disassemble(0x46062103), "div.s $f4, $f4, $f6");
+        assert_eq!(disassemble(0xc5020004), "lwc1 $f2, 4($t0)");
+        assert_eq!(disassemble(0xe7a4fffc), "swc1 $f4, -4($sp)");
+        assert_eq!(disassemble(0x44082800), "mfc1 $t0, $f5");
+        assert_eq!(disassemble(0x44893000), "mtc1 $t1, $f6");
+        assert_eq!(disassemble(0x44082801), ".word 0x44082801");
+        // double precision isn't supported yet
+        assert_eq!(disassemble(0x46220840), ".word 0x46220840");
+    }
+
+    #[test]
+    fn opcodes() {
+        let opcodes = supported_opcodes();
+        let mnemonics = crate::assembler::supported_mnemonics();
+
+        for x in &opcodes {
+            assert!(disassemble(x.example).starts_with(x.mnemonic));
+            // whatever the decoder knows, the assembler knows too
+            assert!(mnemonics.contains(&x.mnemonic), "{}", x.mnemonic);
+        }
+
+        let found: Vec<_> = opcodes.iter().map(|x| x.mnemonic).collect();
+        for x in ["add", "bgezal", "add.s", "mtc1", "sync", "pref", "teq"] {
+            assert!(found.contains(&x), "{}", x);
+        }
+    }
+
+    #[test]
+    fn clz_clo() {
+        assert_eq!(disassemble(0x71108020), "clz $s0, $t0");
+        assert_eq!(disassemble(0x71108021), "clo $s0, $t0");
+    }
+
+    #[test]
+    fn unknown_opcode() {
+        assert_eq!(disassemble(0xfc000000), ".word 0xfc000000");
+        assert_eq!(disassemble(0x0000003f), ".word 0x0000003f");
+    }
+
+    #[test]
+    fn any_word() {
+        let options = [
+            DisassembleOptions::default(),
+            DisassembleOptions {
+                abi_names: false,
+                pseudo: true,
+            },
+        ];
+        let check = |ins: u32| {
+            for o in &options {
+                let text = disassemble_with(ins, Some(0x00400000), o);
+                assert!(!text.is_empty(), "{:08x}", ins);
+            }
+            assert!(!disassemble(ins).is_empty(), "{:08x}", ins);
+        };
+
+        // every opcode with every funct and regimm selector, the rest of the bits set or not
+        for opcode in 0..64u32 {
+            for low in 0..64u32 {
+                for rt in [0, 0x01, 0x10, 0x11, 0x1f] {
+                    check(opcode << 26 | rt << 16 | low);
+                    check(opcode << 26 | 0x03e0_ffc0 | rt << 16 | low);
+                }
+            }
+        }
+
+        // plus a fixed xorshift sample of everything else
+        let mut x = 0x2545_f491u32;
+        for _ in 0..200_000 {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            check(x);
+        }
+    }
+
+    #[test]
+    fn symbols() {
+        let code = ".text\nloop: nop\njal func\nj loop\nj 0x00400100\nbne $t0, $zero, loop
+            beq $t0, $t1, 8\nla $t1, func\nlui $t2, 0x40\nfunc: jr $ra";
+        let asm = assemble_with(EndianMode::native(), code, &Default::default()).unwrap();
+        let symbols = asm.labels.into_iter().map(|(k, v)| (v, k)).collect();
+        let o = Default::default();
+
+        let seg = &asm.segments[0];
+        let mut data = Cursor::new(&seg.data);
+        let words: Vec<u32> = (0..seg.data.len() / 4)
+            .map(|_| data.read_u32::<NativeEndian>().unwrap())
+            .collect();
+        let next = |i: usize| {
+            let addr = seg.base_addr + i as u32 * 4;
+            let around = (
+                i.checked_sub(1).map(|x| words[x]),
+                words.get(i + 1).copied(),
+            );
+            disassemble_with_symbols(words[i], Some(addr), &o, &symbols, around)
+        };
+
+        assert_eq!(next(0), "sll $zero, $zero, 0");
+        assert_eq!(next(1), "jal func");
+        assert_eq!(next(2), "j loop");
+        assert_eq!(next(3), "j 0x00400100");
+        assert_eq!(next(4), "bne $t0, $zero, loop");
+        assert_eq!(next(5), "beq $t0, $t1, 8");
+        assert_eq!(next(6), "lui $t1, %hi(func)");
+        assert_eq!(next(7), "ori $t1, $t1, %lo(func)");
+        // a `lui` on its own keeps its number
+        assert_eq!(next(8), "lui $t2, 0x40");
+    }
+
+    #[test]
+    fn fields_type_r() {
+        let r = RegisterName::new;
+        let x = disassemble_fields(0x012a4020);
+        assert_eq!(x.mnemonic, "add");
+        assert_eq!(
+            x.operands,
+            [Register(r(8)), Register(r(9)), Register(r(10))]
+        );
+        assert_eq!(
+            x.fields,
+            RawFields::R {
+                opcode: 0,
+                rs: 9,
+                rt: 10,
+                rd: 8,
+                shamt: 0,
+                funct: 0x20
+            }
+        );
+    }
+
+    #[test]
+    fn fields_type_i() {
+        let r = RegisterName::new;
+        let x = disassemble_fields(0x2128fffc);
+        assert_eq!(x.mnemonic, "addi");
+        assert_eq!(
+            x.operands,
+            [
+                Register(r(8)),
+                Register(r(9)),
+                Operand::Immediate {
+                    value: -4,
+                    hex: None
+                }
+            ]
+        );
+        assert_eq!(
+            x.fields,
+            RawFields::I {
+                opcode: 8,
+                rs: 9,
+                rt: 8,
+                imm: 0xfffc
+            }
+        );
+
+        let x = disassemble_fields(0xaf91fff4);
+        assert_eq!(x.mnemonic, "sw");
+        assert_eq!(
+            x.operands,
+            [
+                Register(r(17)),
+                Operand::Memory {
+                    base: r(28),
+                    offset: -12
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn fields_type_j() {
+        let x = disassemble_fields(0x08100004);
+        assert_eq!(x.mnemonic, "j");
+        assert_eq!(x.operands, [Operand::Address(0x00400010)]);
+        assert_eq!(
+            x.fields,
+            RawFields::J {
+                opcode: 2,
+                target: 0x100004
+            }
+        );
+    }
+
+    #[test]
+    fn fields_invalid() {
+        let x = disassemble_fields(0xfc000000);
+        assert_eq!(x.mnemonic, ".word");
+        assert_eq!(
+            x.fields,
+            RawFields::I {
+                opcode: 0x3f,
+                rs: 0,
+                rt: 0,
+                imm: 0
+            }
+        );
+    }
+
+    #[test]
+    fn type_j() {
+        assert_eq!(disassemble(0x08100004), "j 0x00400010");
+        assert_eq!(disassemble(0x0c100009), "jal 0x00400024");
+        assert_eq!(disassemble_at(0x00400000, 0x08100004), "j 0x00400010");
+
+        // upper bits are taken from the delay slot address
+        assert_eq!(disassemble(0x08000004), "j 0x00000010");
+        assert_eq!(disassemble_at(0x10000000, 0x08000004), "j 0x10000010");
+        assert_eq!(disassemble_at(0x1ffffffc, 0x0c000004), "jal 0x20000010");
+    }
+
+    #[test]
+    fn region() {
+        let code = ".text 0x00400100\nstart:\naddi $t0, $zero, 1\nbeq $t0, $zero, start\nj start";
+        let segs = assemble(EndianMode::native(), code).unwrap();
+        let mut cursor = Cursor::new(&segs[0].data);
+        let words: Vec<u32> = (0..3)
+            .map(|_| cursor.read_u32::<byteorder::NativeEndian>().unwrap())
+            .collect();
+
+        let listing = disassemble_region(segs[0].base_addr, &words);
+        assert_eq!(
+            listing,
+            [
+                (0x00400100, "addi $t0, $zero, 1".to_string()),
+                (0x00400104, "beq $t0, $zero, -8".to_string()),
+                (0x00400108, "j 0x00400100".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn register_names() {
+        assert_eq!(disassemble(0x03e04020), "add $t0, $ra, $zero");
+
+        let numeric = DisassembleOptions {
+            abi_names: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            disassemble_with(0x03e04020, None, &numeric),
+            "add $8, $31, $0"
+        );
+        assert_eq!(
+            disassemble_with(0xaf91fff4, None, &numeric),
+            "sw $17, -12($28)"
+        );
+    }
+
+    #[test]
+    fn pseudo() {
+        let pretty = DisassembleOptions {
+            pseudo: true,
+            ..Default::default()
+        };
+
+        let cases = [
+            (0x00000000, "nop", "sll $zero, $zero, 0"),
+            (0x00094021, "move $t0, $t1", "addu $t0, $zero, $t1"),
+            (0x01204021, "move $t0, $t1", "addu $t0, $t1, $zero"),
+            (0x01204025, "move $t0, $t1", "or $t0, $t1, $zero"),
+            (0x340804d2, "li $t0, 1234", "ori $t0, $zero, 0x4d2"),
+            (0x2408fffc, "li $t0, -4", "addiu $t0, $zero, -4"),
+            // not idioms
+            (
+                0x00000020,
+                "add $zero, $zero, $zero",
+                "add $zero, $zero, $zero",
+            ),
+            (0x00000040, "sll $zero, $zero, 1", "sll $zero, $zero, 1"),
+            (0x00084080, "sll $t0, $t0, 2", "sll $t0, $t0, 2"),
+            (0x352804d2, "ori $t0, $t1, 0x4d2", "ori $t0, $t1, 0x4d2"),
+        ];
+
+        for (ins, expected_pretty, expected_raw) in cases {
+            assert_eq!(disassemble_with(ins, None, &pretty), expected_pretty);
+            assert_eq!(disassemble(ins), expected_raw);
+        }
+    }
+
+    #[test]
+    fn logical() {
+        assert_eq!(disassemble(0x012a4027), "nor $t0, $t1, $t2");
+        assert_eq!(disassemble(0x012a4026), "xor $t0, $t1, $t2");
+    }
+
     #[test]
     #[ignore] // This really takes long time
     fn backtoback() {
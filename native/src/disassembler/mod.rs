@@ -1,3 +1,6 @@
 mod disassemble;
 
-pub use disassemble::disassemble;
+pub use disassemble::{
+    disassemble, disassemble_at, disassemble_fields, disassemble_region, disassemble_with,
+    disassemble_with_symbols, supported_opcodes, DisassembleOptions,
+};
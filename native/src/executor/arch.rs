@@ -1,5 +1,19 @@
-use crate::component::RegisterName;
+use super::random::Random;
+use crate::assembler::AssemblerConfig;
+use crate::component::{FpRegisterName, RegisterName};
 use crate::memory::Memory;
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+/// Initial `$gp`, 32KiB into the data segment so 16-bit offsets reach its first 64KiB.
+pub const INITIAL_GP: u32 = 0x1000_8000;
+
+/// Initial `$sp`, the top of the user stack as in MARS. The stack grows down from here; like
+/// all memory, its pages are allocated when first written.
+pub const INITIAL_SP: u32 = 0x7fff_effc;
+
+/// Most characters of memory-mapped console output kept. Older ones are dropped first.
+pub(super) const MAX_CONSOLE_CHARS: usize = 64 * 1024;
 
 #[repr(C)]
 #[derive(Debug)]
@@ -7,18 +21,135 @@ pub struct Arch {
     // reg[32] is pc
     pub(super) reg: [u32; 33],
 
+    // Guest addresses [code_lo, code_hi) are covered by compiled JIT code.
+    // Stores into that range set code_written, so the JIT can drop stale blocks.
+    pub(super) code_lo: u32,
+    pub(super) code_hi: u32,
+    pub(super) code_written: u32,
+
+    pub(super) hi: u32,
+    pub(super) lo: u32,
+
+    // Loads and stores must fall in [user_lo, user_hi]; see `set_user_space`.
+    pub(super) user_lo: u32,
+    pub(super) user_hi: u32,
+    // Set by compiled code that left before an access it can't do itself, with the pc on
+    // that access. The JIT interprets it next.
+    pub(super) jit_bailed: u32,
+
     // below here is inaccessible from JIT. May use Rust-specific types.
+    // coprocessor-1 registers, as raw bits
+    pub(super) fpr: [u32; 32],
+    // number of instructions executed so far
+    pub(super) retired: u64,
     pub(super) mem: Box<dyn Memory>,
+    // lines the program hasn't read yet, oldest first
+    pub(super) input: VecDeque<String>,
+    // set by the exit syscalls
+    pub(super) exit_code: Option<u32>,
+    // the latest characters written to the memory-mapped console
+    console: VecDeque<char>,
+    // characters ever written to the console, including dropped ones
+    console_written: u64,
+    // the key waiting in the memory-mapped keyboard, if any
+    pub(super) key: Option<u8>,
+    // backs the random syscalls
+    pub(super) random: Random,
 }
 
 impl Arch {
     pub fn new(mem: Box<dyn Memory>) -> Self {
         let mut reg = [0; 33];
-        reg[28] = 0x10008000; // gp
-        reg[29] = 0x7ffffe40; // sp
+        reg[28] = INITIAL_GP;
+        reg[29] = INITIAL_SP;
         reg[32] = 0x00400024; // pc
+        let user_space = AssemblerConfig::default().user_space();
+
+        Arch {
+            reg,
+            code_lo: 0,
+            code_hi: 0,
+            code_written: 0,
+            hi: 0,
+            lo: 0,
+            user_lo: *user_space.start(),
+            user_hi: *user_space.end(),
+            jit_bailed: 0,
+            fpr: [0; 32],
+            retired: 0,
+            mem,
+            input: VecDeque::new(),
+            exit_code: None,
+            console: VecDeque::new(),
+            console_written: 0,
+            key: None,
+            random: Random::new(0),
+        }
+    }
+
+    /// Must be called by executors after they store `len` bytes at `addr`.
+    pub(super) fn note_store(&mut self, addr: u32, len: u32) {
+        let addr = addr as u64;
+        if addr < self.code_hi as u64 && addr + len as u64 > self.code_lo as u64 {
+            self.code_written = 1;
+        }
+    }
+
+    /// Queues input for the read syscalls, one line per read. A trailing newline doesn't
+    /// start another line.
+    pub fn provide_input(&mut self, text: &str) {
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let lines = text.split('\n').map(|x| x.strip_suffix('\r').unwrap_or(x));
+        self.input.extend(lines.map(String::from));
+    }
+
+    /// The last `MAX_CONSOLE_CHARS` characters written to the memory-mapped console at
+    /// 0xffff000c.
+    pub fn console(&self) -> String {
+        self.console.iter().collect()
+    }
 
-        Arch { reg, mem }
+    /// The last `count` characters written to the console, or `None` if some of them were
+    /// already dropped.
+    pub fn console_tail(&self, count: u64) -> Option<String> {
+        let skip = (self.console.len() as u64).checked_sub(count)?;
+        Some(self.console.iter().skip(skip as usize).collect())
+    }
+
+    /// How many characters were ever written to the console.
+    pub fn console_written(&self) -> u64 {
+        self.console_written
+    }
+
+    pub(super) fn push_console(&mut self, c: char) {
+        if self.console.len() == MAX_CONSOLE_CHARS {
+            self.console.pop_front();
+        }
+        self.console.push_back(c);
+        self.console_written += 1;
+    }
+
+    /// Presses a key on the memory-mapped keyboard at 0xffff0000, replacing any key the
+    /// program hasn't read yet.
+    pub fn push_key(&mut self, key: u8) {
+        self.key = Some(key);
+    }
+
+    /// Restarts the random syscalls' sequence. It starts from seed 0, so runs are
+    /// reproducible even without seeding.
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.random = Random::new(seed);
+    }
+
+    /// Where loads and stores may go, besides the memory-mapped I/O registers. Defaults to
+    /// the standard text and data layout.
+    pub fn set_user_space(&mut self, range: RangeInclusive<u32>) {
+        self.user_lo = *range.start();
+        self.user_hi = *range.end();
+    }
+
+    pub fn user_space(&self) -> RangeInclusive<u32> {
+        self.user_lo..=self.user_hi
     }
 
     pub fn mem(&self) -> &dyn Memory {
@@ -41,6 +172,37 @@ impl Arch {
         self.reg[32] = val;
     }
 
+    /// Whether the program has ended: the pc fell below 0x1000, as when `main` returns to
+    /// the initial `$ra` of 0 or an exit syscall runs.
+    pub fn is_finished(&self) -> bool {
+        self.pc() < 0x00001000
+    }
+
+    /// The code passed to the exit syscalls, if one has run.
+    pub fn exit_code(&self) -> Option<u32> {
+        self.exit_code
+    }
+
+    pub fn hi(&self) -> u32 {
+        self.hi
+    }
+
+    pub fn set_hi(&mut self, val: u32) {
+        self.hi = val;
+    }
+
+    pub fn lo(&self) -> u32 {
+        self.lo
+    }
+
+    pub fn set_lo(&mut self, val: u32) {
+        self.lo = val;
+    }
+
+    pub fn retired(&self) -> u64 {
+        self.retired
+    }
+
     pub fn reg(&self, reg: RegisterName) -> u32 {
         self.reg[reg.num() as usize]
     }
@@ -58,4 +220,21 @@ impl Arch {
     pub fn regs_mut(&mut self) -> &mut [u32] {
         &mut self.reg[..32]
     }
+
+    /// The bits of an FP register. Single-precision values are stored as by `f32::to_bits`.
+    pub fn fpr(&self, reg: FpRegisterName) -> u32 {
+        self.fpr[reg.num() as usize]
+    }
+
+    pub fn set_fpr(&mut self, reg: FpRegisterName, val: u32) {
+        self.fpr[reg.num() as usize] = val;
+    }
+
+    pub fn fprs(&self) -> &[u32] {
+        &self.fpr
+    }
+
+    pub fn fprs_mut(&mut self) -> &mut [u32] {
+        &mut self.fpr
+    }
 }
@@ -0,0 +1,177 @@
+/// Largest block a cache may have, a page.
+pub const MAX_BLOCK_SIZE: u32 = 4096;
+
+/// Most blocks a cache may have over all its sets and ways.
+pub const MAX_BLOCKS: u32 = 1 << 16;
+
+/// Shape of a simulated data cache. One way makes it direct-mapped.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CacheConfig {
+    /// Bytes per block, a power of two.
+    pub block_size: u32,
+    /// A power of two.
+    pub sets: u32,
+    /// Blocks per set.
+    pub ways: u32,
+}
+
+impl CacheConfig {
+    /// Whether the sizes are powers of two where they must be, and within [`MAX_BLOCK_SIZE`]
+    /// and [`MAX_BLOCKS`].
+    pub fn is_valid(&self) -> bool {
+        let blocks = self.sets as u64 * self.ways as u64;
+        self.block_size.is_power_of_two()
+            && self.block_size <= MAX_BLOCK_SIZE
+            && self.sets.is_power_of_two()
+            && self.ways > 0
+            && blocks <= MAX_BLOCKS as u64
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Misses out of all accesses, or 0 before the first one.
+    pub fn miss_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.misses as f64 / total as f64,
+        }
+    }
+}
+
+/// Which blocks a cache of some shape would hold, replacing the least recently used one in a
+/// full set. Only tags are kept; memory is never read through it.
+#[derive(Debug, Clone)]
+pub(super) struct Cache {
+    config: CacheConfig,
+    /// Tags by set, most recently used first.
+    sets: Vec<Vec<u32>>,
+    stats: CacheStats,
+}
+
+impl Cache {
+    pub(super) fn new(config: CacheConfig) -> Self {
+        assert!(config.is_valid(), "invalid cache shape {:?}", config);
+
+        Cache {
+            config,
+            sets: vec![Vec::with_capacity(config.ways as usize); config.sets as usize],
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub(super) fn config(&self) -> CacheConfig {
+        self.config
+    }
+
+    pub(super) fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Records an access to the block holding `addr`. Accesses are aligned, so they never
+    /// span two blocks.
+    pub(super) fn access(&mut self, addr: u32) {
+        let block = addr / self.config.block_size;
+        let set = &mut self.sets[(block % self.config.sets) as usize];
+        let tag = block / self.config.sets;
+
+        match set.iter().position(|&x| x == tag) {
+            Some(i) => {
+                set[..=i].rotate_right(1);
+                self.stats.hits += 1;
+            }
+            None => {
+                set.truncate(self.config.ways as usize - 1);
+                set.insert(0, tag);
+                self.stats.misses += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn run(config: CacheConfig, addrs: impl IntoIterator<Item = u32>) -> CacheStats {
+        let mut cache = Cache::new(config);
+        for addr in addrs {
+            cache.access(addr);
+        }
+        cache.stats()
+    }
+
+    #[test]
+    fn sequential_words() {
+        let config = CacheConfig {
+            block_size: 16,
+            sets: 4,
+            ways: 1,
+        };
+
+        // one miss per block, and the second pass finds nothing of a sweep larger than
+        // the cache
+        let stats = run(config, (0..2).flat_map(|_| (0..256).step_by(4)));
+        assert_eq!(stats.misses, 32);
+        assert_eq!(stats.hits, 96);
+        assert_eq!(stats.miss_rate(), 0.25);
+    }
+
+    #[test]
+    fn conflicts() {
+        let direct = CacheConfig {
+            block_size: 16,
+            sets: 4,
+            ways: 1,
+        };
+        let addrs = || (0..8).flat_map(|_| [0, 64]);
+        assert_eq!(run(direct, addrs()).misses, 16);
+
+        let two_way = CacheConfig { ways: 2, ..direct };
+        assert_eq!(run(two_way, addrs()).misses, 2);
+
+        // the least recently used of a full set goes
+        let stats = run(two_way, [0, 64, 0, 128, 0, 64]);
+        assert_eq!(stats.misses, 4);
+    }
+
+    #[test]
+    fn validity() {
+        let config = CacheConfig {
+            block_size: 16,
+            sets: 4,
+            ways: 1,
+        };
+        assert!(config.is_valid());
+        assert!(!CacheConfig { ways: 0, ..config }.is_valid());
+        assert!(!CacheConfig { sets: 3, ..config }.is_valid());
+        assert!(!CacheConfig {
+            block_size: 0,
+            ..config
+        }
+        .is_valid());
+        assert!(!CacheConfig {
+            block_size: MAX_BLOCK_SIZE * 2,
+            ..config
+        }
+        .is_valid());
+        assert!(!CacheConfig {
+            sets: 1 << 31,
+            ways: u32::MAX,
+            ..config
+        }
+        .is_valid());
+        assert!(CacheConfig {
+            sets: MAX_BLOCKS / 4,
+            ways: 4,
+            ..config
+        }
+        .is_valid());
+        assert_eq!(CacheStats::default().miss_rate(), 0.0);
+    }
+}
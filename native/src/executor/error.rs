@@ -1,5 +1,21 @@
 use snafu::prelude::*;
 use snafu::Backtrace;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Access {
+    Load,
+    Store,
+}
+
+impl Display for Access {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Access::Load => write!(f, "load"),
+            Access::Store => write!(f, "store"),
+        }
+    }
+}
 
 #[derive(Debug, Snafu)]
 #[snafu(visibility(pub(super)))]
@@ -9,4 +25,78 @@ pub enum ExecuteError {
 
     #[snafu(display("overflowed arithmetic operation"))]
     ArithmeticOverflow { backtrace: Backtrace },
+
+    #[snafu(display("address error on {access} at 0x{addr:08x}"))]
+    AddressError {
+        addr: u32,
+        access: Access,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("unaligned access at 0x{addr:08x} (must be {align}-byte aligned)"))]
+    UnalignedAccess {
+        addr: u32,
+        align: u32,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("trap (code {code})"))]
+    Trap { code: u32, backtrace: Backtrace },
+
+    #[snafu(display("waiting for input"))]
+    NeedsInput { backtrace: Backtrace },
+
+    #[snafu(display("`{input}` is not an integer"))]
+    InvalidIntegerInput { input: String, backtrace: Backtrace },
+
+    #[snafu(display("random range bound {bound} is not positive"))]
+    InvalidRandomBound { bound: u32, backtrace: Backtrace },
+}
+
+impl ExecuteError {
+    /// A stable name for the variant, for callers that branch on what went wrong rather
+    /// than show the message.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ExecuteError::InvalidInstruction { .. } => "invalidInstruction",
+            ExecuteError::ArithmeticOverflow { .. } => "overflow",
+            ExecuteError::AddressError { .. } => "address",
+            ExecuteError::UnalignedAccess { .. } => "unaligned",
+            ExecuteError::Trap { .. } => "trap",
+            ExecuteError::NeedsInput { .. } => "needsInput",
+            ExecuteError::InvalidIntegerInput { .. } => "invalidInput",
+            ExecuteError::InvalidRandomBound { .. } => "randomBound",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn display_and_kind() {
+        let err = AddressSnafu {
+            addr: 0x10u32,
+            access: Access::Store,
+        }
+        .build();
+        assert_eq!(err.to_string(), "address error on store at 0x00000010");
+        assert_eq!(err.kind(), "address");
+
+        let err = UnalignedAccessSnafu {
+            addr: 0x10000002u32,
+            align: 4u32,
+        }
+        .build();
+        assert_eq!(
+            err.to_string(),
+            "unaligned access at 0x10000002 (must be 4-byte aligned)"
+        );
+        assert_eq!(err.kind(), "unaligned");
+
+        let err = TrapSnafu { code: 7u32 }.build();
+        assert_eq!(err.to_string(), "trap (code 7)");
+        assert_eq!(err.kind(), "trap");
+    }
 }
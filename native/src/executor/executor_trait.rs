@@ -3,6 +3,27 @@ use crate::executor::interpreter::Interpreter;
 use crate::executor::jit::Jit;
 use crate::executor::pipeline::processor::Pipeline;
 use crate::executor::Arch;
+use std::time::{Duration, Instant};
+
+/// How many times [`Executor::run_for`] executes between looks at the clock.
+const CLOCK_CHECK_INTERVAL: u32 = 1024;
+
+/// Why [`Executor::run_to_completion`] stopped.
+#[derive(Debug)]
+pub enum RunResult {
+    /// The program ended, with the code given to the exit syscall, or 0 if it returned.
+    Exited(u32),
+    /// The pc reached this breakpoint. The instruction there has not run yet.
+    Breakpoint(u32),
+    /// The last instruction changed the word holding this watched address.
+    Watchpoint(u32),
+    StepLimit,
+    /// [`Executor::run_for`] ran out of time; running again resumes.
+    TimedOut,
+    /// A read syscall is waiting for [`Arch::provide_input`]; running again retries it.
+    NeedsInput,
+    Error(ExecuteError),
+}
 
 #[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
@@ -37,6 +58,31 @@ impl Executor {
         }
     }
 
+    /// Returns the interpreter backing this executor, if any.
+    pub fn interpreter(&self) -> Option<&Interpreter> {
+        match self {
+            Executor::ExInterpreter(x) => Some(x),
+            Executor::ExJit(x) => Some(x.interpreter()),
+            Executor::ExPipeline(_) => None,
+        }
+    }
+
+    /// Returns the interpreter backing this executor mutably, if any.
+    pub fn interpreter_mut(&mut self) -> Option<&mut Interpreter> {
+        match self {
+            Executor::ExInterpreter(x) => Some(x),
+            Executor::ExJit(x) => Some(x.interpreter_mut()),
+            Executor::ExPipeline(_) => None,
+        }
+    }
+
+    /// Must be called after memory is modified behind the executor's back.
+    pub fn invalidate_range(&mut self, addr: u32, len: u32) {
+        if let Executor::ExJit(x) = self {
+            x.invalidate_range(addr, len);
+        }
+    }
+
     pub fn step(&mut self) -> Result<(), ExecuteError> {
         match self {
             Executor::ExInterpreter(x) => x.step(),
@@ -48,6 +94,78 @@ impl Executor {
         }
     }
 
+    /// Steps until the program ends, the pc reaches a breakpoint, a step changes a watched
+    /// word, or `max_steps` steps have run. A breakpoint at the starting pc is stepped over,
+    /// so calling this again resumes from the last breakpoint.
+    pub fn run_to_completion(
+        &mut self,
+        breakpoints: &[u32],
+        watchpoints: &[u32],
+        max_steps: u64,
+    ) -> RunResult {
+        let watched = |arch: &Arch| -> Vec<u32> {
+            let mem = arch.mem();
+            watchpoints.iter().map(|x| mem.read_u32(x & !3)).collect()
+        };
+        let mut before = watched(self.as_arch());
+
+        for i in 0..max_steps {
+            let arch = self.as_arch();
+            if arch.is_finished() {
+                return RunResult::Exited(arch.exit_code().unwrap_or(0));
+            }
+            if i != 0 && breakpoints.contains(&arch.pc()) {
+                return RunResult::Breakpoint(arch.pc());
+            }
+
+            match self.step() {
+                Ok(()) => {}
+                Err(ExecuteError::NeedsInput { .. }) => return RunResult::NeedsInput,
+                Err(x) => return RunResult::Error(x),
+            }
+
+            if !watchpoints.is_empty() {
+                let after = watched(self.as_arch());
+                if let Some(i) = (0..after.len()).find(|&i| after[i] != before[i]) {
+                    return RunResult::Watchpoint(watchpoints[i]);
+                }
+                before = after;
+            }
+        }
+
+        let arch = self.as_arch();
+        if arch.is_finished() {
+            RunResult::Exited(arch.exit_code().unwrap_or(0))
+        } else {
+            RunResult::StepLimit
+        }
+    }
+
+    /// Executes, using compiled code where there is some, until the program ends or about
+    /// `timeout` has passed.
+    pub fn run_for(&mut self, timeout: Duration) -> RunResult {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            for _ in 0..CLOCK_CHECK_INTERVAL {
+                let arch = self.as_arch();
+                if arch.is_finished() {
+                    return RunResult::Exited(arch.exit_code().unwrap_or(0));
+                }
+
+                match self.exec() {
+                    Ok(()) => {}
+                    Err(ExecuteError::NeedsInput { .. }) => return RunResult::NeedsInput,
+                    Err(x) => return RunResult::Error(x),
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return RunResult::TimedOut;
+            }
+        }
+    }
+
     pub fn exec(&mut self) -> Result<(), ExecuteError> {
         match self {
             Executor::ExInterpreter(x) => x.step(),
@@ -59,3 +177,75 @@ impl Executor {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::component::RegisterName;
+    use crate::memory::{create_memory, EndianMode};
+
+    const TEXT_ADDR: u32 = 0x00400024;
+
+    fn init_executor(asm: &str) -> Executor {
+        let segments = assemble(EndianMode::native(), asm).unwrap();
+        let mem = create_memory(EndianMode::native(), &segments);
+        Executor::ExInterpreter(Interpreter::new(mem))
+    }
+
+    #[test]
+    fn run_exits() {
+        let mut exec = init_executor(".text\nli $a0, 3\nli $v0, 17\nsyscall\nli $a0, 4");
+        let result = exec.run_to_completion(&[], &[], 100);
+        assert!(matches!(result, RunResult::Exited(3)));
+
+        let mut exec = init_executor(".text\nli $v0, 10\nsyscall");
+        let result = exec.run_to_completion(&[], &[], 100);
+        assert!(matches!(result, RunResult::Exited(0)));
+    }
+
+    #[test]
+    fn run_stops_at_breakpoint() {
+        let mut exec = init_executor(".text\nloop: addi $t0, $t0, 1\nj loop");
+
+        let result = exec.run_to_completion(&[TEXT_ADDR], &[], 100);
+        assert!(matches!(result, RunResult::Breakpoint(TEXT_ADDR)));
+        assert_eq!(exec.as_arch().reg(RegisterName::new(8)), 1);
+
+        let result = exec.run_to_completion(&[TEXT_ADDR], &[], 100);
+        assert!(matches!(result, RunResult::Breakpoint(TEXT_ADDR)));
+        assert_eq!(exec.as_arch().reg(RegisterName::new(8)), 2);
+
+        let result = exec.run_to_completion(&[], &[], 10);
+        assert!(matches!(result, RunResult::StepLimit));
+    }
+
+    #[test]
+    fn run_stops_at_watchpoint() {
+        let mut exec = init_executor(
+            "
+            .text
+            lui $t1, 0x1000
+            sw $zero, 0($t1)
+            addi $t0, $zero, 5
+            sb $t0, 2($t1)
+            nop",
+        );
+
+        // storing the value already there changes nothing
+        let result = exec.run_to_completion(&[], &[0x10000001], 100);
+        assert!(matches!(result, RunResult::Watchpoint(0x10000001)));
+        assert_eq!(exec.as_arch().pc(), TEXT_ADDR + 16);
+    }
+
+    #[test]
+    fn run_for_times_out() {
+        let mut exec = init_executor(".text\nloop: j loop");
+        let result = exec.run_for(Duration::from_millis(10));
+        assert!(matches!(result, RunResult::TimedOut));
+
+        let mut exec = init_executor(".text\nli $v0, 10\nsyscall");
+        let result = exec.run_for(Duration::from_secs(1));
+        assert!(matches!(result, RunResult::Exited(0)));
+    }
+}
@@ -1,7 +1,32 @@
-use crate::component::{Instruction, RegisterName, TypeI};
+use crate::component::{FpRegisterName, Instruction, RegisterName, TypeFR, TypeI, TypeR};
+use crate::disassembler::{disassemble_at, disassemble_fields};
+use crate::executor::cache::{Cache, CacheConfig, CacheStats};
 use crate::executor::error::*;
+use crate::executor::predictor::{BranchReport, Predictor};
 use crate::executor::Arch;
 use crate::memory::Memory;
+use rustc_hash::FxHashMap;
+use std::collections::{HashMap, VecDeque};
+use std::mem::Discriminant;
+use std::ops::RangeInclusive;
+
+/// Memory-mapped I/O registers, laid out as in MARS: the keyboard, then the console.
+pub(super) const MMIO: RangeInclusive<u32> = 0xffff_0000..=0xffff_000f;
+
+/// Reads 1 while a key is waiting in the receiver data register.
+const MMIO_RX_CONTROL: u32 = 0xffff_0000;
+
+/// Loading here takes the waiting key, clearing the ready bit.
+const MMIO_RX_DATA: u32 = 0xffff_0004;
+
+/// Reads 1 once the console can take another character, which is always.
+const MMIO_TX_CONTROL: u32 = 0xffff_0008;
+
+/// Storing here appends the low byte to the console.
+const MMIO_TX_DATA: u32 = 0xffff_000c;
+
+/// How many of the most recent instructions a trace keeps.
+pub const TRACE_CAPACITY: usize = 64;
 
 fn branch_offset(x: TypeI) -> u32 {
     (x.imm as i16 as i32 as u32) << 2
@@ -10,6 +35,92 @@ fn branch_offset(x: TypeI) -> u32 {
 #[derive(Debug)]
 pub struct Interpreter {
     arch: Arch,
+    delay_slots: bool,
+    /// Target of a taken branch, applied after the instruction in its delay slot executes.
+    delay_slot: Option<u32>,
+    trace: Option<VecDeque<TraceEntry>>,
+    last_write: Option<(RegisterName, u32)>,
+    changes: Vec<RegChange>,
+    /// Return addresses of calls that haven't returned yet, innermost last.
+    call_stack: Vec<u32>,
+    flags: Option<ArithFlags>,
+    /// Executions by instruction, with a word of each kind to name it by later.
+    profile: Option<FxHashMap<Discriminant<Instruction>, (u32, u64)>>,
+    /// Loads and stores by page, kept while profiling.
+    heatmap: FxHashMap<u32, PageAccesses>,
+    /// Sees every load and store outside MMIO, for its statistics.
+    cache: Option<Cache>,
+    /// Scores a branch predictor on each conditional branch.
+    predictor: Option<Predictor>,
+}
+
+/// How many loads and stores touched one page while profiling.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct PageAccesses {
+    pub reads: u64,
+    pub writes: u64,
+}
+
+/// What an addition or subtraction would set in a flags register, for display only. MIPS
+/// has no such register.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ArithFlags {
+    /// Unsigned overflow: a carry out of an addition, a borrow in a subtraction.
+    pub carry: bool,
+    /// Signed overflow, which `add`, `addi` and `sub` trap on.
+    pub overflow: bool,
+    pub zero: bool,
+    pub negative: bool,
+}
+
+impl ArithFlags {
+    fn of_add(a: u32, b: u32) -> Self {
+        let (val, carry) = a.overflowing_add(b);
+        ArithFlags {
+            carry,
+            overflow: (a as i32).overflowing_add(b as i32).1,
+            zero: val == 0,
+            negative: (val as i32) < 0,
+        }
+    }
+
+    fn of_sub(a: u32, b: u32) -> Self {
+        let (val, carry) = a.overflowing_sub(b);
+        ArithFlags {
+            carry,
+            overflow: (a as i32).overflowing_sub(b as i32).1,
+            zero: val == 0,
+            negative: (val as i32) < 0,
+        }
+    }
+}
+
+/// A register that one step may change.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ChangedReg {
+    Gpr(RegisterName),
+    Fpr(FpRegisterName),
+    Hi,
+    Lo,
+    Pc,
+}
+
+/// A register whose value the most recent step changed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RegChange {
+    pub reg: ChangedReg,
+    pub old: u32,
+    pub new: u32,
+}
+
+/// One executed instruction, as recorded while tracing.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TraceEntry {
+    pub pc: u32,
+    pub ins: u32,
+    pub disasm: String,
+    /// The general purpose register written by the instruction, and its new value.
+    pub changed_reg: Option<(RegisterName, u32)>,
 }
 
 impl Interpreter {
@@ -26,19 +137,303 @@ impl Interpreter {
     }
 
     pub fn new(mem: Box<dyn Memory>) -> Self {
+        Self::from_arch(Arch::new(mem))
+    }
+
+    /// Continues from an existing machine state.
+    pub fn from_arch(arch: Arch) -> Self {
         Interpreter {
-            arch: Arch::new(mem),
+            arch,
+            delay_slots: false,
+            delay_slot: None,
+            trace: None,
+            last_write: None,
+            changes: Vec::new(),
+            call_stack: Vec::new(),
+            flags: None,
+            profile: None,
+            heatmap: FxHashMap::default(),
+            cache: None,
+            predictor: None,
+        }
+    }
+
+    /// Starts or stops recording executed instructions. Stopping discards the recorded ones.
+    pub fn set_trace(&mut self, enabled: bool) {
+        if enabled != self.trace.is_some() {
+            self.trace = enabled.then(VecDeque::new);
+        }
+    }
+
+    /// The most recent executed instructions, oldest first, or `None` if tracing is off.
+    pub fn trace(&self) -> Option<&VecDeque<TraceEntry>> {
+        self.trace.as_ref()
+    }
+
+    /// Enables or disables branch delay slots. Disabled by default, so a taken branch
+    /// jumps immediately.
+    pub fn set_delay_slots(&mut self, enabled: bool) {
+        self.delay_slots = enabled;
+        self.delay_slot = None;
+    }
+
+    pub fn delay_slots(&self) -> bool {
+        self.delay_slots
+    }
+
+    /// Returns true if the next instruction is in a delay slot of a taken branch.
+    pub fn in_delay_slot(&self) -> bool {
+        self.delay_slot.is_some()
+    }
+
+    /// Starts or stops counting executed instructions by mnemonic, and loads and stores by
+    /// page. Stopping discards the counts.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if enabled != self.profile.is_some() {
+            self.profile = enabled.then(FxHashMap::default);
+            self.heatmap.clear();
+        }
+    }
+
+    pub fn profiling(&self) -> bool {
+        self.profile.is_some()
+    }
+
+    /// How many times each mnemonic ran since profiling started or was last reset. Empty
+    /// if profiling is off.
+    pub fn profile(&self) -> HashMap<String, u64> {
+        let profile = self.profile.iter().flatten();
+        profile
+            .map(|(_, &(word, count))| (disassemble_fields(word).mnemonic.to_string(), count))
+            .collect()
+    }
+
+    /// Starts simulating a data cache of this shape, empty and with fresh statistics, or
+    /// stops with `None`.
+    pub fn set_cache(&mut self, config: Option<CacheConfig>) {
+        self.cache = config.map(Cache::new);
+    }
+
+    pub fn cache_config(&self) -> Option<CacheConfig> {
+        self.cache.as_ref().map(|x| x.config())
+    }
+
+    /// Hits and misses of the simulated cache since it was set, if there is one.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|x| x.stats())
+    }
+
+    /// Starts or stops scoring a 2-bit branch predictor. Starting again begins with fresh
+    /// counters.
+    pub fn set_branch_prediction(&mut self, enabled: bool) {
+        self.predictor = enabled.then(Predictor::default);
+    }
+
+    /// How the predictor did since it was started, if it's on.
+    pub fn branch_stats(&self) -> Option<BranchReport> {
+        self.predictor.as_ref().map(|x| x.report())
+    }
+
+    /// Loads and stores by page number, in page order, counted like [`Self::profile`].
+    /// MMIO accesses count towards their page too.
+    pub fn memory_heatmap(&self) -> Vec<(u32, PageAccesses)> {
+        let mut ret: Vec<_> = self.heatmap.iter().map(|(&k, &v)| (k, v)).collect();
+        ret.sort_unstable_by_key(|x| x.0);
+        ret
+    }
+
+    pub fn reset_profile(&mut self) {
+        if let Some(x) = &mut self.profile {
+            x.clear();
+        }
+        self.heatmap.clear();
+    }
+
+    fn count(&mut self, ins: Instruction, word: u32) {
+        if let Some(x) = &mut self.profile {
+            let entry = x.entry(std::mem::discriminant(&ins)).or_insert((word, 0));
+            entry.1 += 1;
         }
     }
 
+    /// Registers changed by the most recent step: GPRs by number, then HI, LO and PC.
+    /// Empty if that step failed or the last instructions didn't run through `step`.
+    pub fn last_changes(&self) -> &[RegChange] {
+        &self.changes
+    }
+
+    /// Flags of the most recent step if it added or subtracted, whether or not it trapped.
+    pub fn last_flags(&self) -> Option<ArithFlags> {
+        self.flags
+    }
+
+    /// Forgets the last step's changes and flags, for callers that run instructions some
+    /// other way.
+    pub(super) fn clear_changes(&mut self) {
+        self.changes.clear();
+        self.flags = None;
+    }
+
+    fn arith_flags(&self, ins: Instruction) -> Option<ArithFlags> {
+        use Instruction::*;
+
+        let imm = |x: TypeI| x.imm as i16 as i32 as u32;
+        match ins {
+            add(x) | addu(x) => Some(ArithFlags::of_add(self.reg(x.rs), self.reg(x.rt))),
+            sub(x) | subu(x) => Some(ArithFlags::of_sub(self.reg(x.rs), self.reg(x.rt))),
+            addi(x) | addiu(x) => Some(ArithFlags::of_add(self.reg(x.rs), imm(x))),
+            _ => None,
+        }
+    }
+
+    /// Return addresses pushed by `jal`/`jalr` and popped by `jr $ra`, innermost last.
+    /// Only calls made through `step` are seen.
+    pub fn call_stack(&self) -> &[u32] {
+        &self.call_stack
+    }
+
+    /// Forgets the call stack, for callers that run calls some other way.
+    pub(super) fn clear_call_stack(&mut self) {
+        self.call_stack.clear();
+    }
+
     pub fn step(&mut self) -> Result<(), ExecuteError> {
-        let ins = Instruction::decode(self.arch.mem.read_u32(self.arch.pc()));
+        let pc = self.arch.pc();
+        let word = self.arch.mem.read_u32(pc);
+        let ins = Instruction::decode(word);
+        self.flags = self.arith_flags(ins);
+        self.changes.clear();
 
         if let Some(x) = ins.as_invalid() {
             return InvalidInstructionSnafu { ins: x }.fail();
         }
 
-        self.execute(ins)
+        let before = (self.arch.reg, self.arch.fpr, self.arch.hi, self.arch.lo);
+
+        if self.trace.is_none() {
+            self.execute(ins)?;
+            self.arch.retired += 1;
+            self.count(ins, word);
+            self.record_changes(before);
+            return Ok(());
+        }
+
+        self.last_write = None;
+        self.execute(ins)?;
+        self.arch.retired += 1;
+        self.count(ins, word);
+        self.record_changes(before);
+
+        let entry = TraceEntry {
+            pc,
+            ins: word,
+            disasm: disassemble_at(pc, word),
+            changed_reg: self.last_write,
+        };
+        let trace = self.trace.as_mut().unwrap();
+        if trace.len() == TRACE_CAPACITY {
+            trace.pop_front();
+        }
+        trace.push_back(entry);
+
+        Ok(())
+    }
+
+    fn record_changes(&mut self, (reg, fpr, hi, lo): ([u32; 33], [u32; 32], u32, u32)) {
+        let arch = &self.arch;
+        let changes = &mut self.changes;
+        let mut push = |reg, old, new| {
+            if old != new {
+                changes.push(RegChange { reg, old, new });
+            }
+        };
+
+        for (i, old) in reg[..32].iter().enumerate() {
+            let name = RegisterName::new(i as u8);
+            push(ChangedReg::Gpr(name), *old, arch.reg[i]);
+        }
+        for (i, old) in fpr.iter().enumerate() {
+            let name = FpRegisterName::new(i as u8);
+            push(ChangedReg::Fpr(name), *old, arch.fpr[i]);
+        }
+        push(ChangedReg::Hi, hi, arch.hi);
+        push(ChangedReg::Lo, lo, arch.lo);
+        push(ChangedReg::Pc, reg[32], arch.reg[32]);
+    }
+
+    /// The address a load or store of `size` bytes accesses. It must be aligned to `size`
+    /// and lie in user space (see [`Arch::set_user_space`]), below the memory limit if
+    /// there is one.
+    fn mem_addr(&self, x: TypeI, size: u32, access: Access) -> Result<u32, ExecuteError> {
+        let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
+
+        if !addr.is_multiple_of(size) {
+            return UnalignedAccessSnafu { addr, align: size }.fail();
+        }
+        let user_space = self.arch.user_space();
+        // aligned, so the last byte doesn't wrap around
+        let in_user_space = user_space.contains(&addr) && user_space.contains(&(addr + size - 1));
+        if !in_user_space && !MMIO.contains(&addr) {
+            return AddressSnafu { addr, access }.fail();
+        }
+        if let Some(limit) = self.arch.mem.limit() {
+            if !MMIO.contains(&addr) && addr as u64 + size as u64 > limit as u64 {
+                return AddressSnafu { addr, access }.fail();
+            }
+        }
+
+        Ok(addr)
+    }
+
+    /// Loads `size` bytes zero-extended. Sub-word loads from I/O see the low bits of the
+    /// register.
+    fn load(&mut self, addr: u32, size: u32) -> u32 {
+        if self.profile.is_some() {
+            self.heatmap.entry(addr / 4096).or_default().reads += 1;
+        }
+
+        if MMIO.contains(&addr) {
+            let val = match addr & !3 {
+                MMIO_RX_CONTROL => self.arch.key.is_some() as u32,
+                MMIO_RX_DATA => self.arch.key.take().unwrap_or(0) as u32,
+                MMIO_TX_CONTROL => 1,
+                _ => 0,
+            };
+            return val & (u32::MAX >> (32 - size * 8));
+        }
+        if let Some(x) = &mut self.cache {
+            x.access(addr);
+        }
+
+        match size {
+            1 => self.arch.mem.read_u8(addr) as u32,
+            2 => self.arch.mem.read_u16(addr) as u32,
+            _ => self.arch.mem.read_u32(addr),
+        }
+    }
+
+    /// Stores the low `size` bytes of `val`.
+    fn store(&mut self, addr: u32, size: u32, val: u32) {
+        if self.profile.is_some() {
+            self.heatmap.entry(addr / 4096).or_default().writes += 1;
+        }
+
+        if MMIO.contains(&addr) {
+            if addr & !3 == MMIO_TX_DATA {
+                self.arch.push_console(char::from(val as u8));
+            }
+            return;
+        }
+        if let Some(x) = &mut self.cache {
+            x.access(addr);
+        }
+
+        match size {
+            1 => self.arch.mem.write_u8(addr, val as u8),
+            2 => self.arch.mem.write_u16(addr, val as u16),
+            _ => self.arch.mem.write_u32(addr, val),
+        }
+        self.arch.note_store(addr, size);
     }
 
     fn reg(&self, reg: RegisterName) -> u32 {
@@ -46,17 +441,114 @@ impl Interpreter {
     }
 
     fn set_reg(&mut self, reg: RegisterName, val: u32) {
-        self.arch.set_reg(reg, val)
+        self.arch.set_reg(reg, val);
+        if self.trace.is_some() && !reg.is_zero() {
+            self.last_write = Some((reg, val));
+        }
     }
 
-    fn handle_syscall(&mut self) {
-        // do nothing (for now)
+    /// Runs the syscall service selected by `$v0`. Services that need input fail with
+    /// `NeedsInput` while none is queued, leaving the pc on the syscall so it can be retried.
+    /// Unsupported services do nothing.
+    fn handle_syscall(&mut self) -> Result<(), ExecuteError> {
+        let v0 = RegisterName::new(2);
+
+        match self.reg(v0) {
+            // read int: one line, into $v0
+            5 => {
+                let line = self.take_input_line()?;
+                let val: i32 = line
+                    .trim()
+                    .parse()
+                    .map_err(|_| InvalidIntegerInputSnafu { input: &line }.build())?;
+                self.set_reg(v0, val as u32);
+            }
+            // exit, and exit with the code in $a0
+            10 => self.arch.exit_code = Some(0),
+            17 => self.arch.exit_code = Some(self.reg(RegisterName::new(4))),
+            // random: seed from $a1, an int into $a0, and an int below $a1 into $a0. Unlike
+            // MARS there is one generator, so the generator id in $a0 is ignored.
+            40 => {
+                let seed = self.reg(RegisterName::new(5));
+                self.arch.set_random_seed(seed as u64);
+            }
+            41 => {
+                let val = self.arch.random.next_u32();
+                self.set_reg(RegisterName::new(4), val);
+            }
+            42 => {
+                let bound = self.reg(RegisterName::new(5));
+                if bound as i32 <= 0 {
+                    return InvalidRandomBoundSnafu { bound }.fail();
+                }
+                let val = self.arch.random.below(bound);
+                self.set_reg(RegisterName::new(4), val);
+            }
+            // read string: like SPIM, one line and its newline, cut to $a1 - 1 bytes
+            // and terminated with a NUL at $a0
+            8 => {
+                let line = self.take_input_line()?;
+                let addr = self.reg(RegisterName::new(4));
+                let len = self.reg(RegisterName::new(5)) as i32;
+                if len <= 0 {
+                    return Ok(());
+                }
+
+                let mut bytes = line.into_bytes();
+                bytes.push(b'\n');
+                bytes.truncate(len as usize - 1);
+                bytes.push(0);
+
+                for (i, x) in bytes.iter().enumerate() {
+                    self.arch.mem.write_u8(addr.wrapping_add(i as u32), *x);
+                }
+                self.arch.note_store(addr, bytes.len() as u32);
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Fails with `Trap` when `cond($rs, $rt)` holds, leaving the pc on the trap.
+    fn trap_if(&self, x: TypeR, cond: fn(u32, u32) -> bool) -> Result<(), ExecuteError> {
+        if cond(self.reg(x.rs), self.reg(x.rt)) {
+            let code = (x.rd.num() as u32) << 5 | x.shamt as u32;
+            return TrapSnafu { code }.fail();
+        }
+        Ok(())
+    }
+
+    /// `fd = op(fs, ft)` in single precision. IEEE-754 results are kept as they are, so
+    /// dividing by zero gives an infinity or NaN rather than an error.
+    fn fp_op_s(&mut self, x: TypeFR, op: fn(f32, f32) -> f32) {
+        let fs = f32::from_bits(self.arch.fpr(x.fs));
+        let ft = f32::from_bits(self.arch.fpr(x.ft));
+        self.arch.set_fpr(x.fd, op(fs, ft).to_bits());
+    }
+
+    fn take_input_line(&mut self) -> Result<String, ExecuteError> {
+        self.arch
+            .input
+            .pop_front()
+            .ok_or_else(|| NeedsInputSnafu.build())
+    }
+
+    /// Returning past several frames (e.g. `longjmp`) unwinds them all. Returning anywhere
+    /// else means the stack no longer matches the program, so it starts over.
+    fn note_return(&mut self, addr: u32) {
+        match self.call_stack.iter().rposition(|&x| x == addr) {
+            Some(i) => self.call_stack.truncate(i),
+            None => self.call_stack.clear(),
+        }
     }
 
     fn execute(&mut self, ins: Instruction) -> Result<(), ExecuteError> {
         use Instruction::*;
 
-        let mut pc = self.arch.pc() + 4;
+        let pc = self.arch.pc() + 4;
+        let link = if self.delay_slots { pc + 4 } else { pc };
+        let mut target = None;
 
         match ins {
             add(x) => match i32::checked_add(self.reg(x.rs) as i32, self.reg(x.rt) as i32) {
@@ -99,6 +591,22 @@ impl Interpreter {
                 let val = self.reg(x.rs) ^ self.reg(x.rt);
                 self.set_reg(x.rd, val);
             }
+            mul(x) => {
+                let val = self.reg(x.rs).wrapping_mul(self.reg(x.rt));
+                self.set_reg(x.rd, val);
+            }
+            movn(x) => {
+                if self.reg(x.rt) != 0 {
+                    self.set_reg(x.rd, self.reg(x.rs));
+                }
+            }
+            movz(x) => {
+                if self.reg(x.rt) == 0 {
+                    self.set_reg(x.rd, self.reg(x.rs));
+                }
+            }
+            clo(x) => self.set_reg(x.rd, self.reg(x.rs).leading_ones()),
+            clz(x) => self.set_reg(x.rd, self.reg(x.rs).leading_zeros()),
             sll(x) => {
                 let val = self.reg(x.rt) << x.shamt;
                 self.set_reg(x.rd, val);
@@ -156,104 +664,172 @@ impl Interpreter {
             }
             beq(x) => {
                 if self.reg(x.rs) == self.reg(x.rt) {
-                    pc = pc.wrapping_add(branch_offset(x));
+                    target = Some(pc.wrapping_add(branch_offset(x)));
                 }
             }
             bgez(x) => {
                 if (self.reg(x.rs) as i32) >= 0 {
-                    pc = pc.wrapping_add(branch_offset(x));
+                    target = Some(pc.wrapping_add(branch_offset(x)));
                 }
             }
             bgezal(x) => {
                 if (self.reg(x.rs) as i32) >= 0 {
-                    self.set_reg(RegisterName::new(31), pc);
-                    pc = pc.wrapping_add(branch_offset(x));
+                    self.set_reg(RegisterName::new(31), link);
+                    target = Some(pc.wrapping_add(branch_offset(x)));
                 }
             }
             bgtz(x) => {
                 if (self.reg(x.rs) as i32) > 0 {
-                    pc = pc.wrapping_add(branch_offset(x));
+                    target = Some(pc.wrapping_add(branch_offset(x)));
                 }
             }
             blez(x) => {
-                if (self.reg(x.rs) as i32) < 0 {
-                    pc = pc.wrapping_add(branch_offset(x));
+                if (self.reg(x.rs) as i32) <= 0 {
+                    target = Some(pc.wrapping_add(branch_offset(x)));
                 }
             }
             bltz(x) => {
                 if (self.reg(x.rs) as i32) < 0 {
-                    pc = pc.wrapping_add(branch_offset(x));
+                    target = Some(pc.wrapping_add(branch_offset(x)));
                 }
             }
             bltzal(x) => {
                 if (self.reg(x.rs) as i32) < 0 {
-                    self.set_reg(RegisterName::new(31), pc);
-                    pc = pc.wrapping_add(branch_offset(x));
+                    self.set_reg(RegisterName::new(31), link);
+                    target = Some(pc.wrapping_add(branch_offset(x)));
                 }
             }
             bne(x) => {
                 if self.reg(x.rs) != self.reg(x.rt) {
-                    pc = pc.wrapping_add(branch_offset(x));
+                    target = Some(pc.wrapping_add(branch_offset(x)));
                 }
             }
             lb(x) => {
-                let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
-                self.set_reg(x.rt, self.arch.mem.read_u8(addr) as i8 as i32 as u32);
+                let addr = self.mem_addr(x, 1, Access::Load)?;
+                let val = self.load(addr, 1) as i8 as i32 as u32;
+                self.set_reg(x.rt, val);
             }
             lbu(x) => {
-                let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
-                self.set_reg(x.rt, self.arch.mem.read_u8(addr) as u32);
+                let addr = self.mem_addr(x, 1, Access::Load)?;
+                let val = self.load(addr, 1);
+                self.set_reg(x.rt, val);
             }
             lh(x) => {
-                let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
-                self.set_reg(x.rt, self.arch.mem.read_u16(addr) as i16 as i32 as u32);
+                let addr = self.mem_addr(x, 2, Access::Load)?;
+                let val = self.load(addr, 2) as i16 as i32 as u32;
+                self.set_reg(x.rt, val);
             }
             lhu(x) => {
-                let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
-                self.set_reg(x.rt, self.arch.mem.read_u16(addr) as u32);
+                let addr = self.mem_addr(x, 2, Access::Load)?;
+                let val = self.load(addr, 2);
+                self.set_reg(x.rt, val);
             }
             lw(x) => {
-                let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
-                self.set_reg(x.rt, self.arch.mem.read_u32(addr));
+                let addr = self.mem_addr(x, 4, Access::Load)?;
+                let val = self.load(addr, 4);
+                self.set_reg(x.rt, val);
             }
             sb(x) => {
-                let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
-                self.arch.mem.write_u8(addr, self.reg(x.rt) as u8);
+                let addr = self.mem_addr(x, 1, Access::Store)?;
+                self.store(addr, 1, self.reg(x.rt));
             }
             sh(x) => {
-                let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
-                self.arch.mem.write_u16(addr, self.reg(x.rt) as u16);
+                let addr = self.mem_addr(x, 2, Access::Store)?;
+                self.store(addr, 2, self.reg(x.rt));
             }
             sw(x) => {
-                let addr = self.reg(x.rs).wrapping_add(x.imm as i16 as i32 as u32);
-                self.arch.mem.write_u32(addr, self.reg(x.rt));
+                let addr = self.mem_addr(x, 4, Access::Store)?;
+                self.store(addr, 4, self.reg(x.rt));
             }
             j(x) => {
                 let addr = (pc & 0xf000_0000) | ((x.target & 0x3ff_ffff) << 2);
-                pc = addr;
+                target = Some(addr);
             }
             jal(x) => {
                 let addr = (pc & 0xf000_0000) | ((x.target & 0x3ff_ffff) << 2);
-                self.set_reg(RegisterName::new(31), pc);
-                pc = addr;
+                self.set_reg(RegisterName::new(31), link);
+                self.call_stack.push(link);
+                target = Some(addr);
             }
             jalr(x) => {
                 let addr = self.reg(x.rs);
-                self.set_reg(x.rd, pc);
-                pc = addr;
+                self.set_reg(x.rd, link);
+                if !x.rd.is_zero() {
+                    self.call_stack.push(link);
+                }
+                target = Some(addr);
             }
             jr(x) => {
-                pc = self.reg(x.rs);
+                let addr = self.reg(x.rs);
+                if x.rs.num() == 31 {
+                    self.note_return(addr);
+                }
+                target = Some(addr);
             }
             syscall(_) => {
-                self.handle_syscall();
+                self.handle_syscall()?;
+                if self.arch.exit_code.is_some() {
+                    // exiting ends the program at once, pending branch or not
+                    self.delay_slot = None;
+                    self.arch.set_pc(0);
+                    return Ok(());
+                }
+            }
+            // there's no cache or reordering to act on
+            sync(_) | pref(_) => {}
+            teq(x) => self.trap_if(x, |a, b| a == b)?,
+            tge(x) => self.trap_if(x, |a, b| a as i32 >= b as i32)?,
+            tgeu(x) => self.trap_if(x, |a, b| a >= b)?,
+            tlt(x) => self.trap_if(x, |a, b| (a as i32) < b as i32)?,
+            tltu(x) => self.trap_if(x, |a, b| a < b)?,
+            tne(x) => self.trap_if(x, |a, b| a != b)?,
+            add_s(x) => self.fp_op_s(x, |a, b| a + b),
+            sub_s(x) => self.fp_op_s(x, |a, b| a - b),
+            mul_s(x) => self.fp_op_s(x, |a, b| a * b),
+            div_s(x) => self.fp_op_s(x, |a, b| a / b),
+            lwc1(x) => {
+                let addr = self.mem_addr(x, 4, Access::Load)?;
+                let val = self.load(addr, 4);
+                self.arch.set_fpr(FpRegisterName::new(x.rt.num()), val);
+            }
+            swc1(x) => {
+                let addr = self.mem_addr(x, 4, Access::Store)?;
+                self.store(addr, 4, self.arch.fpr(FpRegisterName::new(x.rt.num())));
+            }
+            mfc1(x) => {
+                let val = self.arch.fpr(FpRegisterName::new(x.rd.num()));
+                self.set_reg(x.rt, val);
+            }
+            mtc1(x) => {
+                let val = self.reg(x.rt);
+                self.arch.set_fpr(FpRegisterName::new(x.rd.num()), val);
             }
             invalid(x) => {
                 return InvalidInstructionSnafu { ins: x }.fail();
             }
         }
 
-        self.arch.set_pc(pc);
+        // Branches on $zero alone, like `b` and `bal`, always go the same way.
+        let is_conditional = match ins {
+            beq(x) | bne(x) => !(x.rs.is_zero() && x.rt.is_zero()),
+            bgez(x) | bgezal(x) | bgtz(x) | blez(x) | bltz(x) | bltzal(x) => !x.rs.is_zero(),
+            _ => false,
+        };
+        if let (true, Some(x)) = (is_conditional, &mut self.predictor) {
+            x.record(pc.wrapping_sub(4), target.is_some());
+        }
+
+        // A branch sitting in a delay slot is unpredictable on real hardware;
+        // here the pending branch wins.
+        if let Some(x) = self.delay_slot.take() {
+            self.arch.set_pc(x);
+        } else if let (true, Some(x)) = (self.delay_slots, target) {
+            self.delay_slot = Some(x);
+            self.arch.set_pc(pc);
+        } else {
+            self.arch.set_pc(target.unwrap_or(pc));
+        }
+
         Ok(())
     }
 }
@@ -262,7 +838,8 @@ impl Interpreter {
 mod test {
     use super::*;
     use crate::assembler::assemble;
-    use crate::memory::{create_memory, EndianMode};
+    use crate::executor::arch::{INITIAL_GP, INITIAL_SP, MAX_CONSOLE_CHARS};
+    use crate::memory::{create_memory, create_memory_with, EndianMode, MemoryConfig};
 
     const TEXT_ADDR: u32 = 0x00400024;
 
@@ -293,6 +870,95 @@ mod test {
         assert_eq!(state.arch.reg[18], 3);
     }
 
+    #[test]
+    fn arith_flags() {
+        let mut state = init_state(".text\naddu $t2, $t0, $t1\nsubu $t3, $t0, $t0\nsll $0, $0, 0");
+        state.arch.reg[8] = 0x7fff_ffff;
+        state.arch.reg[9] = 1;
+
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[10], 0x8000_0000);
+        let expected = ArithFlags {
+            carry: false,
+            overflow: true,
+            zero: false,
+            negative: true,
+        };
+        assert_eq!(state.last_flags(), Some(expected));
+
+        state.step().unwrap();
+        let flags = state.last_flags().unwrap();
+        assert!(flags.zero && !flags.carry && !flags.overflow);
+
+        state.step().unwrap();
+        assert_eq!(state.last_flags(), None);
+
+        // a trapping add still reports why
+        let mut state = init_state(".text\naddi $t0, $t0, -1");
+        state.arch.reg[8] = 0x8000_0000;
+        state.step().unwrap_err();
+        assert!(state.last_flags().unwrap().overflow);
+        assert!(state.last_flags().unwrap().carry);
+    }
+
+    #[test]
+    fn add_changes() {
+        let mut state = init_state(".text\nadd $18, $16, $17");
+        state.arch.reg[16] = 1;
+        state.arch.reg[17] = 2;
+        state.step().unwrap();
+
+        assert_eq!(
+            state.last_changes(),
+            [
+                RegChange {
+                    reg: ChangedReg::Gpr(RegisterName::new(18)),
+                    old: 0,
+                    new: 3,
+                },
+                RegChange {
+                    reg: ChangedReg::Pc,
+                    old: TEXT_ADDR,
+                    new: TEXT_ADDR + 4,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_clears_changes() {
+        let mut state = init_state(".text\naddi $8, $0, 1\n.word 0xffffffff");
+        state.step().unwrap();
+        assert!(!state.last_changes().is_empty());
+
+        assert!(state.step().is_err());
+        assert!(state.last_changes().is_empty());
+    }
+
+    #[test]
+    fn add_s_changes() {
+        let mut state = init_state(".text\nadd.s $f3, $f1, $f2");
+        state.arch.fpr[1] = 1.0f32.to_bits();
+        state.arch.fpr[2] = 2.0f32.to_bits();
+        state.step().unwrap();
+
+        assert_eq!(
+            state.last_changes(),
+            [
+                RegChange {
+                    reg: ChangedReg::Fpr(FpRegisterName::new(3)),
+                    old: 0,
+                    new: 3.0f32.to_bits(),
+                },
+                RegChange {
+                    reg: ChangedReg::Pc,
+                    old: TEXT_ADDR,
+                    new: TEXT_ADDR + 4,
+                },
+            ]
+        );
+    }
+
     #[test]
     fn sub() {
         let mut state =
@@ -324,6 +990,38 @@ mod test {
         assert_eq!(state.arch.reg[18], 13 | 9);
     }
 
+    #[test]
+    fn addu_subu_wrap() {
+        let mut state =
+            init_state(".text\naddu $18, $16, $17\nsubu $19, $18, $17\nadd $20, $16, $17");
+        state.arch.reg[16] = 0xffffffff;
+        state.arch.reg[17] = 1;
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[18], 0);
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[19], 0xffffffff);
+
+        state.arch.reg[16] = i32::MAX as u32;
+        if let Err(ExecuteError::ArithmeticOverflow { .. }) = state.step() {
+            // ok
+        } else {
+            panic!("expected ArithmeticOverflow");
+        }
+        assert_eq!(state.arch.reg[20], 0);
+    }
+
+    #[test]
+    fn nor_xor() {
+        let mut state = init_state(".text\nnor $18, $16, $17\nxor $18, $16, $17");
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[18], 0xffffffff);
+
+        state.arch.reg[16] = 0b1100;
+        state.arch.reg[17] = 0b1010;
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[18], 0b0110);
+    }
+
     #[test]
     fn slt() {
         let mut state = init_state(
@@ -350,6 +1048,553 @@ mod test {
         assert_eq!(state.arch.reg[18], 1);
     }
 
+    #[test]
+    fn sltu() {
+        let mut state = init_state(".text\nslt $18, $16, $17\nsltu $19, $16, $17");
+        state.arch.reg[16] = 0xffff_ffff;
+        state.arch.reg[17] = 1;
+        state.step().unwrap();
+        state.step().unwrap();
+
+        // -1 < 1 as signed, but 0xffffffff > 1 as unsigned
+        assert_eq!(state.arch.reg[18], 1);
+        assert_eq!(state.arch.reg[19], 0);
+    }
+
+    #[test]
+    fn mul() {
+        let mut state = init_state(".text\nmul $18, $16, $17\nmul $19, $16, $16");
+        state.arch.reg[16] = -70000i32 as u32;
+        state.arch.reg[17] = 3;
+        state.step().unwrap();
+        state.step().unwrap();
+
+        assert_eq!(state.arch.reg[18], -210000i32 as u32);
+        // 70000^2 doesn't fit in 32 bits; only the low word is kept
+        assert_eq!(state.arch.reg[19], 70000u64.pow(2) as u32);
+        assert_eq!((state.arch.hi, state.arch.lo), (0, 0));
+    }
+
+    #[test]
+    fn sync_pref_do_nothing() {
+        let asm = ".text
+            sync
+            pref 0, 0($zero)
+            pref 1, 4($sp)";
+        let mut state = init_state(asm);
+        let regs = state.arch.reg;
+        for _ in 0..3 {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.pc(), 0x00400030);
+        assert_eq!(state.arch.reg[..32], regs[..32]);
+    }
+
+    #[test]
+    fn fp_arith_s() {
+        let asm = ".text
+            add.s $f0, $f1, $f2
+            sub.s $f3, $f1, $f2
+            mul.s $f4, $f1, $f2
+            div.s $f5, $f1, $f2
+            div.s $f6, $f1, $f7";
+        let mut state = init_state(asm);
+        state.arch.fpr[1] = 1.5f32.to_bits();
+        state.arch.fpr[2] = 0.25f32.to_bits();
+        let regs = state.arch.reg;
+        for _ in 0..5 {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.fpr[0], 1.75f32.to_bits());
+        assert_eq!(state.arch.fpr[3], 1.25f32.to_bits());
+        assert_eq!(state.arch.fpr[4], 0.375f32.to_bits());
+        assert_eq!(state.arch.fpr[5], 6.0f32.to_bits());
+        assert_eq!(state.arch.fpr[6], f32::INFINITY.to_bits());
+        // the integer registers are a separate file
+        assert_eq!(state.arch.reg[..32], regs[..32]);
+    }
+
+    #[test]
+    fn lwc1_swc1() {
+        let test = |endian| {
+            let asm = ".data
+                src: .word 0x3fc00000
+                dst: .word 0
+                .text
+                lui $t0, 0x1000
+                lwc1 $f2, 0($t0)
+                swc1 $f2, 4($t0)";
+            let segments = assemble(endian, asm).unwrap();
+            let mut state = Interpreter::new(create_memory(endian, &segments));
+            for _ in 0..3 {
+                state.step().unwrap();
+            }
+
+            assert_eq!(state.arch.fpr[2], 1.5f32.to_bits());
+            let mut src = [0; 4];
+            let mut dst = [0; 4];
+            state.arch.mem.read_into_slice(0x1000_0000, &mut src);
+            state.arch.mem.read_into_slice(0x1000_0004, &mut dst);
+            assert_eq!(src, dst);
+        };
+
+        test(EndianMode::Little);
+        test(EndianMode::Big);
+    }
+
+    #[test]
+    fn mtc1_mfc1() {
+        let mut state = init_state(".text\nmtc1 $t0, $f5\nmfc1 $t1, $f5");
+        state.arch.reg[8] = 0xffc0_0001; // a NaN, copied without conversion
+        state.step().unwrap();
+        assert_eq!(state.arch.fpr[5], 0xffc0_0001);
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[9], 0xffc0_0001);
+    }
+
+    #[test]
+    fn movn_movz() {
+        let asm = ".text
+            movn $18, $16, $17
+            movz $19, $16, $17
+            movn $20, $16, $zero
+            movz $21, $16, $zero";
+        let mut state = init_state(asm);
+        state.arch.reg[16] = 5;
+        state.arch.reg[17] = 1;
+        for x in 18..=21 {
+            state.arch.reg[x] = 7;
+        }
+        for _ in 0..4 {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.reg[18], 5);
+        assert_eq!(state.arch.reg[19], 7);
+        assert_eq!(state.arch.reg[20], 7);
+        assert_eq!(state.arch.reg[21], 5);
+    }
+
+    #[test]
+    fn clz_clo() {
+        let mut state = init_state(".text\nclz $17, $16\nclz $18, $zero\nclo $19, $16");
+        state.arch.reg[16] = 0x0000_ffff;
+        for _ in 0..3 {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.reg[17], 16);
+        assert_eq!(state.arch.reg[18], 32);
+        assert_eq!(state.arch.reg[19], 0);
+    }
+
+    #[test]
+    fn teq() {
+        let mut state = init_state(".text\nteq $16, $17\nteq $16, $17");
+        state.arch.reg[16] = 1;
+        state.step().unwrap();
+        assert_eq!(state.arch.pc(), TEXT_ADDR + 4);
+
+        state.arch.reg[17] = 1;
+        let err = state.step().unwrap_err();
+        assert!(matches!(err, ExecuteError::Trap { code: 0, .. }));
+        assert_eq!(state.arch.pc(), TEXT_ADDR + 4);
+    }
+
+    #[test]
+    fn trap_compares_signed_or_unsigned() {
+        let mut state = init_state(".text\ntlt $16, $17\ntltu $16, $17");
+        state.arch.reg[16] = 0xffff_ffff;
+        state.arch.reg[17] = 1;
+
+        // -1 < 1 as signed, but not as unsigned
+        assert!(state.step().is_err());
+        state.arch.set_pc(TEXT_ADDR + 4);
+        state.step().unwrap();
+    }
+
+    #[test]
+    fn abs() {
+        let mut state = init_state(".text\nabs $17, $16\nabs $18, $17");
+        state.arch.reg[16] = -1234i32 as u32;
+        for _ in 0..6 {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.reg[17], 1234);
+        assert_eq!(state.arch.reg[18], 1234);
+    }
+
+    #[test]
+    fn mmio_console() {
+        let asm = ".text
+            lui $8, 0xffff
+            lw $10, 8($8)
+            addiu $9, $zero, 72
+            sb $9, 12($8)
+            addiu $9, $zero, 105
+            sw $9, 12($8)
+            lw $11, 12($8)";
+        let mut state = init_state(asm);
+        for _ in 0..7 {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.console(), "Hi");
+        assert_eq!(state.arch.reg[10], 1);
+        assert_eq!(state.arch.reg[11], 0);
+    }
+
+    #[test]
+    fn mmio_console_bounded() {
+        let mut state = init_state(".text\nlui $8, 0xffff\nloop: sb $9, 12($8)\nj loop");
+        state.arch.reg[9] = 'a' as u32;
+        state.step().unwrap();
+        for _ in 0..2 * MAX_CONSOLE_CHARS {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.console_written(), MAX_CONSOLE_CHARS as u64);
+        state.arch.reg[9] = 'b' as u32;
+        state.step().unwrap();
+        state.step().unwrap();
+
+        assert_eq!(state.arch.console().len(), MAX_CONSOLE_CHARS);
+        assert_eq!(state.arch.console_tail(2).unwrap(), "ab");
+        assert_eq!(state.arch.console_written(), MAX_CONSOLE_CHARS as u64 + 1);
+        assert_eq!(state.arch.console_tail(MAX_CONSOLE_CHARS as u64 + 1), None);
+    }
+
+    #[test]
+    fn mmio_keyboard() {
+        let asm = ".text
+            lui $8, 0xffff
+            poll:
+            lw $9, 0($8)
+            beq $9, $zero, poll
+            lbu $10, 4($8)
+            lw $11, 0($8)";
+        let mut state = init_state(asm);
+        for _ in 0..4 {
+            state.step().unwrap();
+        }
+        assert_eq!(state.arch.pc(), 0x0040002c);
+
+        state.arch.push_key(b'k');
+        for _ in 0..5 {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.reg[10], b'k' as u32);
+        assert_eq!(state.arch.reg[11], 0);
+    }
+
+    #[test]
+    fn random_syscalls() {
+        let asm = ".text
+            addiu $v0, $zero, 40
+            addiu $a1, $zero, 1234
+            syscall
+            addiu $v0, $zero, 41
+            syscall
+            addu $16, $a0, $zero
+            addiu $v0, $zero, 42
+            addiu $a1, $zero, 100
+            syscall
+            addiu $a1, $zero, 0
+            syscall";
+        let mut state = init_state(asm);
+        for _ in 0..9 {
+            state.step().unwrap();
+        }
+
+        assert_eq!(state.arch.reg[16], 0xbb0c_f61b);
+        assert_eq!(state.arch.reg[4], 59);
+
+        state.step().unwrap();
+        let err = state.step().unwrap_err();
+        assert!(matches!(
+            err,
+            ExecuteError::InvalidRandomBound { bound: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn stack_push_pop() {
+        let mut state = init_state(
+            ".text\naddi $sp, $sp, -4\nsw $16, 0($sp)\nlw $17, 0($sp)\naddi $sp, $sp, 4",
+        );
+        assert_eq!(state.arch.reg[28], INITIAL_GP);
+        assert_eq!(state.arch.reg[29], INITIAL_SP);
+
+        let page_idx = (INITIAL_SP - 4) / 4096;
+        assert!(!state.arch.mem.is_page_mapped(page_idx));
+
+        state.arch.reg[16] = 0xcafe;
+        for _ in 0..4 {
+            state.step().unwrap();
+        }
+
+        assert!(state.arch.mem.is_page_mapped(page_idx));
+        assert_eq!(state.arch.reg[17], 0xcafe);
+        assert_eq!(state.arch.reg[29], INITIAL_SP);
+    }
+
+    #[test]
+    fn mem_unaligned() {
+        let mut state = init_state(".text\nlw $16, 2($gp)\nlh $16, 1($gp)\nlb $16, 1($gp)");
+
+        let err = state.step().unwrap_err();
+        assert!(matches!(
+            err,
+            ExecuteError::UnalignedAccess {
+                addr: 0x10008002,
+                align: 4,
+                ..
+            }
+        ));
+        assert_eq!(state.arch.pc(), TEXT_ADDR);
+
+        state.arch.set_pc(TEXT_ADDR + 4);
+        let err = state.step().unwrap_err();
+        assert!(matches!(
+            err,
+            ExecuteError::UnalignedAccess { align: 2, .. }
+        ));
+
+        // bytes are always aligned
+        state.arch.set_pc(TEXT_ADDR + 8);
+        state.step().unwrap();
+    }
+
+    #[test]
+    fn mem_out_of_range() {
+        let mut state = init_state(".text\nsw $16, 0($17)\nlw $16, -4($17)");
+        state.arch.reg[16] = 1234;
+        state.arch.reg[17] = 0x8000_0000;
+
+        let err = state.step().unwrap_err();
+        assert!(matches!(
+            err,
+            ExecuteError::AddressError {
+                addr: 0x8000_0000,
+                access: Access::Store,
+                ..
+            }
+        ));
+        assert_eq!(state.arch.mem.read_u32(0x8000_0000), 0);
+
+        // the last word of user space is fine
+        state.arch.set_pc(TEXT_ADDR + 4);
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[16], 0);
+    }
+
+    #[test]
+    fn profile() {
+        let mut state = init_state(
+            ".text\naddi $8, $0, 5\nloop: add $9, $9, $8\naddi $8, $8, -1\nbne $8, $0, loop",
+        );
+        state.set_profiling(true);
+        for _ in 0..16 {
+            state.step().unwrap();
+        }
+
+        let profile = state.profile();
+        assert_eq!(profile.len(), 3);
+        assert_eq!(profile["add"], 5);
+        assert_eq!(profile["addi"], 6);
+        assert_eq!(profile["bne"], 5);
+
+        state.reset_profile();
+        assert!(state.profile().is_empty());
+        assert!(state.profiling());
+
+        state.set_profiling(false);
+        state.as_arch_mut().set_pc(TEXT_ADDR);
+        state.step().unwrap();
+        assert!(state.profile().is_empty());
+    }
+
+    #[test]
+    fn memory_heatmap() {
+        let code = ".text\nlui $16, 0x1001\nsw $0, 0x1000($16)\nli $8, 10\n\
+                    loop: lw $9, 0($16)\nsw $9, 0($16)\naddi $8, $8, -1\nbne $8, $0, loop";
+        let mut state = init_state(code);
+        state.set_profiling(true);
+        for _ in 0..43 {
+            state.step().unwrap();
+        }
+
+        let heatmap = state.memory_heatmap();
+        assert_eq!(heatmap.len(), 2);
+        let (page, hot) = heatmap[0];
+        assert_eq!(page, 0x10010);
+        assert_eq!(
+            hot,
+            PageAccesses {
+                reads: 10,
+                writes: 10
+            }
+        );
+        assert_eq!(
+            heatmap[1],
+            (
+                0x10011,
+                PageAccesses {
+                    reads: 0,
+                    writes: 1
+                }
+            )
+        );
+
+        state.reset_profile();
+        assert!(state.memory_heatmap().is_empty());
+    }
+
+    #[test]
+    fn cache_stride() {
+        // eight loads 16 bytes apart, then a store to the console
+        let code = ".text\nlui $16, 0x1001\nli $8, 8\n\
+                    loop: lw $9, 0($16)\naddi $16, $16, 16\naddi $8, $8, -1\nbne $8, $0, loop\n\
+                    lui $17, 0xffff\nsw $0, 12($17)";
+        let run = |block_size| {
+            let mut state = init_state(code);
+            state.set_cache(Some(CacheConfig {
+                block_size,
+                sets: 8,
+                ways: 1,
+            }));
+            for _ in 0..36 {
+                state.step().unwrap();
+            }
+            state.cache_stats().unwrap()
+        };
+
+        // a block per load, then two loads per block
+        assert_eq!(run(16), CacheStats { hits: 0, misses: 8 });
+        assert_eq!(run(32), CacheStats { hits: 4, misses: 4 });
+    }
+
+    #[test]
+    fn branch_prediction() {
+        let code = ".text\nli $8, 10\nloop: addi $8, $8, -1\nbne $8, $0, loop\nnop";
+        let mut state = init_state(code);
+        state.set_branch_prediction(true);
+        for _ in 0..21 {
+            state.step().unwrap();
+        }
+
+        // taken nine times, then not: wrong on the first taken one and on the exit
+        let report = state.branch_stats().unwrap();
+        assert_eq!(report.total.mispredicted, 2);
+        assert_eq!(report.total.predicted, 8);
+        assert_eq!(report.branches, [(TEXT_ADDR + 8, report.total)]);
+        assert_eq!(state.as_arch().pc(), TEXT_ADDR + 12);
+
+        state.set_branch_prediction(false);
+        assert!(state.branch_stats().is_none());
+    }
+
+    #[test]
+    fn branch_prediction_skips_unconditional() {
+        let mut state = init_state(".text\nb next\nnext: bal end\nnop\nend: beq $0, $0, 0");
+        state.set_branch_prediction(true);
+        state.step().unwrap();
+        state.step().unwrap();
+        state.step().unwrap();
+
+        assert_eq!(state.branch_stats().unwrap(), BranchReport::default());
+
+        state.set_branch_prediction(false);
+        assert!(state.branch_stats().is_none());
+    }
+
+    #[test]
+    fn mem_user_space() {
+        let mut state = init_state(".text\nsw $16, -4($17)\nsw $16, 0($17)");
+        state.arch.set_user_space(0x0040_0000..=0x1000_ffff);
+        state.arch.reg[17] = 0x1001_0000;
+
+        state.step().unwrap();
+        let err = state.step().unwrap_err();
+        assert!(matches!(
+            err,
+            ExecuteError::AddressError {
+                addr: 0x1001_0000,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn mem_limit() {
+        let segments = assemble(
+            EndianMode::native(),
+            ".text\nsw $16, -4($17)\nlw $18, -4($17)\nlw $18, 0($17)",
+        )
+        .unwrap();
+        let config = MemoryConfig {
+            limit: Some(0x1001_0000),
+            ..Default::default()
+        };
+        let mut state =
+            Interpreter::new(create_memory_with(EndianMode::native(), &segments, config));
+        state.arch.reg[16] = 1234;
+        state.arch.reg[17] = 0x1001_0000;
+
+        state.step().unwrap();
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[18], 1234);
+
+        let err = state.step().unwrap_err();
+        assert!(matches!(
+            err,
+            ExecuteError::AddressError {
+                addr: 0x1001_0000,
+                access: Access::Load,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn syscall_read_int() {
+        let mut state = init_state(".text\nli $v0, 5\nsyscall\nli $v0, 5\nsyscall");
+        state.step().unwrap();
+
+        // nothing queued: the syscall waits and can be retried
+        let err = state.step().unwrap_err();
+        assert!(matches!(err, ExecuteError::NeedsInput { .. }));
+        assert_eq!(state.arch.pc(), TEXT_ADDR + 4);
+
+        state.arch.provide_input("42\n-7\n");
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[2], 42);
+        state.step().unwrap();
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[2], -7_i32 as u32);
+    }
+
+    #[test]
+    fn syscall_read_string() {
+        let mut state = init_state(
+            ".text\nli $v0, 8\nli $a0, 0x10000000\nli $a1, 4\nsyscall\nli $a1, 8\nsyscall",
+        );
+        state.arch.provide_input("hello\nhi");
+        // `li $a0` is two instructions
+        for _ in 0..7 {
+            state.step().unwrap();
+        }
+
+        let mut buf = [0; 4];
+        state.arch.mem.read_into_slice(0x10000000, &mut buf);
+        // the second read overwrote the first, which was cut to 3 bytes
+        assert_eq!(&buf, b"hi\n\0");
+    }
+
     #[test]
     fn mem() {
         let mut state = init_state(".data 0x10008000\n.word -1234, 1234\n.text\nlw $16, 0($gp)\nlw $16, 4($gp)\nadd $16, $16, $17\nsw $16, 8($gp)");
@@ -393,6 +1638,184 @@ mod test {
         assert_eq!(state.arch.pc(), 0x1234);
     }
 
+    #[test]
+    fn branch_on_zero() {
+        let cases = [
+            ("bgez", [false, true, true]),
+            ("bltz", [true, false, false]),
+            ("bgtz", [false, false, true]),
+            ("blez", [true, true, false]),
+        ];
+
+        for (mnemonic, taken) in cases {
+            for (&val, &taken) in [-1_i32, 0, 1].iter().zip(taken.iter()) {
+                let mut state = init_state(&format!(
+                    ".text\n{} $16, target\nnop\ntarget:\nnop",
+                    mnemonic
+                ));
+                state.arch.reg[16] = val as u32;
+                state.step().unwrap();
+
+                let expected = if taken { 8 } else { 4 };
+                assert_eq!(
+                    state.arch.pc(),
+                    TEXT_ADDR + expected,
+                    "{} with {}",
+                    mnemonic,
+                    val
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn branch_always() {
+        let mut state = init_state(".text\nb skip\nnop\nskip:\nbal func\nnop\nfunc:\nnop");
+        state.step().unwrap();
+        assert_eq!(state.arch.pc(), TEXT_ADDR + 8);
+
+        state.step().unwrap();
+        assert_eq!(state.arch.pc(), TEXT_ADDR + 16);
+        assert_eq!(state.arch.reg[31], TEXT_ADDR + 12);
+    }
+
+    #[test]
+    fn delay_slot() {
+        let asm = ".text
+            addi $8, $0, 0
+            beq $0, $0, skip
+            addi $8, $8, 1
+            addi $8, $8, 10
+            skip:
+            addi $9, $0, 5";
+
+        let mut state = init_state(asm);
+        for _ in 0..3 {
+            state.step().unwrap();
+        }
+        assert_eq!(state.arch.reg[8], 0);
+        assert_eq!(state.arch.reg[9], 5);
+
+        let mut state = init_state(asm);
+        state.set_delay_slots(true);
+        state.step().unwrap();
+        state.step().unwrap();
+        assert!(state.in_delay_slot());
+        assert_eq!(state.arch.pc(), TEXT_ADDR + 8);
+        state.step().unwrap();
+        assert!(!state.in_delay_slot());
+        assert_eq!(state.arch.pc(), TEXT_ADDR + 16);
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[8], 1);
+        assert_eq!(state.arch.reg[9], 5);
+    }
+
+    #[test]
+    fn delay_slot_link() {
+        let asm = ".text\njal 0x00401000\nnop";
+
+        let mut state = init_state(asm);
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[31], TEXT_ADDR + 4);
+        assert_eq!(state.arch.pc(), 0x00401000);
+
+        let mut state = init_state(asm);
+        state.set_delay_slots(true);
+        state.step().unwrap();
+        state.step().unwrap();
+        assert_eq!(state.arch.reg[31], TEXT_ADDR + 8);
+        assert_eq!(state.arch.pc(), 0x00401000);
+    }
+
+    #[test]
+    fn call_stack() {
+        let asm = r#"
+            .text
+            main:
+                jal outer
+                nop
+            outer:
+                addu $s0, $ra, $zero
+                jal inner
+                addu $ra, $s0, $zero
+                jr $ra
+            inner:
+                jr $ra
+        "#;
+        let mut state = init_state(asm);
+
+        let mut depths = Vec::new();
+        for _ in 0..6 {
+            state.step().unwrap();
+            depths.push(state.call_stack().len());
+        }
+        assert_eq!(depths, [1, 1, 2, 1, 1, 0]);
+        assert_eq!(state.arch.pc(), TEXT_ADDR + 4);
+
+        // a return to somewhere never called from starts over
+        state.arch.set_pc(TEXT_ADDR);
+        state.step().unwrap();
+        state.step().unwrap();
+        state.step().unwrap();
+        assert_eq!(state.call_stack(), [TEXT_ADDR + 4, TEXT_ADDR + 16]);
+        state.arch.reg[31] = TEXT_ADDR;
+        state.arch.set_pc(TEXT_ADDR + 24);
+        state.step().unwrap();
+        assert!(state.call_stack().is_empty());
+    }
+
+    #[test]
+    fn trace() {
+        let mut state = init_state(".text\naddi $t0, $zero, 5\nsw $t0, 0($gp)\naddu $t1, $t0, $t0");
+        state.set_trace(true);
+        for _ in 0..3 {
+            state.step().unwrap();
+        }
+
+        let t0 = RegisterName::new(8);
+        let t1 = RegisterName::new(9);
+        let trace: Vec<_> = state.trace().unwrap().iter().cloned().collect();
+        assert_eq!(
+            trace,
+            [
+                TraceEntry {
+                    pc: TEXT_ADDR,
+                    ins: 0x20080005,
+                    disasm: "addi $t0, $zero, 5".into(),
+                    changed_reg: Some((t0, 5)),
+                },
+                TraceEntry {
+                    pc: TEXT_ADDR + 4,
+                    ins: 0xaf880000,
+                    disasm: "sw $t0, 0($gp)".into(),
+                    changed_reg: None,
+                },
+                TraceEntry {
+                    pc: TEXT_ADDR + 8,
+                    ins: 0x01084821,
+                    disasm: "addu $t1, $t0, $t0".into(),
+                    changed_reg: Some((t1, 10)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_is_bounded() {
+        let mut state = init_state(".text\nloop:\naddi $t0, $t0, 1\nj loop");
+        state.set_trace(true);
+        for _ in 0..(TRACE_CAPACITY * 2 + 1) {
+            state.step().unwrap();
+        }
+
+        let trace = state.trace().unwrap();
+        assert_eq!(trace.len(), TRACE_CAPACITY);
+        assert_eq!(trace.back().unwrap().changed_reg.unwrap().1, 65);
+
+        state.set_trace(false);
+        assert!(state.trace().is_none());
+    }
+
     #[test]
     fn arithmetic() {
         let mut state = init_state(
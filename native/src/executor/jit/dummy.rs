@@ -1,15 +1,22 @@
+use super::JitStats;
 use crate::executor::error::ExecuteError;
-use crate::executor::Arch;
+use crate::executor::{Arch, Interpreter};
+#[cfg(test)]
 use crate::memory::Memory;
 
 #[derive(Debug)]
 pub struct DummyJit;
 
 impl DummyJit {
+    #[cfg(test)]
     pub fn new(_mem: Box<dyn Memory>) -> Self {
         panic!("this platform does not support JIT");
     }
 
+    pub fn from_interpreter(_interpreter: Interpreter) -> Self {
+        panic!("this platform does not support JIT");
+    }
+
     pub fn as_arch(&self) -> &Arch {
         panic!("this platform does not support JIT");
     }
@@ -22,6 +29,18 @@ impl DummyJit {
         panic!("this platform does not support JIT");
     }
 
+    pub fn into_interpreter(self) -> Interpreter {
+        panic!("this platform does not support JIT");
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        panic!("this platform does not support JIT");
+    }
+
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        panic!("this platform does not support JIT");
+    }
+
     pub fn step(&mut self) -> Result<(), ExecuteError> {
         panic!("this platform does not support JIT");
     }
@@ -30,7 +49,11 @@ impl DummyJit {
         panic!("this platform does not support JIT");
     }
 
-    pub fn invalidate(&mut self) {
+    pub fn stats(&self) -> JitStats {
+        panic!("this platform does not support JIT");
+    }
+
+    pub fn invalidate_range(&mut self, _addr: u32, _len: u32) {
         panic!("this platform does not support JIT");
     }
 }
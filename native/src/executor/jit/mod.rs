@@ -9,3 +9,13 @@ cfg_if::cfg_if! {
         pub const HAS_JIT: bool = false;
     }
 }
+
+/// Where `Jit::exec` ran guest instructions.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct JitStats {
+    /// Instructions run by compiled blocks.
+    pub compiled: u64,
+    /// Instructions handed to the interpreter one at a time, because they can't be
+    /// compiled or compiled code can't honor the current settings.
+    pub interpreted: u64,
+}
@@ -1,18 +1,33 @@
+use super::JitStats;
 use crate::component::{Instruction, TypeI, TypeJ, TypeR};
 use crate::executor::error::ExecuteError;
+use crate::executor::interpreter::MMIO;
 use crate::executor::{Arch, Interpreter};
+#[cfg(test)]
 use crate::memory::Memory;
 use dynasmrt::x64::Assembler;
-use dynasmrt::{dynasm, AssemblyOffset, DynasmApi, ExecutableBuffer};
+use dynasmrt::{dynasm, AssemblyOffset, DynasmApi, DynasmLabelApi, ExecutableBuffer};
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::mem;
 
 type CompiledFunction = extern "win64" fn(&mut Arch, *mut u8);
 
+// Offsets into Arch, following reg
+const ARCH_CODE_LO: i32 = 33 * 4;
+const ARCH_CODE_HI: i32 = 34 * 4;
+const ARCH_CODE_WRITTEN: i32 = 35 * 4;
+const ARCH_USER_LO: i32 = 38 * 4;
+const ARCH_USER_HI: i32 = 39 * 4;
+const ARCH_JIT_BAILED: i32 = 40 * 4;
+
 #[derive(Debug)]
 struct CompiledCode {
     offset: AssemblyOffset,
     buf: ExecutableBuffer,
+    // guest address right after the last compiled instruction
+    addr_to: u32,
+    // ends in a jump that may call or return, which the call stack doesn't see
+    jumps: bool,
 }
 
 #[derive(Debug)]
@@ -20,19 +35,29 @@ pub struct X64Jit {
     interpreter: Interpreter,
     codes: FxHashMap<u32, CompiledCode>,
     failures: FxHashSet<u32>,
+    stats: JitStats,
 }
 
 impl X64Jit {
+    /// Only for use in unit tests
+    #[cfg(test)]
     pub fn new(mem: Box<dyn Memory>) -> Self {
+        Self::from_interpreter(Interpreter::new(mem))
+    }
+
+    /// Continues from an interpreter, keeping its machine state, settings and what it has
+    /// recorded. Its memory must be fastmem.
+    pub fn from_interpreter(interpreter: Interpreter) -> Self {
         assert!(
-            mem.fastmem_addr().is_some(),
+            interpreter.as_arch().mem().fastmem_addr().is_some(),
             "JIT should be used only with fastmem"
         );
 
         X64Jit {
-            interpreter: Interpreter::new(mem),
+            interpreter,
             codes: FxHashMap::default(),
             failures: FxHashSet::default(),
+            stats: JitStats::default(),
         }
     }
 
@@ -48,31 +73,67 @@ impl X64Jit {
         self.interpreter.into_arch()
     }
 
+    pub fn into_interpreter(self) -> Interpreter {
+        self.interpreter
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    pub fn interpreter_mut(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
     pub fn step(&mut self) -> Result<(), ExecuteError> {
-        self.interpreter.step()
+        let result = self.interpreter.step();
+        self.drop_stale_code();
+        result
+    }
+
+    pub fn stats(&self) -> JitStats {
+        self.stats
     }
 
     pub fn exec(&mut self) -> Result<(), ExecuteError> {
+        // compiled blocks know nothing about delay slots, and leave no trace, profile,
+        // cache or branch statistics
+        if self.interpreter.delay_slots()
+            || self.interpreter.trace().is_some()
+            || self.interpreter.profiling()
+            || self.interpreter.cache_config().is_some()
+            || self.interpreter.branch_stats().is_some()
+        {
+            return self.fall_back();
+        }
+
         let addr_from = self.interpreter.as_arch().pc();
 
         let code = match self.codes.get(&addr_from) {
             Some(x) => x,
             None => {
                 if self.failures.contains(&addr_from) {
-                    return self.interpreter.step();
+                    return self.fall_back();
                 }
 
                 match self.compile(addr_from) {
                     Ok(x) => x,
                     Err(_) => {
                         self.failures.insert(addr_from);
-                        return self.interpreter.step();
+                        return self.fall_back();
                     }
                 }
             }
         };
 
         let f: CompiledFunction = unsafe { mem::transmute(code.buf.ptr(code.offset)) };
+        let addr_to = code.addr_to;
+        let jumps = code.jumps;
+        // a whole block is not one step, and its calls go unseen
+        self.interpreter.clear_changes();
+        if jumps {
+            self.interpreter.clear_call_stack();
+        }
         let arch = self.interpreter.as_arch_mut();
         let base_addr = arch
             .mem
@@ -88,11 +149,56 @@ impl X64Jit {
         debug_assert_eq!(arch.reg[0], 0, "JIT code modified $0");
         arch.reg[0] = 0;
 
+        // Blocks are straight-line. They either run to the end, or leave early with the pc
+        // right after the store that hit compiled code, or on an access they can't do.
+        let bailed = arch.jit_bailed != 0;
+        arch.jit_bailed = 0;
+        let end = if arch.code_written != 0 || bailed {
+            arch.pc()
+        } else {
+            addr_to
+        };
+        let count = (end.wrapping_sub(addr_from) / 4) as u64;
+        arch.retired += count;
+        self.stats.compiled += count;
+
+        self.drop_stale_code();
+        if bailed {
+            return self.fall_back();
+        }
         Ok(())
     }
 
-    pub fn invalidate(&mut self) {
+    /// Interprets the one instruction at the pc, for when there's no compiled block to run.
+    fn fall_back(&mut self) -> Result<(), ExecuteError> {
+        self.stats.interpreted += 1;
+        self.step()
+    }
+
+    /// Throws away every compiled block if the guest wrote over any of them.
+    fn drop_stale_code(&mut self) {
+        let arch = self.interpreter.as_arch_mut();
+        if arch.code_written == 0 {
+            return;
+        }
+
+        arch.code_written = 0;
+        arch.code_lo = 0;
+        arch.code_hi = 0;
         self.codes.clear();
+        self.failures.clear();
+    }
+
+    /// Drops compiled blocks that contain any of the `len` bytes starting at `addr`,
+    /// so they get recompiled from the new memory contents.
+    pub fn invalidate_range(&mut self, addr: u32, len: u32) {
+        let from = addr as u64;
+        let to = from + len as u64;
+
+        self.codes
+            .retain(|k, v| to <= *k as u64 || (v.addr_to as u64) <= from);
+        self.failures
+            .retain(|k| to <= *k as u64 || (*k as u64 + 4) <= from);
     }
 
     fn compile(&mut self, addr_from: u32) -> Result<&CompiledCode, ()> {
@@ -101,6 +207,7 @@ impl X64Jit {
         let mem = self.interpreter.as_arch().mem();
 
         let mut should_set_pc = true;
+        let mut jumps = false;
         let mut addr = addr_from;
         let mut ops = Assembler::new().unwrap();
         let label = ops.offset();
@@ -138,14 +245,14 @@ impl X64Jit {
                 slti(x) => emit_slti(&mut ops, x),
                 sltiu(x) => emit_sltiu(&mut ops, x),
                 xori(x) => emit_xori(&mut ops, x),
-                lb(x) => emit_lb(&mut ops, x),
-                lbu(x) => emit_lbu(&mut ops, x),
-                lh(x) => emit_lh(&mut ops, x),
-                lhu(x) => emit_lhu(&mut ops, x),
-                lw(x) => emit_lw(&mut ops, x),
-                sb(x) => emit_sb(&mut ops, x),
-                sh(x) => emit_sh(&mut ops, x),
-                sw(x) => emit_sw(&mut ops, x),
+                lb(x) => emit_lb(&mut ops, x, addr),
+                lbu(x) => emit_lbu(&mut ops, x, addr),
+                lh(x) => emit_lh(&mut ops, x, addr),
+                lhu(x) => emit_lhu(&mut ops, x, addr),
+                lw(x) => emit_lw(&mut ops, x, addr),
+                sb(x) => emit_sb(&mut ops, x, addr),
+                sh(x) => emit_sh(&mut ops, x, addr),
+                sw(x) => emit_sw(&mut ops, x, addr),
                 j(x) => {
                     emit_j(&mut ops, x, addr);
                     should_set_pc = false;
@@ -154,16 +261,19 @@ impl X64Jit {
                 jal(x) => {
                     emit_jal(&mut ops, x, addr);
                     should_set_pc = false;
+                    jumps = true;
                     break; // basic block finished
                 }
                 jalr(x) => {
                     emit_jalr(&mut ops, x, addr);
                     should_set_pc = false;
+                    jumps = true;
                     break; // basic block finished
                 }
                 jr(x) => {
                     emit_jr(&mut ops, x);
                     should_set_pc = false;
+                    jumps = true;
                     break; //basic block finished
                 }
                 _ => {
@@ -183,7 +293,22 @@ impl X64Jit {
 
         let buf = ops.finalize().unwrap();
 
-        let code = CompiledCode { offset: label, buf };
+        let code = CompiledCode {
+            offset: label,
+            buf,
+            addr_to: addr,
+            jumps,
+        };
+
+        let arch = self.interpreter.as_arch_mut();
+        if arch.code_lo == arch.code_hi {
+            arch.code_lo = addr_from;
+            arch.code_hi = addr;
+        } else {
+            arch.code_lo = arch.code_lo.min(addr_from);
+            arch.code_hi = arch.code_hi.max(addr);
+        }
+
         Ok(self.codes.entry(addr_from).or_insert(code))
     }
 }
@@ -471,96 +596,138 @@ fn emit_xori(ops: &mut Assembler, x: TypeI) {
     );
 }
 
-fn emit_lb(ops: &mut Assembler, x: TypeI) {
+fn emit_lb(ops: &mut Assembler, x: TypeI, next_pc: u32) {
+    emit_mem_addr(ops, x, 1, next_pc);
     if x.rt.is_zero() {
         return;
     }
 
     dynasm!(ops
-        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
-        ; add eax, x.imm as i16 as i32
         ; movsx eax, BYTE [rdx + rax]
         ; mov DWORD [rcx + (x.rt.num() as i32) * 4], eax
     );
 }
 
-fn emit_lbu(ops: &mut Assembler, x: TypeI) {
+fn emit_lbu(ops: &mut Assembler, x: TypeI, next_pc: u32) {
+    emit_mem_addr(ops, x, 1, next_pc);
     if x.rt.is_zero() {
         return;
     }
 
     dynasm!(ops
-        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
-        ; add eax, x.imm as i16 as i32
         ; movzx eax, BYTE [rdx + rax]
         ; mov DWORD [rcx + (x.rt.num() as i32) * 4], eax
     );
 }
 
-fn emit_lh(ops: &mut Assembler, x: TypeI) {
+fn emit_lh(ops: &mut Assembler, x: TypeI, next_pc: u32) {
+    emit_mem_addr(ops, x, 2, next_pc);
     if x.rt.is_zero() {
         return;
     }
 
     dynasm!(ops
-        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
-        ; add eax, x.imm as i16 as i32
         ; movsx eax, WORD [rdx + rax]
         ; mov DWORD [rcx + (x.rt.num() as i32) * 4], eax
     );
 }
 
-fn emit_lhu(ops: &mut Assembler, x: TypeI) {
+fn emit_lhu(ops: &mut Assembler, x: TypeI, next_pc: u32) {
+    emit_mem_addr(ops, x, 2, next_pc);
     if x.rt.is_zero() {
         return;
     }
 
     dynasm!(ops
-        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
-        ; add eax, x.imm as i16 as i32
         ; movzx eax, WORD [rdx + rax]
         ; mov DWORD [rcx + (x.rt.num() as i32) * 4], eax
     );
 }
 
-fn emit_lw(ops: &mut Assembler, x: TypeI) {
+fn emit_lw(ops: &mut Assembler, x: TypeI, next_pc: u32) {
+    emit_mem_addr(ops, x, 4, next_pc);
     if x.rt.is_zero() {
         return;
     }
 
     dynasm!(ops
-        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
-        ; add eax, x.imm as i16 as i32
         ; mov eax, DWORD [rdx + rax]
         ; mov DWORD [rcx + (x.rt.num() as i32) * 4], eax
     );
 }
 
-fn emit_sb(ops: &mut Assembler, x: TypeI) {
+fn emit_sb(ops: &mut Assembler, x: TypeI, next_pc: u32) {
+    emit_mem_addr(ops, x, 1, next_pc);
     dynasm!(ops
-        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
         ; mov r8d, DWORD [rcx + (x.rt.num() as i32) * 4]
-        ; add eax, x.imm as i16 as i32
         ; mov BYTE [rdx + rax], r8b
     );
+    emit_code_write_check(ops, 1, next_pc);
 }
 
-fn emit_sh(ops: &mut Assembler, x: TypeI) {
+fn emit_sh(ops: &mut Assembler, x: TypeI, next_pc: u32) {
+    emit_mem_addr(ops, x, 2, next_pc);
     dynasm!(ops
-        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
         ; mov r8d, DWORD [rcx + (x.rt.num() as i32) * 4]
-        ; add eax, x.imm as i16 as i32
         ; mov WORD [rdx + rax], r8w
     );
+    emit_code_write_check(ops, 2, next_pc);
 }
 
-fn emit_sw(ops: &mut Assembler, x: TypeI) {
+fn emit_sw(ops: &mut Assembler, x: TypeI, next_pc: u32) {
+    emit_mem_addr(ops, x, 4, next_pc);
     dynasm!(ops
-        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
         ; mov r8d, DWORD [rcx + (x.rt.num() as i32) * 4]
-        ; add eax, x.imm as i16 as i32
         ; mov DWORD [rdx + rax], r8d
     );
+    emit_code_write_check(ops, 4, next_pc);
+}
+
+/// Leaves the address a load or store of `size` bytes accesses in eax. If it's unaligned,
+/// outside user space or on the I/O registers, leaves the block with the pc on the access
+/// instead, for the interpreter to do or fail.
+fn emit_mem_addr(ops: &mut Assembler, x: TypeI, size: i32, next_pc: u32) {
+    let pc = next_pc.wrapping_sub(4);
+    dynasm!(ops
+        ; mov eax, DWORD [rcx + (x.rs.num() as i32) * 4]
+        ; add eax, x.imm as i16 as i32
+    );
+    if size > 1 {
+        dynasm!(ops
+            ; test eax, size - 1
+            ; jnz >bail
+        );
+    }
+    dynasm!(ops
+        ; cmp eax, *MMIO.start() as i32
+        ; jae >bail
+        ; cmp eax, DWORD [rcx + ARCH_USER_LO]
+        ; jb >bail
+        ; lea r8d, [rax + size - 1]
+        ; cmp r8d, DWORD [rcx + ARCH_USER_HI]
+        ; jbe >ok
+        ; bail:
+        ; mov DWORD [rcx + ARCH_JIT_BAILED], 1
+        ; mov DWORD [rcx + 32*4], pc as _
+        ; ret
+        ; ok:
+    );
+}
+
+/// Expects the address just stored to in eax.
+/// If it hits compiled code, flags it and leaves the block so nothing stale runs.
+fn emit_code_write_check(ops: &mut Assembler, len: i32, next_pc: u32) {
+    dynasm!(ops
+        ; cmp eax, DWORD [rcx + ARCH_CODE_HI]
+        ; jae >skip
+        ; lea r8d, [rax + len]
+        ; cmp r8d, DWORD [rcx + ARCH_CODE_LO]
+        ; jbe >skip
+        ; mov DWORD [rcx + ARCH_CODE_WRITTEN], 1
+        ; mov DWORD [rcx + 32*4], next_pc as _
+        ; ret
+        ; skip:
+    );
 }
 
 fn emit_j(ops: &mut Assembler, x: TypeJ, pc: u32) {
@@ -626,6 +793,27 @@ mod test {
         assert_eq!(jit.as_arch_mut().reg[16], 3);
     }
 
+    #[test]
+    fn unsupported_falls_back() {
+        let _guard = TEST_MUTEX.lock();
+        let asm = ".text
+            addi $8, $zero, 6
+            addi $9, $zero, 7
+            mul $10, $8, $9
+            addi $10, $10, 1
+            j 0";
+        let mut jit = init_state(asm);
+
+        while !jit.as_arch().is_finished() {
+            jit.exec().unwrap();
+        }
+
+        assert_eq!(jit.as_arch().reg[10], 43);
+        assert!(jit.failures.contains(&0x0040_002c));
+        let stats = jit.stats();
+        assert_eq!((stats.compiled, stats.interpreted), (4, 1));
+    }
+
     #[test]
     fn sub() {
         let _guard = TEST_MUTEX.lock();
@@ -661,25 +849,119 @@ mod test {
     #[test]
     fn lw() {
         let _guard = TEST_MUTEX.lock();
-        let mut jit = init_state(".text\nlw $17, 0($16)\nlw $18, -1($16)\nlw $19, 1($16)\nsyscall");
+        let mut jit = init_state(".text\nlw $17, 0($16)\nlw $18, -4($16)\nlw $19, 4($16)\nsyscall");
 
         jit.as_arch_mut().reg[16] = 0x1000_0000;
         jit.as_arch_mut().mem.write_u32(0x1000_0000 - 4, 0x11223344);
         jit.as_arch_mut().mem.write_u32(0x1000_0000, 0x55667788);
         jit.as_arch_mut().mem.write_u32(0x1000_0000 + 4, 0x99aabbcc);
 
-        let ans_1 = jit.as_arch_mut().mem.read_u32(0x1000_0000);
-        let ans_2 = jit.as_arch_mut().mem.read_u32(0x1000_0000 - 1);
-        let ans_3 = jit.as_arch_mut().mem.read_u32(0x1000_0000 + 1);
-
         jit.exec().unwrap();
 
         assert!(jit.codes.contains_key(&0x0040_0024));
         assert_eq!(jit.as_arch_mut().pc(), 0x0040_0030);
         assert_eq!(jit.as_arch_mut().reg[16], 0x1000_0000);
-        assert_eq!(jit.as_arch_mut().reg[17], ans_1);
-        assert_eq!(jit.as_arch_mut().reg[18], ans_2);
-        assert_eq!(jit.as_arch_mut().reg[19], ans_3);
+        assert_eq!(jit.as_arch_mut().reg[17], 0x55667788);
+        assert_eq!(jit.as_arch_mut().reg[18], 0x11223344);
+        assert_eq!(jit.as_arch_mut().reg[19], 0x99aabbcc);
+    }
+
+    #[test]
+    fn bad_access_stops_block() {
+        let _guard = TEST_MUTEX.lock();
+        let code = ".text\naddi $8, $0, 1\nlw $17, 1($16)\naddi $8, $0, 2\nsw $8, 0($18)\nsyscall";
+        let mut jit = init_state(code);
+        jit.as_arch_mut().reg[16] = 0x1000_0000;
+        jit.as_arch_mut().reg[18] = 0x8000_0000;
+
+        // the instructions before the access still run, and the access itself is
+        // interpreted, so it fails as it would there
+        let err = jit.exec().unwrap_err();
+        assert!(matches!(
+            err,
+            ExecuteError::UnalignedAccess { align: 4, .. }
+        ));
+        assert_eq!(jit.as_arch().pc(), 0x0040_0028);
+        assert_eq!(jit.as_arch().reg[8], 1);
+        assert_eq!(
+            jit.stats(),
+            JitStats {
+                compiled: 1,
+                interpreted: 1
+            }
+        );
+
+        jit.as_arch_mut().reg[16] = 0x1000_0003;
+        let err = jit.exec().unwrap_err();
+        assert!(matches!(
+            err,
+            ExecuteError::AddressError {
+                addr: 0x8000_0000,
+                ..
+            }
+        ));
+        assert_eq!(jit.as_arch().pc(), 0x0040_0030);
+        assert_eq!(jit.as_arch().reg[8], 2);
+        assert_eq!(jit.as_arch().mem().read_u32(0x8000_0000), 0);
+    }
+
+    #[test]
+    fn console_output() {
+        let _guard = TEST_MUTEX.lock();
+        let code = ".text\nlui $8, 0xffff\naddi $9, $0, 72\nsw $9, 12($8)\n\
+                    addi $9, $0, 105\nsw $9, 12($8)\nsyscall";
+        let mut jit = init_state(code);
+
+        while jit.as_arch().pc() != 0x0040_0038 {
+            jit.exec().unwrap();
+        }
+
+        assert_eq!(jit.as_arch().console(), "Hi");
+        assert_eq!(
+            jit.stats(),
+            JitStats {
+                compiled: 3,
+                interpreted: 2
+            }
+        );
+    }
+
+    #[test]
+    fn keyboard_polling() {
+        let _guard = TEST_MUTEX.lock();
+        let code = ".text\nlui $8, 0xffff\npoll: lw $9, 0($8)\nbeq $9, $0, poll\n\
+                    lw $10, 4($8)\nsyscall";
+        let mut jit = init_state(code);
+
+        for _ in 0..20 {
+            jit.exec().unwrap();
+        }
+        assert_ne!(jit.as_arch().pc(), 0x0040_0034);
+
+        jit.as_arch_mut().push_key(b'a');
+        for _ in 0..20 {
+            if jit.as_arch().pc() == 0x0040_0034 {
+                break;
+            }
+            jit.exec().unwrap();
+        }
+
+        assert_eq!(jit.as_arch().pc(), 0x0040_0034);
+        assert_eq!(jit.as_arch().reg[10], b'a' as u32);
+        assert_eq!(jit.as_arch_mut().mem.read_u32(0xffff_0000), 0);
+    }
+
+    #[test]
+    fn arch_offsets() {
+        let arch = Arch::new(create_memory_fastmem(EndianMode::native(), &[]));
+        let offset = |field: *const u32| field as usize - &arch as *const Arch as usize;
+
+        assert_eq!(offset(&arch.code_lo) as i32, ARCH_CODE_LO);
+        assert_eq!(offset(&arch.code_hi) as i32, ARCH_CODE_HI);
+        assert_eq!(offset(&arch.code_written) as i32, ARCH_CODE_WRITTEN);
+        assert_eq!(offset(&arch.user_lo) as i32, ARCH_USER_LO);
+        assert_eq!(offset(&arch.user_hi) as i32, ARCH_USER_HI);
+        assert_eq!(offset(&arch.jit_bailed) as i32, ARCH_JIT_BAILED);
     }
 
     #[test]
@@ -756,4 +1038,152 @@ fibonacciExit:
         assert_eq!(state.as_arch_mut().mem.read_u32(data_addr + 20), 13);
         assert_eq!(state.as_arch_mut().mem.read_u32(data_addr + 24), 0);
     }
+
+    const SMC_CODE: &str = ".text
+    target:
+        addi $s1, $zero, 5
+        jr $ra
+    patcher:
+        sw $t0, 0($a1)
+        jr $ra";
+
+    // addi $s1, $zero, 7
+    const PATCHED_INS: u32 = 0x20110007;
+
+    #[test]
+    fn self_modifying_code() {
+        let _guard = TEST_MUTEX.lock();
+        let mut jit = init_state(SMC_CODE);
+        let target = 0x0040_0024;
+        let patcher = 0x0040_002c;
+
+        jit.as_arch_mut().reg[31] = patcher;
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().reg[17], 5);
+        assert_eq!(jit.as_arch().pc(), patcher);
+
+        // the block at `patcher` overwrites the compiled one at `target`
+        jit.as_arch_mut().reg[8] = PATCHED_INS;
+        jit.as_arch_mut().reg[5] = target;
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().pc(), patcher + 4);
+        assert!(jit.codes.is_empty());
+
+        jit.as_arch_mut().set_pc(target);
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().reg[17], 7);
+    }
+
+    #[test]
+    fn retired_count() {
+        let _guard = TEST_MUTEX.lock();
+        let mut jit = init_state(SMC_CODE);
+        let patcher = 0x0040_002c;
+
+        jit.as_arch_mut().reg[31] = patcher;
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().retired(), 2);
+
+        // leaves after the store, before `jr`
+        jit.as_arch_mut().reg[5] = 0x0040_0024;
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().retired(), 3);
+
+        jit.step().unwrap();
+        assert_eq!(jit.as_arch().retired(), 4);
+    }
+
+    #[test]
+    fn self_modifying_code_interpreted() {
+        let _guard = TEST_MUTEX.lock();
+        let mut jit = init_state(SMC_CODE);
+        let target = 0x0040_0024;
+        let patcher = 0x0040_002c;
+
+        jit.as_arch_mut().reg[31] = patcher;
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().reg[17], 5);
+
+        jit.as_arch_mut().reg[8] = PATCHED_INS;
+        jit.as_arch_mut().reg[5] = target;
+        jit.step().unwrap();
+        assert!(jit.codes.is_empty());
+
+        jit.as_arch_mut().set_pc(target);
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().reg[17], 7);
+    }
+
+    #[test]
+    fn trace_bypasses_compiled_code() {
+        let _guard = TEST_MUTEX.lock();
+        let mut jit = init_state(".text\naddi $16, $0, 1\naddi $17, $16, 1\nsyscall");
+        jit.interpreter_mut().set_trace(true);
+
+        jit.exec().unwrap();
+        jit.exec().unwrap();
+
+        assert!(jit.codes.is_empty());
+        assert_eq!(jit.as_arch().reg[17], 2);
+        assert_eq!(jit.interpreter().trace().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn profiling_bypasses_compiled_code() {
+        let _guard = TEST_MUTEX.lock();
+        let mut jit = init_state(".text\naddi $16, $0, 1\naddi $17, $16, 1\nsyscall");
+        jit.interpreter_mut().set_profiling(true);
+
+        jit.exec().unwrap();
+        jit.exec().unwrap();
+
+        assert!(jit.codes.is_empty());
+        assert_eq!(jit.interpreter().profile()["addi"], 2);
+    }
+
+    #[test]
+    fn external_write_invalidates() {
+        let _guard = TEST_MUTEX.lock();
+        let mut jit = init_state(SMC_CODE);
+        let target = 0x0040_0024;
+
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().reg[17], 5);
+
+        jit.as_arch_mut().mem.write_u32(target, PATCHED_INS);
+        jit.invalidate_range(target, 4);
+        assert!(!jit.codes.contains_key(&target));
+
+        jit.as_arch_mut().set_pc(target);
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().reg[17], 7);
+    }
+
+    #[test]
+    fn call_stack_kept_without_jumps() {
+        let _guard = TEST_MUTEX.lock();
+        let mut jit = init_state(".text\njal f\nnop\nf: addi $16, $0, 1\nsyscall");
+        let link = jit.as_arch().pc() + 4;
+
+        jit.step().unwrap();
+        jit.exec().unwrap();
+
+        assert_eq!(jit.as_arch().reg[16], 1);
+        assert_eq!(jit.interpreter().call_stack(), [link]);
+    }
+
+    #[test]
+    fn data_write_keeps_code() {
+        let _guard = TEST_MUTEX.lock();
+        let mut jit = init_state(SMC_CODE);
+        let patcher = 0x0040_002c;
+
+        jit.as_arch_mut().reg[31] = patcher;
+        jit.exec().unwrap();
+
+        jit.as_arch_mut().reg[5] = 0x1000_0000;
+        jit.exec().unwrap();
+        assert_eq!(jit.as_arch().pc(), patcher);
+        assert!(jit.codes.contains_key(&0x0040_0024));
+    }
 }
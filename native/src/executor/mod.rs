@@ -1,12 +1,18 @@
 mod arch;
+mod cache;
 mod error;
 mod executor_trait;
 mod interpreter;
 mod jit;
 mod pipeline;
+mod predictor;
+mod random;
 
 pub use arch::Arch;
-pub use executor_trait::Executor;
-pub use interpreter::Interpreter;
-pub use jit::{Jit, HAS_JIT};
+pub use cache::{CacheConfig, CacheStats, MAX_BLOCKS, MAX_BLOCK_SIZE};
+pub use error::ExecuteError;
+pub use executor_trait::{Executor, RunResult};
+pub use interpreter::{ArithFlags, ChangedReg, Interpreter, PageAccesses, RegChange, TraceEntry};
+pub use jit::{Jit, JitStats, HAS_JIT};
 pub use pipeline::processor::{Description, Pipeline};
+pub use predictor::{BranchReport, BranchStats};
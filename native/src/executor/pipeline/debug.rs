@@ -1,4 +1,4 @@
-use crate::disassembler::disassemble;
+use crate::disassembler::{disassemble_with, DisassembleOptions};
 use crate::executor::pipeline::{info, pipes};
 use crate::executor::Arch;
 
@@ -196,5 +196,9 @@ pub fn generate_info(
 
 fn try_disassembly(arch: &Arch, pc: u32) -> String {
     let ins = arch.mem.read_u32(pc);
-    disassemble(ins)
+    let options = DisassembleOptions {
+        pseudo: true,
+        ..Default::default()
+    };
+    disassemble_with(ins, Some(pc), &options)
 }
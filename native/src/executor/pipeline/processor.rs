@@ -3,7 +3,7 @@ use crate::component::RegisterName;
 use crate::executor::Arch;
 use crate::memory::Memory;
 
-use crate::disassembler::disassemble;
+use crate::disassembler::{disassemble, disassemble_at};
 use crate::executor::pipeline::stage::ex_stage::ex_next;
 use crate::executor::pipeline::stage::id_stage::id_next;
 use crate::executor::pipeline::stage::if_stage::if_next;
@@ -84,6 +84,9 @@ impl Pipeline {
         let ex_output = ex_next(&self.id_ex, &self.arch, &self.ex_mem, &self.mem_wb);
         let mem_output = mem_next(&self.ex_mem, &self.mem_wb, &mut self.arch);
         let wb_output = wb_next(&self.mem_wb, &mut self.arch);
+        if wb_output.debug_pc.is_some() {
+            self.arch.retired += 1;
+        }
 
         let hazard = hazard_ctrl(&if_output, &id_output, &ex_output);
 
@@ -96,8 +99,10 @@ impl Pipeline {
         };
 
         if LOG_OUTPUT {
-            let read_disassemble =
-                |debug_pc: Option<u32>| disassemble(self.arch.mem.read_u32(debug_pc.unwrap_or(0)));
+            let read_disassemble = |debug_pc: Option<u32>| match debug_pc {
+                Some(pc) => disassemble_at(pc, self.arch.mem.read_u32(pc)),
+                None => "(bubble)".into(),
+            };
 
             println!("IF ins: {}", disassemble(if_output.inst));
             println!("IF: {:?}", if_output);
@@ -153,7 +158,6 @@ impl Pipeline {
 mod test {
     use super::*;
     use crate::assembler::assemble;
-    use crate::disassembler::disassemble;
     use crate::executor::pipeline::units;
     use crate::executor::Interpreter;
     use crate::memory::{create_memory, EndianMode};
@@ -165,6 +169,32 @@ mod test {
         Pipeline::new(mem)
     }
 
+    #[test]
+    fn retired_count() {
+        let mut proc = make(".text\nnop\nnop\nnop");
+        for _ in 0..4 {
+            proc.step();
+        }
+        assert_eq!(proc.arch.retired(), 0);
+
+        // one instruction leaves write-back each cycle once the pipeline is full
+        proc.step();
+        proc.step();
+        assert_eq!(proc.arch.retired(), 2);
+    }
+
+    #[test]
+    fn finalize_fetches_bubble() {
+        let mut proc = make(".text\naddi $8, $0, 1");
+        proc.finalize();
+        assert_eq!(proc.if_id.inst, 0);
+        assert_eq!(proc.if_id.debug_pc, None);
+
+        let detail = proc.get_pipeline_detail();
+        assert_eq!(detail.debug_ins_id, None);
+        assert_eq!(detail.debug_ins_ex, None);
+    }
+
     #[test]
     fn processor_pc_value() {
         let mut proc = make(".text\nadd $18, $16, $17");
@@ -1,11 +1,16 @@
 use crate::executor::pipeline::pipes;
 
 pub fn if_next(pc: u32, inst: u32, finalize: bool) -> pipes::IfPipe {
-    let output_inst = if !finalize { inst } else { 0 };
+    // a finalizing step fetches a bubble, which isn't the instruction at `pc`
+    let (output_inst, debug_pc) = if !finalize {
+        (inst, Some(pc))
+    } else {
+        (0, None)
+    };
 
     pipes::IfPipe {
         npc: pc.wrapping_add(4),
         inst: output_inst,
-        debug_pc: Some(pc),
+        debug_pc,
     }
 }
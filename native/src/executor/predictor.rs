@@ -0,0 +1,122 @@
+use rustc_hash::FxHashMap;
+
+/// Counter value from which a branch is predicted taken. Counters start one below it.
+const PREDICT_TAKEN: u8 = 2;
+
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct BranchStats {
+    pub predicted: u64,
+    pub mispredicted: u64,
+}
+
+impl BranchStats {
+    /// Correct predictions out of all of them, or 1 before the first one.
+    pub fn accuracy(&self) -> f64 {
+        match self.predicted + self.mispredicted {
+            0 => 1.0,
+            total => self.predicted as f64 / total as f64,
+        }
+    }
+
+    fn record(&mut self, correct: bool) {
+        if correct {
+            self.predicted += 1;
+        } else {
+            self.mispredicted += 1;
+        }
+    }
+}
+
+/// What the predictor got right, overall and for each conditional branch by address.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct BranchReport {
+    pub total: BranchStats,
+    /// In address order.
+    pub branches: Vec<(u32, BranchStats)>,
+}
+
+/// A 2-bit saturating counter for each conditional branch, predicting its direction. Only
+/// keeps score; execution never follows its guesses.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Predictor {
+    branches: FxHashMap<u32, (u8, BranchStats)>,
+    total: BranchStats,
+}
+
+impl Predictor {
+    /// Scores the prediction for the branch at `pc`, then trains its counter on what it did.
+    pub(super) fn record(&mut self, pc: u32, taken: bool) {
+        let (counter, stats) = self
+            .branches
+            .entry(pc)
+            .or_insert((PREDICT_TAKEN - 1, BranchStats::default()));
+
+        let correct = (*counter >= PREDICT_TAKEN) == taken;
+        stats.record(correct);
+        self.total.record(correct);
+
+        *counter = if taken {
+            u8::min(*counter + 1, 3)
+        } else {
+            counter.saturating_sub(1)
+        };
+    }
+
+    pub(super) fn report(&self) -> BranchReport {
+        let mut branches: Vec<_> = self.branches.iter().map(|(&k, v)| (k, v.1)).collect();
+        branches.sort_unstable_by_key(|x| x.0);
+
+        BranchReport {
+            total: self.total,
+            branches,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn saturates() {
+        let mut predictor = Predictor::default();
+
+        // warms up on the first taken branch
+        for _ in 0..5 {
+            predictor.record(0x100, true);
+        }
+        assert_eq!(predictor.report().total.mispredicted, 1);
+
+        // one not taken doesn't flip a saturated counter, two do
+        predictor.record(0x100, false);
+        predictor.record(0x100, true);
+        predictor.record(0x100, false);
+        predictor.record(0x100, false);
+        predictor.record(0x100, false);
+
+        let report = predictor.report();
+        assert_eq!(report.total.mispredicted, 4);
+        assert_eq!(report.total.predicted, 6);
+        assert_eq!(report.branches, [(0x100, report.total)]);
+        assert_eq!(report.total.accuracy(), 0.6);
+    }
+
+    #[test]
+    fn separate_counters() {
+        let mut predictor = Predictor::default();
+        predictor.record(0x100, true);
+        predictor.record(0x100, true);
+        predictor.record(0x200, false);
+
+        let report = predictor.report();
+        let stats = |predicted, mispredicted| BranchStats {
+            predicted,
+            mispredicted,
+        };
+        assert_eq!(
+            report.branches,
+            [(0x100, stats(1, 1)), (0x200, stats(1, 0))]
+        );
+        assert_eq!(report.total, stats(2, 1));
+    }
+}
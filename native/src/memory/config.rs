@@ -0,0 +1,10 @@
+use crate::memory::MemoryInit;
+
+/// How `create_memory_with` and `create_empty_memory_with` set up memory.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct MemoryConfig {
+    pub init: MemoryInit,
+    /// Addresses from here on are out of range: loads and stores there are address errors,
+    /// and writes are dropped. `None` leaves the whole space usable.
+    pub limit: Option<u32>,
+}
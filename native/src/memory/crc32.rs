@@ -0,0 +1,80 @@
+/// The reflected polynomial of the common CRC-32 (IEEE 802.3), as used by zip, PNG and
+/// `crc32` tools.
+const POLY: u32 = 0xedb8_8320;
+
+const fn make_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                crc >> 1 ^ POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static TABLE: [u32; 256] = make_table();
+
+/// CRC-32 of `bytes`, matching what external tools report for the same bytes.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::new();
+    crc.update(bytes);
+    crc.finish()
+}
+
+/// [`crc32`] of bytes that arrive piece by piece.
+#[derive(Debug, Copy, Clone)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Crc32(!0)
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.0 = bytes.iter().fold(self.0, |crc, &x| {
+            TABLE[((crc ^ x as u32) & 0xff) as usize] ^ crc >> 8
+        });
+    }
+
+    /// Same as [`Self::update`] with `len` zero bytes, without allocating them.
+    pub fn update_zeros(&mut self, len: u64) {
+        for _ in 0..len {
+            self.0 = TABLE[(self.0 & 0xff) as usize] ^ self.0 >> 8;
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_values() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf43926);
+        assert_eq!(crc32(&[0; 4]), 0x2144df1c);
+    }
+
+    #[test]
+    fn in_pieces() {
+        let mut crc = Crc32::new();
+        crc.update(b"12");
+        crc.update_zeros(3);
+        crc.update(b"9");
+        assert_eq!(crc.finish(), crc32(b"12\0\0\09"));
+    }
+}
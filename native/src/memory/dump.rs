@@ -0,0 +1,80 @@
+use crate::memory::Memory;
+use std::fmt::Write;
+
+const PAGE_SIZE: u32 = 4096;
+const BYTES_PER_LINE: u32 = 16;
+
+/// Formats `len` bytes starting at `addr` like `xxd`: one line per 16 bytes with the
+/// address, the bytes in hex and their printable ASCII. Bytes on unmapped pages show as
+/// `..` and a blank in the ASCII column.
+pub fn hex_dump(mem: &dyn Memory, addr: u32, len: u32) -> String {
+    let mut out = String::new();
+    let mut offset = 0;
+
+    while offset < len {
+        let line_addr = addr.wrapping_add(offset);
+        let count = (len - offset).min(BYTES_PER_LINE);
+        let mut ascii = String::new();
+
+        write!(out, "{:08x}:", line_addr).unwrap();
+        for i in 0..BYTES_PER_LINE {
+            let byte_addr = line_addr.wrapping_add(i);
+
+            if i >= count {
+                out.push_str("   ");
+            } else if !mem.is_page_mapped(byte_addr / PAGE_SIZE) {
+                out.push_str(" ..");
+                ascii.push(' ');
+            } else {
+                let x = mem.read_u8(byte_addr);
+                write!(out, " {:02x}", x).unwrap();
+                ascii.push(if x == b' ' || x.is_ascii_graphic() {
+                    x as char
+                } else {
+                    '.'
+                });
+            }
+        }
+        writeln!(out, "  {}", ascii).unwrap();
+
+        offset += count;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::{create_memory, EndianMode, Segment};
+
+    #[test]
+    fn dump_known_data() {
+        // the last 8 bytes fall on the unmapped page before the data
+        let mut seg = Segment::new(0x10001000, EndianMode::Big);
+        seg.append_bytes(b"Hello, MIPS!\n\0\x7f\xff");
+        seg.append_u32(0xdeadbeef);
+        seg.append_bytes(b"0123");
+        let mem = create_memory(EndianMode::Big, &[seg]);
+
+        let dump = hex_dump(&*mem, 0x10000ff8, 32);
+        assert_eq!(
+            dump,
+            "10000ff8: .. .. .. .. .. .. .. .. 48 65 6c 6c 6f 2c 20 4d          Hello, M\n\
+             10001008: 49 50 53 21 0a 00 7f ff de ad be ef 30 31 32 33  IPS!........0123\n"
+        );
+    }
+
+    #[test]
+    fn dump_partial_line() {
+        let mut seg = Segment::new(0x10000000, EndianMode::Big);
+        seg.append_bytes(b"abc");
+        let mem = create_memory(EndianMode::Big, &[seg]);
+
+        assert_eq!(
+            hex_dump(&*mem, 0x10000000, 3),
+            format!("10000000: 61 62 63{}  abc\n", " ".repeat(39))
+        );
+        assert_eq!(hex_dump(&*mem, 0x10000000, 0), "");
+    }
+}
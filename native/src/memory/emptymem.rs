@@ -1,4 +1,4 @@
-use crate::memory::{EndianMode, Memory};
+use crate::memory::{EndianMode, Memory, MemoryInit};
 use std::ptr::NonNull;
 
 // Something like /dev/null
@@ -6,11 +6,12 @@ use std::ptr::NonNull;
 #[derive(Debug)]
 pub struct EmptyMem {
     endian: EndianMode,
+    init: MemoryInit,
 }
 
 impl EmptyMem {
-    pub fn new(endian: EndianMode) -> Box<EmptyMem> {
-        Box::new(EmptyMem { endian })
+    pub fn new(endian: EndianMode, init: MemoryInit) -> Box<EmptyMem> {
+        Box::new(EmptyMem { endian, init })
     }
 }
 
@@ -23,20 +24,25 @@ impl Memory for EmptyMem {
         None
     }
 
-    fn read_u8(&self, _addr: u32) -> u8 {
-        0
+    fn read_u8(&self, addr: u32) -> u8 {
+        self.init.byte_at(self.endian, addr)
     }
 
-    fn read_u16(&self, _addr: u32) -> u16 {
-        0
+    fn read_u16(&self, addr: u32) -> u16 {
+        let buf = [self.read_u8(addr), self.read_u8(addr.wrapping_add(1))];
+        self.endian.read_u16(&buf)
     }
 
-    fn read_u32(&self, _addr: u32) -> u32 {
-        0
+    fn read_u32(&self, addr: u32) -> u32 {
+        let mut buf = [0; 4];
+        self.read_into_slice(addr, &mut buf);
+        self.endian.read_u32(&buf)
     }
 
-    fn read_into_slice(&self, _addr: u32, output: &mut [u8]) {
-        output.fill(0);
+    fn read_into_slice(&self, addr: u32, output: &mut [u8]) {
+        for (i, x) in output.iter_mut().enumerate() {
+            *x = self.read_u8(addr.wrapping_add(i as u32));
+        }
     }
 
     fn write_u8(&mut self, _addr: u32, _data: u8) {}
@@ -46,4 +52,8 @@ impl Memory for EmptyMem {
     fn write_u32(&mut self, _addr: u32, _data: u32) {}
 
     fn write_from_slice(&mut self, _addr: u32, _data: &[u8]) {}
+
+    fn is_page_mapped(&self, _page_idx: u32) -> bool {
+        false
+    }
 }
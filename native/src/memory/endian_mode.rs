@@ -25,6 +25,13 @@ impl EndianMode {
         }
     }
 
+    pub fn write_u64(self, buf: &mut [u8], n: u64) {
+        match self {
+            Little => byteorder::LE::write_u64(buf, n),
+            Big => byteorder::BE::write_u64(buf, n),
+        }
+    }
+
     pub fn write_u16(self, buf: &mut [u8], n: u16) {
         match self {
             Little => byteorder::LE::write_u16(buf, n),
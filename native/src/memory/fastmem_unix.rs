@@ -1,5 +1,5 @@
 use super::memory_trait::FastMem;
-use super::{EndianMode, Memory, Segment};
+use super::{EndianMode, Memory, MemoryInit, Segment};
 use lazy_static::lazy_static;
 use libc::*;
 use lockfree::map::Map;
@@ -22,6 +22,7 @@ const TOTAL_ALLOC_SIZE: size_t = usize::wrapping_add(0xffffffff, 4096);
 
 pub struct FastMemUnix {
     base_addr: *mut u8,
+    init: MemoryInit,
     allocated: [AtomicBool; 1048576],
 }
 
@@ -37,7 +38,11 @@ impl Debug for FastMemUnix {
 }
 
 impl FastMemUnix {
-    pub fn try_new(endian: EndianMode, segments: &[Segment]) -> Option<Box<Self>> {
+    pub fn try_new(
+        endian: EndianMode,
+        segments: &[Segment],
+        init: MemoryInit,
+    ) -> Option<Box<Self>> {
         if endian != EndianMode::native() || size_of::<usize>() <= size_of::<u32>() {
             // non-native endian or 32-bit system
             return None;
@@ -69,6 +74,7 @@ impl FastMemUnix {
 
             // init fields
             (&mut (*ptr).base_addr as *mut *mut u8).write(base_addr);
+            (&mut (*ptr).init as *mut MemoryInit).write(init);
             // finish init fields (`allocated` is skipped because zero is valid)
 
             let obj = Box::from_raw(ptr);
@@ -102,11 +108,13 @@ impl FastMemUnix {
         let host_addr = self.base_addr.add(page_idx * 4096);
         let status = mprotect(host_addr as *mut c_void, 4096, PROT_READ | PROT_WRITE);
 
-        if status == 0 {
-            Ok(())
-        } else {
-            Err(*__errno_location())
+        if status != 0 {
+            return Err(*__errno_location());
         }
+
+        let page = std::slice::from_raw_parts_mut(host_addr, 4096);
+        self.init.fill(EndianMode::native(), page);
+        Ok(())
     }
 }
 
@@ -114,6 +122,10 @@ impl FastMem for FastMemUnix {
     fn fastmem_addr(&self) -> *mut u8 {
         self.base_addr
     }
+
+    fn is_page_mapped(&self, page_idx: u32) -> bool {
+        self.allocated[page_idx as usize].load(Ordering::SeqCst)
+    }
 }
 
 impl Drop for FastMemUnix {
@@ -211,7 +223,8 @@ mod test {
     fn basic() {
         let _guard = TEST_LOCK.lock();
 
-        let mut mem = FastMemUnix::try_new(EndianMode::native(), &[]).expect("creating memory");
+        let mut mem = FastMemUnix::try_new(EndianMode::native(), &[], MemoryInit::Zero)
+            .expect("creating memory");
 
         mem.write_u32(0x12345678, 4321);
         assert_eq!(mem.read_u32(0x12345678), 4321);
@@ -223,8 +236,10 @@ mod test {
     fn multiple_instance() {
         let _guard = TEST_LOCK.lock();
 
-        let mut mem1 = FastMemUnix::try_new(EndianMode::native(), &[]).expect("creating memory");
-        let mut mem2 = FastMemUnix::try_new(EndianMode::native(), &[]).expect("creating memory");
+        let mut mem1 = FastMemUnix::try_new(EndianMode::native(), &[], MemoryInit::Zero)
+            .expect("creating memory");
+        let mut mem2 = FastMemUnix::try_new(EndianMode::native(), &[], MemoryInit::Zero)
+            .expect("creating memory");
 
         mem1.write_u32(0x1234, 1);
         mem2.write_u32(0x4321, 2);
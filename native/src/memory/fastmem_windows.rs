@@ -1,4 +1,4 @@
-use super::{EndianMode, Memory, Segment};
+use super::{EndianMode, Memory, MemoryInit, Segment};
 use crate::memory::memory_trait::FastMem;
 use lazy_static::lazy_static;
 use lockfree::map::Map;
@@ -33,6 +33,7 @@ unsafe impl Sync for MemoryListEntry {}
 
 pub struct FastMemWindows {
     base_addr: *mut u8,
+    init: MemoryInit,
     curr_process: HANDLE,
     handler: *mut c_void,
     pagefiles: [AtomicIsize; 1048576],
@@ -50,7 +51,11 @@ impl Debug for FastMemWindows {
 }
 
 impl FastMemWindows {
-    pub fn try_new(endian: EndianMode, segments: &[Segment]) -> Option<Box<Self>> {
+    pub fn try_new(
+        endian: EndianMode,
+        segments: &[Segment],
+        init: MemoryInit,
+    ) -> Option<Box<Self>> {
         if endian != EndianMode::native() || !has_virtualalloc2() {
             return None;
         }
@@ -68,6 +73,7 @@ impl FastMemWindows {
 
             // Init fields
             (&mut (*ptr).base_addr as *mut *mut u8).write(base_addr);
+            (&mut (*ptr).init as *mut MemoryInit).write(init);
             (&mut (*ptr).curr_process as *mut HANDLE).write(curr_process);
             (&mut (*ptr).handler as *mut *mut c_void).write(null_mut());
             (&mut (*ptr).pagefiles as *mut AtomicIsize).write_bytes(0xFF, 1048576);
@@ -131,6 +137,8 @@ unsafe extern "system" fn exception_handler(info: *mut EXCEPTION_POINTERS) -> i3
                         .compare_exchange(-1, x.0, Ordering::SeqCst, Ordering::SeqCst)
                         .is_ok()
                     {
+                        let page = std::slice::from_raw_parts_mut(host_page as *mut u8, 4096);
+                        (*obj).init.fill(EndianMode::native(), page);
                         return -1;
                     }
                 }
@@ -152,6 +160,10 @@ impl FastMem for FastMemWindows {
     fn fastmem_addr(&self) -> *mut u8 {
         self.base_addr
     }
+
+    fn is_page_mapped(&self, page_idx: u32) -> bool {
+        self.pagefiles[page_idx as usize].load(Ordering::SeqCst) != -1
+    }
 }
 
 impl Drop for FastMemWindows {
@@ -308,7 +320,8 @@ mod test {
     fn basic() {
         let _guard = TEST_LOCK.lock();
 
-        let mut mem = FastMemWindows::try_new(EndianMode::native(), &[]).expect("creating memory");
+        let mut mem = FastMemWindows::try_new(EndianMode::native(), &[], MemoryInit::Zero)
+            .expect("creating memory");
 
         mem.write_u32(0x12345678, 4321);
         assert_eq!(mem.read_u32(0x12345678), 4321);
@@ -320,8 +333,10 @@ mod test {
     fn multiple_instance() {
         let _guard = TEST_LOCK.lock();
 
-        let mut mem1 = FastMemWindows::try_new(EndianMode::native(), &[]).expect("creating memory");
-        let mut mem2 = FastMemWindows::try_new(EndianMode::native(), &[]).expect("creating memory");
+        let mut mem1 = FastMemWindows::try_new(EndianMode::native(), &[], MemoryInit::Zero)
+            .expect("creating memory");
+        let mut mem2 = FastMemWindows::try_new(EndianMode::native(), &[], MemoryInit::Zero)
+            .expect("creating memory");
 
         mem1.write_u32(0x1234, 1);
         mem2.write_u32(0x4321, 2);
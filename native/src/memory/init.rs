@@ -0,0 +1,37 @@
+use crate::memory::EndianMode;
+
+/// Read by code that loads a word nothing has written, under `MemoryInit::Poison`.
+pub const POISON_WORD: u32 = 0xdead_beef;
+
+/// What memory holds before anything writes it.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum MemoryInit {
+    #[default]
+    Zero,
+    /// Every word reads `POISON_WORD`, to catch programs that rely on zeroed memory.
+    Poison,
+}
+
+impl MemoryInit {
+    /// The byte at `addr` in memory nothing has written.
+    pub(super) fn byte_at(self, endian: EndianMode, addr: u32) -> u8 {
+        match self {
+            MemoryInit::Zero => 0,
+            MemoryInit::Poison => {
+                let mut buf = [0; 4];
+                endian.write_u32(&mut buf, POISON_WORD);
+                buf[(addr % 4) as usize]
+            }
+        }
+    }
+
+    /// Fills a freshly allocated page, which must start word-aligned and may be zeroed
+    /// already.
+    pub(super) fn fill(self, endian: EndianMode, page: &mut [u8]) {
+        if self == MemoryInit::Poison {
+            for word in page.chunks_exact_mut(4) {
+                endian.write_u32(word, POISON_WORD);
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::memory::{EndianMode, Memory};
+use std::ptr::NonNull;
+
+/// Memory cut off at `limit`. Writes from there on are dropped, and reads see whatever the
+/// inner memory fills unwritten bytes with.
+///
+/// Never fastmem, as compiled code would access past the limit without checking it.
+#[derive(Debug)]
+pub struct Limited {
+    inner: Box<dyn Memory>,
+    limit: u32,
+}
+
+impl Limited {
+    pub fn new(inner: Box<dyn Memory>, limit: u32) -> Box<Limited> {
+        Box::new(Limited { inner, limit })
+    }
+
+    /// How many bytes of a write of `len` at `addr` fall under the limit.
+    fn writable(&self, addr: u32, len: usize) -> usize {
+        usize::min(len, self.limit.saturating_sub(addr) as usize)
+    }
+}
+
+impl Memory for Limited {
+    fn endian(&self) -> EndianMode {
+        self.inner.endian()
+    }
+
+    fn fastmem_addr(&self) -> Option<NonNull<u8>> {
+        None
+    }
+
+    fn limit(&self) -> Option<u32> {
+        Some(self.limit)
+    }
+
+    fn read_u8(&self, addr: u32) -> u8 {
+        self.inner.read_u8(addr)
+    }
+
+    fn read_u16(&self, addr: u32) -> u16 {
+        self.inner.read_u16(addr)
+    }
+
+    fn read_u32(&self, addr: u32) -> u32 {
+        self.inner.read_u32(addr)
+    }
+
+    fn read_into_slice(&self, addr: u32, output: &mut [u8]) {
+        self.inner.read_into_slice(addr, output)
+    }
+
+    fn write_u8(&mut self, addr: u32, data: u8) {
+        self.write_from_slice(addr, &[data]);
+    }
+
+    fn write_u16(&mut self, addr: u32, data: u16) {
+        let mut buf = [0; 2];
+        self.endian().write_u16(&mut buf, data);
+        self.write_from_slice(addr, &buf);
+    }
+
+    fn write_u32(&mut self, addr: u32, data: u32) {
+        let mut buf = [0; 4];
+        self.endian().write_u32(&mut buf, data);
+        self.write_from_slice(addr, &buf);
+    }
+
+    fn write_from_slice(&mut self, addr: u32, data: &[u8]) {
+        let len = self.writable(addr, data.len());
+        self.inner.write_from_slice(addr, &data[..len]);
+    }
+
+    fn is_page_mapped(&self, page_idx: u32) -> bool {
+        self.inner.is_page_mapped(page_idx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::memory::create_memory;
+
+    #[test]
+    fn drops_writes_past_limit() {
+        let inner = create_memory(EndianMode::native(), &[]);
+        let mut mem = Limited::new(inner, 0x1002);
+
+        mem.write_u8(0x1001, 1);
+        assert_eq!(mem.read_u8(0x1001), 1);
+        mem.write_u8(0x1002, 2);
+        assert_eq!(mem.read_u8(0x1002), 0);
+
+        // a write across the limit keeps the part under it
+        mem.write_u32(0x1000, u32::MAX);
+        assert_eq!(mem.read_u32(0x1000), u32::from_ne_bytes([255, 255, 0, 0]));
+        assert!(!mem.is_page_mapped(2));
+    }
+}
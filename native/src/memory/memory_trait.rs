@@ -1,4 +1,4 @@
-use crate::memory::{EndianMode, Segment};
+use crate::memory::{EndianMode, MemoryConfig, MemoryInit, Segment};
 use std::fmt::Debug;
 use std::ptr::{slice_from_raw_parts, NonNull};
 
@@ -8,6 +8,11 @@ pub trait Memory: Send + Sync + Debug {
     // If this memory type is fastmem-compatible, return the base address
     fn fastmem_addr(&self) -> Option<NonNull<u8>>;
 
+    // The first address past usable memory, if it doesn't span the whole space
+    fn limit(&self) -> Option<u32> {
+        None
+    }
+
     fn read_u8(&self, addr: u32) -> u8;
     fn read_u16(&self, addr: u32) -> u16;
     fn read_u32(&self, addr: u32) -> u32;
@@ -19,10 +24,15 @@ pub trait Memory: Send + Sync + Debug {
     fn write_u32(&mut self, addr: u32, data: u32);
 
     fn write_from_slice(&mut self, addr: u32, data: &[u8]);
+
+    // Whether the page has backing storage. Unmapped pages read as the `MemoryInit` fill.
+    fn is_page_mapped(&self, page_idx: u32) -> bool;
 }
 
 pub trait FastMem: Send + Sync + Debug {
     fn fastmem_addr(&self) -> *mut u8;
+
+    fn is_page_mapped(&self, page_idx: u32) -> bool;
 }
 
 impl<T> Memory for T
@@ -87,21 +97,48 @@ where
                 .copy_from(data.as_ptr(), data.len());
         }
     }
+
+    fn is_page_mapped(&self, page_idx: u32) -> bool {
+        FastMem::is_page_mapped(self, page_idx)
+    }
 }
 
+/// Only for use in unit tests
+#[cfg(test)]
 pub fn create_memory(endian: EndianMode, segments: &[Segment]) -> Box<dyn Memory> {
-    create_memory_impl(endian, segments, false)
+    create_memory_with(endian, segments, MemoryConfig::default())
+}
+
+/// Memory holding `segments`, with everything else holding `config.init`. Segment data
+/// past `config.limit` is left out.
+pub fn create_memory_with(
+    endian: EndianMode,
+    segments: &[Segment],
+    config: MemoryConfig,
+) -> Box<dyn Memory> {
+    match config.limit {
+        Some(limit) => {
+            let inner = create_memory_impl(endian, &[], config.init, false);
+            let mut mem = super::limited::Limited::new(inner, limit);
+            for seg in segments {
+                mem.write_from_slice(seg.base_addr, &seg.data);
+            }
+            mem
+        }
+        None => create_memory_impl(endian, segments, config.init, false),
+    }
 }
 
 /// Only for use in unit tests
 #[cfg(test)]
 pub fn create_memory_fastmem(endian: EndianMode, segments: &[Segment]) -> Box<dyn Memory> {
-    create_memory_impl(endian, segments, true)
+    create_memory_impl(endian, segments, MemoryInit::Zero, true)
 }
 
 fn create_memory_impl(
     endian: EndianMode,
     segments: &[Segment],
+    init: MemoryInit,
     force_fastmem: bool,
 ) -> Box<dyn Memory> {
     use super::slowmem::SlowMem;
@@ -109,26 +146,35 @@ fn create_memory_impl(
     if !force_fastmem {
         cfg_if::cfg_if! {
             if #[cfg(test)] {
-                return SlowMem::new(endian, segments);
+                return SlowMem::new(endian, segments, init);
             }
         }
     }
 
     cfg_if::cfg_if! {
         if #[cfg(windows)] {
-            super::fastmem_windows::FastMemWindows::try_new(endian, segments)
+            super::fastmem_windows::FastMemWindows::try_new(endian, segments, init)
                 .map(|x| -> Box<dyn Memory> { x })
-                .unwrap_or_else(|| SlowMem::new(endian, segments))
+                .unwrap_or_else(|| SlowMem::new(endian, segments, init))
         } else if #[cfg(unix)] {
-            super::fastmem_unix::FastMemUnix::try_new(endian, segments)
+            super::fastmem_unix::FastMemUnix::try_new(endian, segments, init)
                 .map(|x| -> Box<dyn Memory> { x })
-                .unwrap_or_else(|| SlowMem::new(endian, segments))
+                .unwrap_or_else(|| SlowMem::new(endian, segments, init))
         } else {
-            SlowMem::new(endian, segments)
+            SlowMem::new(endian, segments, init)
         }
     }
 }
 
 pub fn create_empty_memory(endian: EndianMode) -> Box<dyn Memory> {
-    super::emptymem::EmptyMem::new(endian)
+    create_empty_memory_with(endian, MemoryConfig::default())
+}
+
+/// Memory that ignores writes and reads as `config.init`, with `config.limit` still reported.
+pub fn create_empty_memory_with(endian: EndianMode, config: MemoryConfig) -> Box<dyn Memory> {
+    let mem = super::emptymem::EmptyMem::new(endian, config.init);
+    match config.limit {
+        Some(limit) => super::limited::Limited::new(mem, limit),
+        None => mem,
+    }
 }
@@ -1,5 +1,10 @@
+mod config;
+mod crc32;
+mod dump;
 mod emptymem;
 mod endian_mode;
+mod init;
+mod limited;
 mod memory_trait;
 mod segment;
 mod slowmem;
@@ -12,9 +17,13 @@ cfg_if::cfg_if! {
     }
 }
 
+pub use config::MemoryConfig;
+pub use crc32::{crc32, Crc32};
+pub use dump::hex_dump;
 pub use endian_mode::EndianMode;
-pub use memory_trait::{create_empty_memory, create_memory, Memory};
+pub use init::MemoryInit;
+pub use memory_trait::{create_empty_memory, create_empty_memory_with, create_memory_with, Memory};
 pub use segment::Segment;
 
 #[cfg(test)]
-pub use memory_trait::create_memory_fastmem;
+pub use memory_trait::{create_memory, create_memory_fastmem};
@@ -1,3 +1,4 @@
+use crate::memory::crc32;
 use crate::memory::endian_mode::EndianMode;
 use std::collections::HashMap;
 
@@ -32,6 +33,17 @@ impl Segment {
         }
     }
 
+    pub fn contains(&self, addr: u32) -> bool {
+        let addr = addr as u64;
+        let base = self.base_addr as u64;
+        base <= addr && addr < base + self.data.len() as u64
+    }
+
+    /// CRC-32 of `data`, for checking a copy of the segment.
+    pub fn checksum(&self) -> u32 {
+        crc32(&self.data)
+    }
+
     pub fn labels(&self) -> &HashMap<String, u32> {
         &self.labels
     }
@@ -46,6 +58,12 @@ impl Segment {
         self.data.extend_from_slice(&buf);
     }
 
+    pub fn append_u64(&mut self, data: u64) {
+        let mut buf = [0; 8];
+        self.endian.write_u64(&mut buf, data);
+        self.data.extend_from_slice(&buf);
+    }
+
     pub fn append_u8(&mut self, data: u8) {
         self.data.push(data);
     }
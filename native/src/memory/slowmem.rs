@@ -1,5 +1,5 @@
 use crate::memory::memory_trait::Memory;
-use crate::memory::{EndianMode, Segment};
+use crate::memory::{EndianMode, MemoryInit, Segment};
 use std::alloc::Layout;
 use std::convert::TryInto;
 use std::fmt::{Debug, Formatter};
@@ -27,11 +27,12 @@ fn create_boxed_page() -> Box<Page> {
 
 pub struct SlowMem {
     endian: EndianMode,
+    init: MemoryInit,
     pages: Pages,
 }
 
 impl SlowMem {
-    pub fn new(endian: EndianMode, segments: &[Segment]) -> Box<Self> {
+    pub fn new(endian: EndianMode, segments: &[Segment], init: MemoryInit) -> Box<Self> {
         let mut obj = unsafe {
             // SAFETY: SlowMem is not zero-sized type
             let ptr = std::alloc::alloc_zeroed(Layout::new::<SlowMem>()) as *mut SlowMem;
@@ -39,6 +40,8 @@ impl SlowMem {
             // SAFETY: ptr is allocated, and we're using `write`
             let endian_ptr = &mut (*ptr).endian as *mut EndianMode;
             endian_ptr.write(endian);
+            let init_ptr = &mut (*ptr).init as *mut MemoryInit;
+            init_ptr.write(init);
 
             // SAFETY: This is allocated just above, and we wrote to every field
             //         (except pages, which is an array full of None)
@@ -91,7 +94,9 @@ impl SlowMem {
     #[inline(always)]
     fn ensure_page(&mut self, page_idx: usize) -> &mut Page {
         if self.pages[page_idx as usize].is_none() {
-            self.pages[page_idx as usize] = Some(create_boxed_page());
+            let mut page = create_boxed_page();
+            self.init.fill(self.endian, &mut *page);
+            self.pages[page_idx as usize] = Some(page);
         }
         self.pages[page_idx as usize].as_mut().unwrap()
     }
@@ -121,32 +126,29 @@ impl Memory for SlowMem {
         self.pages[page_idx as usize]
             .as_ref()
             .map(|x| x[page_offset as usize])
-            .unwrap_or(0)
+            .unwrap_or_else(|| self.init.byte_at(self.endian, addr))
     }
 
     fn read_u16(&self, addr: u32) -> u16 {
         let page_idx = addr / PAGE_SIZE;
         let page_offset = addr % PAGE_SIZE;
-        if page_offset + 1 < PAGE_SIZE {
-            self.pages[page_idx as usize]
-                .as_ref()
-                .map(|x| self.endian.read_u16(&x[page_offset as usize..]))
-                .unwrap_or(0)
-        } else {
-            self.read_u16_unaligned(addr)
+        match &self.pages[page_idx as usize] {
+            Some(x) if page_offset + 1 < PAGE_SIZE => {
+                self.endian.read_u16(&x[page_offset as usize..])
+            }
+            // unmapped pages go byte by byte, as their fill depends on the address
+            _ => self.read_u16_unaligned(addr),
         }
     }
 
     fn read_u32(&self, addr: u32) -> u32 {
         let page_idx = addr / PAGE_SIZE;
         let page_offset = addr % PAGE_SIZE;
-        if page_offset + 3 < PAGE_SIZE {
-            self.pages[page_idx as usize]
-                .as_ref()
-                .map(|x| self.endian.read_u32(&x[page_offset as usize..]))
-                .unwrap_or(0)
-        } else {
-            self.read_u32_unaligned(addr)
+        match &self.pages[page_idx as usize] {
+            Some(x) if page_offset + 3 < PAGE_SIZE => {
+                self.endian.read_u32(&x[page_offset as usize..])
+            }
+            _ => self.read_u32_unaligned(addr),
         }
     }
 
@@ -176,7 +178,10 @@ impl Memory for SlowMem {
             if let Some(page) = self.pages[page_idx as usize].as_ref() {
                 output[output_from..output_to].copy_from_slice(&page[mem_from..mem_to]);
             } else {
-                output[output_from..output_to].fill(0);
+                for (i, x) in output[output_from..output_to].iter_mut().enumerate() {
+                    let at = addr.wrapping_add((output_from + i) as u32);
+                    *x = self.init.byte_at(self.endian, at);
+                }
             }
 
             page_offset = 0;
@@ -262,6 +267,10 @@ impl Memory for SlowMem {
             }
         }
     }
+
+    fn is_page_mapped(&self, page_idx: u32) -> bool {
+        self.pages[page_idx as usize].is_some()
+    }
 }
 
 #[cfg(test)]
@@ -271,7 +280,7 @@ mod test {
     #[test]
     fn basic() {
         let test = |endian| {
-            let mut mem = SlowMem::new(endian, &[]);
+            let mut mem = SlowMem::new(endian, &[], MemoryInit::Zero);
             mem.write_u8(0, 42);
             assert_eq!(mem.read_u8(0), 42);
             mem.write_u16(0, 42);
@@ -287,7 +296,7 @@ mod test {
     #[test]
     fn read_uncommitted() {
         let test = |endian| {
-            let mem = SlowMem::new(endian, &[]);
+            let mem = SlowMem::new(endian, &[], MemoryInit::Zero);
             assert_eq!(mem.read_u32(0x12345678), 0);
             assert_eq!(mem.read_u32(0), 0);
             assert_eq!(mem.read_u32(0xfffffff0), 0);
@@ -297,10 +306,25 @@ mod test {
         test(EndianMode::Big);
     }
 
+    #[test]
+    fn read_uncommitted_poison() {
+        let test = |endian| {
+            let mut mem = SlowMem::new(endian, &[], MemoryInit::Poison);
+            assert_eq!(mem.read_u32(0x12345678), 0xdeadbeef);
+
+            // the rest of a written page keeps the pattern
+            mem.write_u8(0x1000, 0);
+            assert_eq!(mem.read_u32(0x1004), 0xdeadbeef);
+        };
+
+        test(EndianMode::Little);
+        test(EndianMode::Big);
+    }
+
     #[test]
     fn unaligned() {
         let test = |endian| {
-            let mut mem = SlowMem::new(endian, &[]);
+            let mut mem = SlowMem::new(endian, &[], MemoryInit::Zero);
             mem.write_u16(4095, 42);
             assert_eq!(mem.read_u16(4095), 42);
             mem.write_u32(4094, 0x12345678);
@@ -315,14 +339,14 @@ mod test {
 
     #[test]
     fn endian() {
-        let mut mem = SlowMem::new(EndianMode::Little, &[]);
+        let mut mem = SlowMem::new(EndianMode::Little, &[], MemoryInit::Zero);
         mem.write_u32(0, 0x11223344);
         assert_eq!(mem.read_u8(0), 0x44);
         assert_eq!(mem.read_u8(1), 0x33);
         assert_eq!(mem.read_u8(2), 0x22);
         assert_eq!(mem.read_u8(3), 0x11);
 
-        let mut mem = SlowMem::new(EndianMode::Big, &[]);
+        let mut mem = SlowMem::new(EndianMode::Big, &[], MemoryInit::Zero);
         mem.write_u32(0, 0x11223344);
         assert_eq!(mem.read_u8(0), 0x11);
         assert_eq!(mem.read_u8(1), 0x22);
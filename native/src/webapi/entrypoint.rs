@@ -1,11 +1,17 @@
 use super::state::State;
-use super::util::take_state;
+use super::util::{branch_stats_object, cache_stats_object, take_state};
+use crate::assembler::supported_mnemonics;
 use crate::component::RegisterName;
-use crate::memory::EndianMode;
+use crate::disassembler::{disassemble_region, supported_opcodes};
+use crate::executor::CacheConfig;
+use crate::memory::{EndianMode, MemoryInit};
+use crate::webapi::reg_format::RegisterFormat;
+use crate::webapi::updates::Updates;
 use crate::webapi::util::log_console;
 use neon::prelude::*;
 use neon::types::buffer::TypedArray;
 use std::cell::RefCell;
+use std::time::Duration;
 
 const API_VERSION: u32 = 1;
 
@@ -21,7 +27,7 @@ fn init(mut cx: FunctionContext) -> JsResult<JsNumber> {
     let callback = cx.argument::<JsFunction>(0)?.root(&mut cx);
 
     let mut guard = super::GLOBAL_STATE.lock();
-    if let Some(x) = guard.take() {
+    if let Some(mut x) = guard.take() {
         // Re-init is not an error because refreshing the page causes them.
         log_console(&mut cx, "Re-initializing native module!");
         let _ = x.stop();
@@ -34,7 +40,7 @@ fn init(mut cx: FunctionContext) -> JsResult<JsNumber> {
 
 fn finalize(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     let mut guard = super::GLOBAL_STATE.lock();
-    if let Some(x) = guard.take() {
+    if let Some(mut x) = guard.take() {
         let _ = x.stop();
         drop(x);
     }
@@ -53,15 +59,18 @@ fn reset(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     Ok(JsUndefined::new(&mut cx))
 }
 
-fn assemble(mut cx: FunctionContext) -> JsResult<JsValue> {
-    let code = cx.argument::<JsString>(0)?.value(&mut cx);
-    let endian = cx.argument::<JsString>(1)?.value(&mut cx);
-
-    let endian = match endian.as_str() {
+fn parse_endian(endian: &str) -> EndianMode {
+    match endian {
         "big" => EndianMode::Big,
         "little" => EndianMode::Little,
         _ => EndianMode::native(),
-    };
+    }
+}
+
+fn assemble(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let code = cx.argument::<JsString>(0)?.value(&mut cx);
+    let endian = cx.argument::<JsString>(1)?.value(&mut cx);
+    let endian = parse_endian(&endian);
 
     let mut state = take_state(&mut cx)?;
 
@@ -74,6 +83,44 @@ fn assemble(mut cx: FunctionContext) -> JsResult<JsValue> {
     }
 }
 
+fn assemble_units(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let arr = cx.argument::<JsArray>(0)?.to_vec(&mut cx)?;
+    let endian = cx.argument::<JsString>(1)?.value(&mut cx);
+    let endian = parse_endian(&endian);
+
+    let mut units = Vec::new();
+    for x in arr {
+        let obj = x.downcast_or_throw::<JsObject, _>(&mut cx)?;
+        let name = obj.get::<JsString, _, _>(&mut cx, "name")?.value(&mut cx);
+        let code = obj.get::<JsString, _, _>(&mut cx, "code")?.value(&mut cx);
+        units.push((name, code));
+    }
+
+    let mut state = take_state(&mut cx)?;
+
+    match state.assemble_units(&units, endian) {
+        Ok(x) => {
+            state.notify(x);
+            Ok(cx.null().upcast())
+        }
+        Err(e) => Ok(cx.string(e).upcast()),
+    }
+}
+
+fn assemble_and_run(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let code = cx.argument::<JsString>(0)?.value(&mut cx);
+    let endian = cx.argument::<JsString>(1)?.value(&mut cx);
+    let endian = parse_endian(&endian);
+    let max_steps = cx.argument::<JsNumber>(2)?.value(&mut cx) as u64;
+
+    let mut state = take_state(&mut cx)?;
+
+    match state.assemble_and_run(&code, endian, max_steps) {
+        Ok(()) => Ok(cx.null().upcast()),
+        Err(e) => Ok(cx.string(e).upcast()),
+    }
+}
+
 fn edit_register(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     let idx = cx.argument::<JsNumber>(0)?.value(&mut cx) as u8;
     let val = cx.argument::<JsNumber>(1)?.value(&mut cx) as u32;
@@ -87,6 +134,40 @@ fn edit_register(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     Ok(cx.undefined())
 }
 
+fn edit_hi(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let val = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.edit_hi(val);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn edit_lo(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let val = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.edit_lo(val);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn edit_pc(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let val = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    let mut state = take_state(&mut cx)?;
+
+    match state.edit_pc(val) {
+        Ok(x) => {
+            state.notify(x);
+            Ok(cx.null().upcast())
+        }
+        Err(e) => Ok(cx.string(e).upcast()),
+    }
+}
+
 fn read_memory(mut cx: FunctionContext) -> JsResult<JsValue> {
     let page_idx = cx.argument::<JsNumber>(0)?.value(&mut cx) as i32;
     let mut dst = cx.argument::<JsUint8Array>(1)?;
@@ -117,6 +198,255 @@ fn read_memory(mut cx: FunctionContext) -> JsResult<JsValue> {
     })
 }
 
+fn read_pages(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let start_page = cx.argument::<JsNumber>(0)?.value(&mut cx) as i64;
+    let count = cx.argument::<JsNumber>(1)?.value(&mut cx) as i64;
+    let mut dst = cx.argument::<JsUint8Array>(2)?;
+    let end_page = start_page.checked_add(count);
+    if start_page < 0 || count < 0 || !matches!(end_page, Some(x) if x <= 1048576) {
+        return Ok(cx.null().upcast());
+    }
+
+    let state = take_state(&mut cx)?;
+    let output = dst.as_mut_slice(&mut cx);
+    if output.len() != count as usize * 4096 {
+        panic!("buffer length must be 4096 bytes per page");
+    }
+
+    state.read_pages(start_page as u32, count as u32, output);
+    Ok(dst.upcast())
+}
+
+fn read_memory_words(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let page_idx = cx.argument::<JsNumber>(0)?.value(&mut cx) as i32;
+    let mut dst = cx.argument::<JsTypedArray<u32>>(1)?;
+    if !(0..1048576).contains(&page_idx) {
+        return Ok(cx.null().upcast());
+    }
+
+    let state = take_state(&mut cx)?;
+    let output = dst.as_mut_slice(&mut cx);
+    if output.len() != 1024 {
+        panic!("buffer length must be 1024 words");
+    }
+
+    state.read_memory_words(page_idx as u32, output);
+    Ok(dst.upcast())
+}
+
+fn read_range(mut cx: FunctionContext) -> JsResult<JsTypedArray<u8>> {
+    let addr = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let mut dst = cx.argument::<JsUint8Array>(1)?;
+
+    let state = take_state(&mut cx)?;
+    state.read_range(addr, dst.as_mut_slice(&mut cx));
+
+    Ok(dst)
+}
+
+fn write_memory(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let addr = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let src = cx.argument::<JsUint8Array>(1)?;
+    let bytes = src.as_slice(&cx).to_vec();
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.write_memory(addr, &bytes);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn load_binary(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let base = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let src = cx.argument::<JsUint8Array>(1)?;
+    let bytes = src.as_slice(&cx).to_vec();
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.load_binary(base, &bytes);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn snapshot(mut cx: FunctionContext) -> JsResult<JsTypedArray<u8>> {
+    let bytes = take_state(&mut cx)?.snapshot();
+    JsUint8Array::from_slice(&mut cx, &bytes)
+}
+
+fn restore(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let src = cx.argument::<JsUint8Array>(0)?;
+    let bytes = src.as_slice(&cx).to_vec();
+
+    let mut state = take_state(&mut cx)?;
+    match state.restore(&bytes) {
+        Ok(()) => Ok(cx.null().upcast()),
+        Err(e) => Ok(cx.string(e).upcast()),
+    }
+}
+
+fn disassemble_words(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let base = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let src = cx.argument::<JsTypedArray<u32>>(1)?;
+    let words = src.as_slice(&cx).to_vec();
+
+    let listing = disassemble_region(base, &words);
+    let arr = cx.empty_array();
+    for (i, (addr, text)) in listing.into_iter().enumerate() {
+        let obj = cx.empty_object();
+        let addr = cx.number(addr);
+        let text = cx.string(text);
+        obj.set(&mut cx, "addr", addr)?;
+        obj.set(&mut cx, "text", text)?;
+        arr.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(arr)
+}
+
+fn disassemble_image(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let base = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let src = cx.argument::<JsUint8Array>(1)?;
+    let bytes = src.as_slice(&cx).to_vec();
+    let endian = cx.argument::<JsString>(2)?.value(&mut cx);
+    let endian = parse_endian(&endian);
+
+    let listing = take_state(&mut cx)?.disassemble_image(base, &bytes, endian);
+    let arr = cx.empty_array();
+    for (i, (addr, text)) in listing.into_iter().enumerate() {
+        let obj = cx.empty_object();
+        let addr = cx.number(addr);
+        let text = cx.string(text);
+        obj.set(&mut cx, "addr", addr)?;
+        obj.set(&mut cx, "text", text)?;
+        arr.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(arr)
+}
+
+fn get_globals(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let globals = take_state(&mut cx)?.globals().clone();
+
+    let obj = cx.empty_object();
+    for (name, addr) in globals {
+        let addr = cx.number(addr);
+        obj.set(&mut cx, name.as_str(), addr)?;
+    }
+    Ok(obj)
+}
+
+fn get_label_address(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let name = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    match take_state(&mut cx)?.label_address(&name) {
+        Some(addr) => Ok(cx.number(addr).upcast()),
+        None => Ok(cx.null().upcast()),
+    }
+}
+
+fn get_warnings(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let warnings = take_state(&mut cx)?.warnings().to_vec();
+
+    let arr = cx.empty_array();
+    for (i, warning) in warnings.into_iter().enumerate() {
+        let obj = cx.empty_object();
+        let line = cx.number(warning.line as u32);
+        let message = cx.string(warning.message);
+        obj.set(&mut cx, "line", line)?;
+        obj.set(&mut cx, "message", message)?;
+        arr.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(arr)
+}
+
+fn export_binary(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let result = take_state(&mut cx)?.export_binary();
+    match result {
+        Ok(image) => Ok(JsUint8Array::from_slice(&mut cx, &image)?.upcast()),
+        Err(e) => Ok(cx.string(e).upcast()),
+    }
+}
+
+fn get_checksums(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let (image, segments) = {
+        let state = take_state(&mut cx)?;
+        (state.image_checksum(), state.segment_checksums())
+    };
+
+    let obj = cx.empty_object();
+    let image = cx.number(image);
+    obj.set(&mut cx, "image", image)?;
+
+    let arr = cx.empty_array();
+    for (i, (base, crc)) in segments.into_iter().enumerate() {
+        let seg = cx.empty_object();
+        let base = cx.number(base);
+        let crc = cx.number(crc);
+        seg.set(&mut cx, "base", base)?;
+        seg.set(&mut cx, "crc", crc)?;
+        arr.set(&mut cx, i as u32, seg)?;
+    }
+    obj.set(&mut cx, "segments", arr)?;
+    Ok(obj)
+}
+
+fn get_listing(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let listing = match take_state(&mut cx)?.listing() {
+        Ok(x) => x,
+        Err(e) => {
+            log_console(&mut cx, e);
+            return Ok(cx.null().upcast());
+        }
+    };
+
+    let arr = cx.empty_array();
+    for (i, line) in listing.into_iter().enumerate() {
+        let obj = cx.empty_object();
+        let addr = match line.addr {
+            Some(x) => cx.number(x).upcast::<JsValue>(),
+            None => cx.null().upcast(),
+        };
+        let bytes = JsUint8Array::from_slice(&mut cx, &line.bytes)?;
+        let source = cx.string(line.source);
+        obj.set(&mut cx, "addr", addr)?;
+        obj.set(&mut cx, "bytes", bytes)?;
+        obj.set(&mut cx, "source", source)?;
+        arr.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(arr.upcast())
+}
+
+fn provide_input(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let text = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.provide_input(&text);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn push_key(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let key = cx.argument::<JsNumber>(0)?.value(&mut cx) as u8;
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.push_key(key);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn dump_memory(mut cx: FunctionContext) -> JsResult<JsString> {
+    let addr = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let len = cx.argument::<JsNumber>(1)?.value(&mut cx) as u32;
+
+    let dump = take_state(&mut cx)?.dump_memory(addr, len);
+    Ok(cx.string(dump))
+}
+
+fn export_intel_hex(mut cx: FunctionContext) -> JsResult<JsString> {
+    let hex = take_state(&mut cx)?.export_intel_hex();
+    Ok(cx.string(hex))
+}
+
 fn step(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     let mut state = take_state(&mut cx)?;
 
@@ -128,18 +458,148 @@ fn step(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     Ok(cx.undefined())
 }
 
+fn step_over(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let mut state = take_state(&mut cx)?;
+
+    match state.step_over() {
+        Ok(x) => state.notify(x),
+        Err(e) => {
+            state.notify(Updates::REGISTERS);
+            log_console(&mut cx, e);
+        }
+    }
+
+    Ok(cx.undefined())
+}
+
+fn run_until(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let addr = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+
+    let mut state = take_state(&mut cx)?;
+    if let Err(e) = state.run_until(addr) {
+        log_console(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+fn run_steps(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let n = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+
+    let mut state = take_state(&mut cx)?;
+    if let Err(e) = state.run_steps(n) {
+        log_console(&mut cx, e);
+    }
+
+    Ok(cx.undefined())
+}
+
+fn exec_with_timeout(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let ms = cx.argument::<JsNumber>(0)?.value(&mut cx) as u64;
+
+    match take_state(&mut cx)?.exec_with_timeout(ms) {
+        Ok(()) => Ok(cx.null().upcast()),
+        Err(e) => Ok(cx.string(e).upcast()),
+    }
+}
+
 fn run(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     let allow_jit = cx.argument::<JsBoolean>(0)?.value(&mut cx);
 
-    let state = take_state(&mut cx)?;
+    let mut state = take_state(&mut cx)?;
     let updates = state.run(allow_jit);
     state.notify(updates);
 
     Ok(cx.undefined())
 }
 
+fn set_run_limit(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let limit = cx.argument::<JsNumber>(0)?.value(&mut cx);
+    let limit = if limit >= 1.0 {
+        Some(limit as u64)
+    } else {
+        None
+    };
+
+    take_state(&mut cx)?.set_run_limit(limit);
+
+    Ok(cx.undefined())
+}
+
+fn set_update_interval(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let ms = cx.argument::<JsNumber>(0)?.value(&mut cx).max(0.0);
+
+    take_state(&mut cx)?.set_update_interval(Duration::from_millis(ms as u64));
+
+    Ok(cx.undefined())
+}
+
+fn set_register_format(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let format = match cx.argument::<JsString>(0)?.value(&mut cx).as_str() {
+        "signed" => RegisterFormat::Signed,
+        "unsigned" => RegisterFormat::Unsigned,
+        "hex" => RegisterFormat::Hex,
+        _ => RegisterFormat::Raw,
+    };
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_register_format(format);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn set_memory_init(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let init = match cx.argument::<JsString>(0)?.value(&mut cx).as_str() {
+        "poison" => MemoryInit::Poison,
+        _ => MemoryInit::Zero,
+    };
+    take_state(&mut cx)?.set_memory_init(init);
+
+    Ok(cx.undefined())
+}
+
+fn set_memory_limit(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let limit = cx.argument::<JsNumber>(0)?.value(&mut cx);
+    let limit = if limit >= 1.0 {
+        Some(limit as u32)
+    } else {
+        None
+    };
+
+    take_state(&mut cx)?.set_memory_limit(limit);
+
+    Ok(cx.undefined())
+}
+
+fn set_random_seed(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let seed = cx.argument::<JsNumber>(0)?.value(&mut cx);
+    take_state(&mut cx)?.set_random_seed(seed as u64);
+
+    Ok(cx.undefined())
+}
+
+fn pause(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let mut state = take_state(&mut cx)?;
+
+    let updates = state.pause();
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn resume(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let allow_jit = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.resume(allow_jit);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
 fn stop(mut cx: FunctionContext) -> JsResult<JsUndefined> {
-    let state = take_state(&mut cx)?;
+    let mut state = take_state(&mut cx)?;
 
     let updates = state.stop();
     state.notify(updates);
@@ -147,6 +607,297 @@ fn stop(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     Ok(cx.undefined())
 }
 
+fn set_endian(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let endian = cx.argument::<JsString>(0)?.value(&mut cx);
+    let endian = parse_endian(&endian);
+
+    let mut state = take_state(&mut cx)?;
+
+    match state.set_endian(endian) {
+        Ok(x) => {
+            state.notify(x);
+            Ok(cx.null().upcast())
+        }
+        Err(e) => Ok(cx.string(e).upcast()),
+    }
+}
+
+fn set_abi_register_names(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_abi_register_names(enabled);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn set_strict_segments(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    take_state(&mut cx)?.set_strict_segments(enabled);
+
+    Ok(cx.undefined())
+}
+
+fn set_lint(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    take_state(&mut cx)?.set_lint(enabled);
+
+    Ok(cx.undefined())
+}
+
+fn set_fill_delay_slots(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    take_state(&mut cx)?.set_fill_delay_slots(enabled);
+
+    Ok(cx.undefined())
+}
+
+fn set_entry_label(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let label = cx.argument::<JsString>(0)?.value(&mut cx);
+
+    take_state(&mut cx)?.set_entry_label(&label);
+
+    Ok(cx.undefined())
+}
+
+fn set_force_interpreter(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_force_interpreter(enabled);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn set_profiling(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_profiling(enabled);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn get_profile(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let profile = take_state(&mut cx)?.profile();
+
+    let obj = cx.empty_object();
+    for (mnemonic, count) in profile {
+        let count = cx.number(count as f64);
+        obj.set(&mut cx, mnemonic.as_str(), count)?;
+    }
+    Ok(obj)
+}
+
+fn get_memory_heatmap(mut cx: FunctionContext) -> JsResult<JsArray> {
+    let heatmap = take_state(&mut cx)?.memory_heatmap();
+
+    let arr = cx.empty_array();
+    for (i, (page, x)) in heatmap.into_iter().enumerate() {
+        let obj = cx.empty_object();
+        let page = cx.number(page);
+        let reads = cx.number(x.reads as f64);
+        let writes = cx.number(x.writes as f64);
+        obj.set(&mut cx, "page", page)?;
+        obj.set(&mut cx, "reads", reads)?;
+        obj.set(&mut cx, "writes", writes)?;
+        arr.set(&mut cx, i as u32, obj)?;
+    }
+    Ok(arr)
+}
+
+fn set_cache(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let arg = cx.argument::<JsValue>(0)?;
+    let config = match arg.downcast::<JsObject, _>(&mut cx) {
+        Ok(obj) => {
+            let mut field = |name| -> NeonResult<Result<u32, String>> {
+                let val = obj.get::<JsNumber, _, _>(&mut cx, name)?.value(&mut cx);
+                // `as u32` would saturate 1e10 and truncate 1.5
+                Ok(match val {
+                    x if x.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&x) => Ok(x as u32),
+                    x => Err(format!("{} {} is not a whole number", name, x)),
+                })
+            };
+            let fields = (field("blockSize")?, field("sets")?, field("ways")?);
+            match fields {
+                (Ok(block_size), Ok(sets), Ok(ways)) => Some(CacheConfig {
+                    block_size,
+                    sets,
+                    ways,
+                }),
+                (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                    return Ok(cx.string(e).upcast())
+                }
+            }
+        }
+        Err(_) => None,
+    };
+
+    let mut state = take_state(&mut cx)?;
+
+    match state.set_cache(config) {
+        Ok(x) => {
+            state.notify(x);
+            Ok(cx.null().upcast())
+        }
+        Err(e) => Ok(cx.string(e).upcast()),
+    }
+}
+
+fn get_cache_stats(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let stats = match take_state(&mut cx)?.cache_stats() {
+        Some(x) => x,
+        None => return Ok(cx.null().upcast()),
+    };
+
+    Ok(cache_stats_object(&mut cx, stats)?.upcast())
+}
+
+fn set_branch_prediction(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_branch_prediction(enabled);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn get_branch_stats(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let report = match take_state(&mut cx)?.branch_stats() {
+        Some(x) => x,
+        None => return Ok(cx.null().upcast()),
+    };
+
+    let obj = cx.empty_object();
+    let total = branch_stats_object(&mut cx, report.total)?;
+    obj.set(&mut cx, "total", total)?;
+
+    let arr = cx.empty_array();
+    for (i, (addr, x)) in report.branches.into_iter().enumerate() {
+        let entry = branch_stats_object(&mut cx, x)?;
+        let addr = cx.number(addr);
+        entry.set(&mut cx, "addr", addr)?;
+        arr.set(&mut cx, i as u32, entry)?;
+    }
+    obj.set(&mut cx, "branches", arr)?;
+
+    Ok(obj.upcast())
+}
+
+fn reset_profile(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let mut state = take_state(&mut cx)?;
+    let updates = state.reset_profile();
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn enable_trace(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.enable_trace(enabled);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn set_data_as_words(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_data_as_words(enabled);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn set_diff_updates(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_diff_updates(enabled);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn number_list(cx: &mut FunctionContext, idx: usize) -> NeonResult<Vec<u32>> {
+    let arr = cx.argument::<JsArray>(idx)?.to_vec(cx)?;
+    arr.into_iter()
+        .map(|x| Ok(x.downcast_or_throw::<JsNumber, _>(cx)?.value(cx) as u32))
+        .collect()
+}
+
+fn set_breakpoints(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let addrs = number_list(&mut cx, 0)?;
+
+    let mut state = take_state(&mut cx)?;
+    state.set_breakpoints(addrs);
+
+    Ok(cx.undefined())
+}
+
+fn set_watchpoints(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let addrs = number_list(&mut cx, 0)?;
+
+    let mut state = take_state(&mut cx)?;
+    state.set_watchpoints(addrs);
+
+    Ok(cx.undefined())
+}
+
+fn set_disasm_window(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let before = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let after = cx.argument::<JsNumber>(1)?.value(&mut cx) as u32;
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_disasm_window(before, after);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn set_delay_slots(mut cx: FunctionContext) -> JsResult<JsUndefined> {
+    let enabled = cx.argument::<JsBoolean>(0)?.value(&mut cx);
+
+    let mut state = take_state(&mut cx)?;
+    let updates = state.set_delay_slots(enabled);
+    state.notify(updates);
+
+    Ok(cx.undefined())
+}
+
+fn get_supported_instructions(mut cx: FunctionContext) -> JsResult<JsObject> {
+    let mnemonics = cx.empty_array();
+    for (i, x) in supported_mnemonics().into_iter().enumerate() {
+        let x = cx.string(x);
+        mnemonics.set(&mut cx, i as u32, x)?;
+    }
+
+    let opcodes = cx.empty_array();
+    for (i, x) in supported_opcodes().into_iter().enumerate() {
+        let obj = cx.empty_object();
+        let mnemonic = cx.string(x.mnemonic);
+        let example = cx.number(x.example);
+        obj.set(&mut cx, "mnemonic", mnemonic)?;
+        obj.set(&mut cx, "example", example)?;
+        opcodes.set(&mut cx, i as u32, obj)?;
+    }
+
+    let ret = cx.empty_object();
+    ret.set(&mut cx, "mnemonics", mnemonics)?;
+    ret.set(&mut cx, "opcodes", opcodes)?;
+    Ok(ret)
+}
+
 fn get_native_endian(mut cx: FunctionContext) -> JsResult<JsString> {
     let endian = match EndianMode::native() {
         EndianMode::Little => "little",
@@ -168,11 +919,70 @@ pub fn register_functions(cx: &mut ModuleContext) -> NeonResult<()> {
     cx.export_function("finalize", finalize)?;
     cx.export_function("reset", reset)?;
     cx.export_function("assemble", assemble)?;
+    cx.export_function("assembleUnits", assemble_units)?;
+    cx.export_function("assembleAndRun", assemble_and_run)?;
     cx.export_function("editRegister", edit_register)?;
+    cx.export_function("editHi", edit_hi)?;
+    cx.export_function("editLo", edit_lo)?;
+    cx.export_function("editPc", edit_pc)?;
     cx.export_function("readMemory", read_memory)?;
+    cx.export_function("readRange", read_range)?;
+    cx.export_function("readPages", read_pages)?;
+    cx.export_function("readMemoryWords", read_memory_words)?;
+    cx.export_function("writeMemory", write_memory)?;
+    cx.export_function("dumpMemory", dump_memory)?;
+    cx.export_function("loadBinary", load_binary)?;
+    cx.export_function("snapshot", snapshot)?;
+    cx.export_function("restore", restore)?;
+    cx.export_function("getGlobals", get_globals)?;
+    cx.export_function("getLabelAddress", get_label_address)?;
+    cx.export_function("getWarnings", get_warnings)?;
+    cx.export_function("disassembleRegion", disassemble_words)?;
+    cx.export_function("disassembleImage", disassemble_image)?;
+    cx.export_function("exportBinary", export_binary)?;
+    cx.export_function("getChecksums", get_checksums)?;
+    cx.export_function("exportIntelHex", export_intel_hex)?;
+    cx.export_function("getListing", get_listing)?;
     cx.export_function("step", step)?;
+    cx.export_function("stepOver", step_over)?;
+    cx.export_function("runUntil", run_until)?;
+    cx.export_function("runSteps", run_steps)?;
+    cx.export_function("setBreakpoints", set_breakpoints)?;
+    cx.export_function("setWatchpoints", set_watchpoints)?;
+    cx.export_function("execWithTimeout", exec_with_timeout)?;
     cx.export_function("run", run)?;
     cx.export_function("stop", stop)?;
+    cx.export_function("pause", pause)?;
+    cx.export_function("resume", resume)?;
+    cx.export_function("provideInput", provide_input)?;
+    cx.export_function("pushKey", push_key)?;
+    cx.export_function("setRunLimit", set_run_limit)?;
+    cx.export_function("setUpdateInterval", set_update_interval)?;
+    cx.export_function("setRandomSeed", set_random_seed)?;
+    cx.export_function("setMemoryInit", set_memory_init)?;
+    cx.export_function("setMemoryLimit", set_memory_limit)?;
+    cx.export_function("setRegisterFormat", set_register_format)?;
+    cx.export_function("setEndian", set_endian)?;
+    cx.export_function("setAbiRegisterNames", set_abi_register_names)?;
+    cx.export_function("setDelaySlots", set_delay_slots)?;
+    cx.export_function("setDisasmWindow", set_disasm_window)?;
+    cx.export_function("setDataAsWords", set_data_as_words)?;
+    cx.export_function("setDiffUpdates", set_diff_updates)?;
+    cx.export_function("enableTrace", enable_trace)?;
+    cx.export_function("setProfiling", set_profiling)?;
+    cx.export_function("getProfile", get_profile)?;
+    cx.export_function("getMemoryHeatmap", get_memory_heatmap)?;
+    cx.export_function("resetProfile", reset_profile)?;
+    cx.export_function("setCache", set_cache)?;
+    cx.export_function("getCacheStats", get_cache_stats)?;
+    cx.export_function("setBranchPrediction", set_branch_prediction)?;
+    cx.export_function("getBranchStats", get_branch_stats)?;
+    cx.export_function("setForceInterpreter", set_force_interpreter)?;
+    cx.export_function("setStrictSegments", set_strict_segments)?;
+    cx.export_function("setLint", set_lint)?;
+    cx.export_function("setFillDelaySlots", set_fill_delay_slots)?;
+    cx.export_function("setEntryLabel", set_entry_label)?;
+    cx.export_function("getSupportedInstructions", get_supported_instructions)?;
     cx.export_function("getNativeEndian", get_native_endian)?;
     cx.export_function("convertToPipeline", convert_to_pipeline)?;
     Ok(())
@@ -27,41 +27,73 @@ pub fn start(allow_jit: bool) {
     *guard = Some(spawn(move || run_thread(allow_jit)));
 }
 
-/// Returns true if was running
+/// Returns true if was running. A run that already stopped on its own, on an error or to
+/// wait for input, wasn't.
 pub fn stop() -> bool {
-    FLAG_RUN.store(false, Ordering::Release);
+    let was_running = FLAG_RUN.swap(false, Ordering::AcqRel);
     let mut guard = LOOPER.lock();
     // FIXME: Second unwrap (first is ok because it means thread panicked)
     if let Some(x) = guard.take() {
         x.join().unwrap().unwrap();
-        true
-    } else {
-        false
+    }
+    was_running
+}
+
+/// Coalesces a run's updates into at most one notification per `interval`.
+struct Throttle {
+    interval: Duration,
+    last: Instant,
+}
+
+impl Throttle {
+    fn new(interval: Duration, now: Instant) -> Self {
+        Throttle {
+            interval,
+            last: now,
+        }
+    }
+
+    /// Whether to notify at `now`. If so, the next one is due an interval later.
+    fn due(&mut self, now: Instant) -> bool {
+        if now - self.last > self.interval {
+            self.last = now;
+            true
+        } else {
+            false
+        }
     }
 }
 
 fn run_thread(allow_jit: bool) -> Option<()> {
     let mut guard = GLOBAL_STATE.lock();
-    let mut last_notify_time = Instant::now();
+    let mut throttle = Throttle::new(guard.as_ref()?.update_interval(), Instant::now());
     let mut updates = Updates::empty();
 
     // Use relaxed here. We acquire below there.
     while FLAG_RUN.load(Ordering::Relaxed) {
-        //TODO: Handle exceptions (like overflow)
-
-        if allow_jit {
-            updates |= guard.as_mut()?.exec().ok()?;
-        } else {
-            updates |= guard.as_mut()?.step().ok()?;
+        match guard.as_mut()?.run_tick(allow_jit) {
+            Ok(x) if guard.as_ref()?.waiting_for_input() => {
+                // pause until the frontend provides input and runs again
+                FLAG_RUN.store(false, Ordering::Release);
+                guard.as_mut()?.notify(updates | x | Updates::FLAG_RUNNING);
+                return Some(());
+            }
+            Ok(x) => updates |= x,
+            Err(e) => {
+                // stop on our own, and tell the frontend why
+                FLAG_RUN.store(false, Ordering::Release);
+                let state = guard.as_mut()?;
+                state.set_last_error(e);
+                state.notify(updates | Updates::REGISTERS | Updates::FLAG_RUNNING);
+                return Some(());
+            }
         }
 
-        let now = Instant::now();
-        if 10 < (now - last_notify_time).as_millis() {
+        if throttle.due(Instant::now()) {
             guard.as_mut()?.notify(updates);
             MutexGuard::unlock_fair(guard);
 
             // Code here runs mutex unlocked
-            last_notify_time = now;
 
             // We need to check if we should exit before locking mutex
             // or we will get deadlock
@@ -80,3 +112,52 @@ fn run_thread(allow_jit: bool) -> Option<()> {
 
     Some(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stop_after_stopping_itself() {
+        // a run that ended on its own, as on an error or while waiting for input
+        FLAG_RUN.store(true, Ordering::Release);
+        *LOOPER.lock() = Some(spawn(|| {
+            FLAG_RUN.store(false, Ordering::Release);
+            Some(())
+        }));
+        while is_running() {
+            std::thread::yield_now();
+        }
+        assert!(!stop());
+
+        // one still going
+        FLAG_RUN.store(true, Ordering::Release);
+        *LOOPER.lock() = Some(spawn(|| {
+            while FLAG_RUN.load(Ordering::Acquire) {
+                std::thread::yield_now();
+            }
+            Some(())
+        }));
+        assert!(stop());
+        assert!(!is_running());
+        assert!(!stop());
+    }
+
+    #[test]
+    fn throttle_bounds_rate() {
+        let start = Instant::now();
+        let mut throttle = Throttle::new(Duration::from_millis(10), start);
+
+        // a tick every 50us for a second
+        let notified = (1..=20_000)
+            .filter(|i| throttle.due(start + Duration::from_micros(i * 50)))
+            .count();
+        assert!((90..=100).contains(&notified), "{}", notified);
+
+        // nothing is due before an interval has passed
+        let mut throttle = Throttle::new(Duration::from_millis(250), start);
+        assert!(!throttle.due(start + Duration::from_millis(250)));
+        assert!(throttle.due(start + Duration::from_millis(251)));
+        assert!(!throttle.due(start + Duration::from_millis(300)));
+    }
+}
@@ -1,5 +1,7 @@
 mod entrypoint;
 mod looper;
+mod reg_format;
+mod snapshot;
 mod state;
 mod updates;
 mod util;
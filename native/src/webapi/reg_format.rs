@@ -0,0 +1,37 @@
+/// How register values are sent as text, besides the raw numbers.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RegisterFormat {
+    /// Only the raw numbers.
+    Raw,
+    Signed,
+    Unsigned,
+    Hex,
+}
+
+impl RegisterFormat {
+    /// `None` for `Raw`, which sends no text.
+    pub fn format(self, val: u32) -> Option<String> {
+        match self {
+            RegisterFormat::Raw => None,
+            RegisterFormat::Signed => Some((val as i32).to_string()),
+            RegisterFormat::Unsigned => Some(val.to_string()),
+            RegisterFormat::Hex => Some(format!("0x{:08x}", val)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format() {
+        assert_eq!(RegisterFormat::Raw.format(0xffffffff), None);
+        assert_eq!(RegisterFormat::Signed.format(0xffffffff).unwrap(), "-1");
+        assert_eq!(
+            RegisterFormat::Unsigned.format(0xffffffff).unwrap(),
+            "4294967295"
+        );
+        assert_eq!(RegisterFormat::Hex.format(10).unwrap(), "0x0000000a");
+    }
+}
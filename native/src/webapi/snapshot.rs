@@ -0,0 +1,193 @@
+use crate::executor::Arch;
+use crate::memory::{EndianMode, Segment};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{Cursor, Read};
+
+const MAGIC: &[u8; 8] = b"MIPSSNAP";
+const VERSION: u32 = 2;
+const PAGE_SIZE: usize = 4096;
+
+/// Machine state as stored in a snapshot.
+///
+/// Layout (all integers little endian):
+/// magic, version, endian (0 = little, 1 = big), 32 GPRs, pc, hi, lo, 32 FPRs,
+/// page count, then every mapped page as its index followed by 4096 bytes.
+pub struct Snapshot {
+    pub endian: EndianMode,
+    pub regs: [u32; 32],
+    pub pc: u32,
+    pub hi: u32,
+    pub lo: u32,
+    pub fprs: [u32; 32],
+    pub pages: Vec<(u32, Vec<u8>)>,
+}
+
+impl Snapshot {
+    pub fn capture(arch: &Arch) -> Self {
+        let mem = arch.mem();
+        let mut regs = [0; 32];
+        regs.copy_from_slice(arch.regs());
+        let mut fprs = [0; 32];
+        fprs.copy_from_slice(arch.fprs());
+
+        let mut pages = Vec::new();
+        for page_idx in 0..(1 << 20) {
+            if mem.is_page_mapped(page_idx) {
+                let mut data = vec![0; PAGE_SIZE];
+                mem.read_into_slice(page_idx * PAGE_SIZE as u32, &mut data);
+                pages.push((page_idx, data));
+            }
+        }
+
+        Snapshot {
+            endian: mem.endian(),
+            regs,
+            pc: arch.pc(),
+            hi: arch.hi(),
+            lo: arch.lo(),
+            fprs,
+            pages,
+        }
+    }
+
+    /// Writes the registers into `arch`. Memory is restored separately through [`Self::segments`].
+    pub fn apply_regs(&self, arch: &mut Arch) {
+        arch.regs_mut().copy_from_slice(&self.regs);
+        arch.set_pc(self.pc);
+        arch.set_hi(self.hi);
+        arch.set_lo(self.lo);
+        arch.fprs_mut().copy_from_slice(&self.fprs);
+    }
+
+    /// The saved pages, with runs of adjacent pages merged into one segment each.
+    pub fn segments(&self) -> Vec<Segment> {
+        let mut segs: Vec<Segment> = Vec::new();
+
+        for (page_idx, data) in &self.pages {
+            let addr = page_idx * PAGE_SIZE as u32;
+            match segs.last_mut() {
+                Some(x) if x.base_addr as u64 + x.data.len() as u64 == addr as u64 => {
+                    x.append_bytes(data)
+                }
+                _ => {
+                    let mut seg = Segment::new(addr, self.endian);
+                    seg.append_bytes(data);
+                    segs.push(seg);
+                }
+            }
+        }
+
+        segs
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(288 + self.pages.len() * (PAGE_SIZE + 4));
+        out.extend_from_slice(MAGIC);
+
+        // writing into a Vec cannot fail
+        let mut write = |x: u32| out.write_u32::<LittleEndian>(x).unwrap();
+        write(VERSION);
+        write(match self.endian {
+            EndianMode::Little => 0,
+            EndianMode::Big => 1,
+        });
+        for x in self.regs {
+            write(x);
+        }
+        write(self.pc);
+        write(self.hi);
+        write(self.lo);
+        for x in self.fprs {
+            write(x);
+        }
+        write(self.pages.len() as u32);
+
+        for (page_idx, data) in &self.pages {
+            out.write_u32::<LittleEndian>(*page_idx).unwrap();
+            out.extend_from_slice(data);
+        }
+
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        let truncated = |_| "snapshot is truncated".to_string();
+        let mut rd = Cursor::new(bytes);
+
+        let mut magic = [0; 8];
+        rd.read_exact(&mut magic).map_err(truncated)?;
+        if &magic != MAGIC {
+            return Err("not a snapshot".into());
+        }
+
+        let version = rd.read_u32::<LittleEndian>().map_err(truncated)?;
+        if version != VERSION {
+            return Err(format!("unsupported snapshot version {}", version));
+        }
+
+        let endian = match rd.read_u32::<LittleEndian>().map_err(truncated)? {
+            0 => EndianMode::Little,
+            1 => EndianMode::Big,
+            x => return Err(format!("invalid endian {} in snapshot", x)),
+        };
+
+        let mut regs = [0; 32];
+        rd.read_u32_into::<LittleEndian>(&mut regs)
+            .map_err(truncated)?;
+        let pc = rd.read_u32::<LittleEndian>().map_err(truncated)?;
+        let hi = rd.read_u32::<LittleEndian>().map_err(truncated)?;
+        let lo = rd.read_u32::<LittleEndian>().map_err(truncated)?;
+        let mut fprs = [0; 32];
+        rd.read_u32_into::<LittleEndian>(&mut fprs)
+            .map_err(truncated)?;
+
+        let page_cnt = rd.read_u32::<LittleEndian>().map_err(truncated)?;
+        let mut pages = Vec::new();
+        for _ in 0..page_cnt {
+            let page_idx = rd.read_u32::<LittleEndian>().map_err(truncated)?;
+            if page_idx >= 1 << 20 {
+                return Err(format!("invalid page index {} in snapshot", page_idx));
+            }
+
+            let mut data = vec![0; PAGE_SIZE];
+            rd.read_exact(&mut data).map_err(truncated)?;
+            pages.push((page_idx, data));
+        }
+
+        if rd.position() != bytes.len() as u64 {
+            return Err("trailing bytes after snapshot".into());
+        }
+
+        Ok(Snapshot {
+            endian,
+            regs,
+            pc,
+            hi,
+            lo,
+            fprs,
+            pages,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rejects_bad_header() {
+        assert!(Snapshot::decode(b"MIPSSNAP").is_err());
+        assert!(Snapshot::decode(b"NOTASNAPSHOT").is_err());
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        let err = Snapshot::decode(&bytes).err().unwrap();
+        assert!(err.contains("version 3"));
+
+        // version 1 had no FPRs
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        let err = Snapshot::decode(&bytes).err().unwrap();
+        assert!(err.contains("version 1"));
+    }
+}
@@ -1,15 +1,54 @@
-use crate::assembler::assemble;
-use crate::component::RegisterName;
-use crate::disassembler::disassemble;
-use crate::executor::{Executor, Interpreter, Jit, Pipeline, HAS_JIT};
-use crate::memory::{create_empty_memory, create_memory, EndianMode};
+use crate::assembler::{
+    assemble_listing, assemble_units, assemble_with, segments_checksum, segments_to_binary,
+    to_intel_hex, AssemblerConfig, Assembly, ListingLine, Warning,
+};
+use crate::component::{Instruction, RegisterName};
+use crate::disassembler::{disassemble_with_symbols, DisassembleOptions};
+use crate::executor::{
+    ArithFlags, BranchReport, CacheConfig, CacheStats, ChangedReg, ExecuteError, Executor,
+    Interpreter, Jit, JitStats, PageAccesses, Pipeline, RegChange, RunResult, TraceEntry, HAS_JIT,
+    MAX_BLOCKS, MAX_BLOCK_SIZE,
+};
+use crate::memory::{
+    create_empty_memory, create_empty_memory_with, create_memory_with, hex_dump, EndianMode,
+    Memory, MemoryConfig, MemoryInit, Segment,
+};
+use crate::webapi::reg_format::RegisterFormat;
+use crate::webapi::snapshot::Snapshot;
 use crate::webapi::updates::Updates;
+use crate::webapi::util::{branch_stats_object, cache_stats_object};
 use neon::prelude::*;
 use parking_lot::Mutex;
 use rustc_hash::FxHashMap;
-use std::mem::swap;
-use std::ops::RangeInclusive;
+use std::collections::HashMap;
+use std::mem::{replace, swap};
+use std::ops::{Range, RangeInclusive};
 use std::sync::Arc;
+use std::time::Duration;
+
+/// Safety cap for `run_until`, so a program that never reaches the target doesn't hang the caller.
+const RUN_UNTIL_MAX_STEPS: u64 = 10_000_000;
+
+/// Most instructions the disassembly window reaches on either side of the pc.
+const MAX_DISASM_WINDOW: u32 = 2048;
+
+/// Entries the disassembly cache may hold before it starts over. A few windows' worth.
+const DISASM_CACHE_CAPACITY: usize = 4096;
+
+/// Index of the highest page, at 0xfffff000.
+const LAST_PAGE: u32 = 0xfffff;
+
+/// What the frontend was last sent, for diff updates.
+#[derive(Debug, Default)]
+struct LastSent {
+    // registers and pc, if sent since the last full update
+    regs: Option<([u32; 32], u32)>,
+    fp_regs: Option<[u32; 32]>,
+    // the word shown at each disassembled address
+    disasm: FxHashMap<u32, u32>,
+    // console characters ever written, as of the last update that sent them
+    console_written: Option<u64>,
+}
 
 #[derive(Debug)]
 pub struct State {
@@ -21,8 +60,71 @@ pub struct State {
 #[derive(Debug)]
 struct Inner {
     clean_after_reset: bool,
+    endian: EndianMode,
+    assembler_config: AssemblerConfig,
+    source: Option<String>,
+    // `(name, source)` of a program assembled from several files; empty otherwise
+    units: Vec<(String, String)>,
+    globals: HashMap<String, u32>,
+    labels: HashMap<String, u32>,
+    warnings: Vec<Warning>,
+    // labels by address, for naming jump targets in the disassembly
+    symbols: HashMap<u32, String>,
+    // execution starts here when the program exports it with `.globl`
+    entry_label: String,
+    delay_slots: bool,
+    trace: bool,
+    // count executed instructions by mnemonic
+    profiling: bool,
+    // shape of the simulated data cache, if any
+    cache: Option<CacheConfig>,
+    // score a branch predictor on conditional branches
+    branch_prediction: bool,
+    force_interpreter: bool,
+    // free runs stop after this many instructions
+    run_limit: Option<u64>,
+    // a free run notifies the frontend at most once per interval
+    update_interval: Duration,
+    // each new program's random syscalls start from this
+    random_seed: u64,
+    // what memory the program hasn't written holds, and where it ends
+    memory_config: MemoryConfig,
+    // registers are also sent as text in this format
+    register_format: RegisterFormat,
+    run_started_at: u64,
+    last_error: Option<String>,
+    // what kind of execution error the last step or run stopped on, see `ExecuteError::kind`
+    error_kind: Option<&'static str>,
+    // the last step stopped at a read syscall with no input queued
+    waiting_for_input: bool,
+    // the last timed run stopped for time, and can be resumed
+    timed_out: bool,
+    // a free run was paused, and resuming continues it with its run limit counted so far
+    paused: bool,
+    // what the last stepped or timed run stopped on other than its limit, and the exit
+    // code or address that goes with it
+    stopped: Option<(&'static str, u32)>,
+    breakpoints: Vec<u32>,
+    watchpoints: Vec<u32>,
     exec: Executor,
+    // the program as assembled or loaded, which export and the disassembly go by
+    segments: Vec<Segment>,
+    // memory brought back by `restore`, stack and heap included, with no program segments
+    restored: Vec<Segment>,
+    // what the assembler filled with data directives, shown as words rather than code
+    data_ranges: Vec<Range<u32>>,
+    data_as_words: bool,
     disassembly_range: Mutex<Option<RangeInclusive<u32>>>,
+    // text by address and the words before, at and after it, so a word changed by a store
+    // or edit misses on its own, along with its neighbours whose `la` halves may change.
+    // Cleared whenever anything else the text depends on changes
+    disasm_cache: Mutex<FxHashMap<(u32, [u32; 3]), String>>,
+    // send only what changed since the last notification
+    diff_updates: bool,
+    last_sent: Mutex<LastSent>,
+    disasm_options: DisassembleOptions,
+    // instructions shown before and after the pc
+    disasm_window: (u32, u32),
 }
 
 impl Default for Inner {
@@ -31,8 +133,49 @@ impl Default for Inner {
 
         Inner {
             clean_after_reset: true,
+            endian: EndianMode::native(),
+            assembler_config: Default::default(),
+            source: None,
+            units: Vec::new(),
+            globals: HashMap::new(),
+            labels: HashMap::new(),
+            warnings: Vec::new(),
+            symbols: HashMap::new(),
+            entry_label: "main".into(),
+            delay_slots: false,
+            trace: false,
+            profiling: false,
+            cache: None,
+            branch_prediction: false,
+            force_interpreter: false,
+            run_limit: None,
+            update_interval: Duration::from_millis(10),
+            random_seed: 0,
+            memory_config: MemoryConfig::default(),
+            register_format: RegisterFormat::Raw,
+            run_started_at: 0,
+            last_error: None,
+            error_kind: None,
+            waiting_for_input: false,
+            timed_out: false,
+            paused: false,
+            stopped: None,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
             exec: Executor::ExInterpreter(interpreter),
+            segments: Vec::new(),
+            restored: Vec::new(),
+            data_ranges: Vec::new(),
+            data_as_words: true,
             disassembly_range: Mutex::new(None),
+            disasm_cache: Mutex::new(FxHashMap::default()),
+            diff_updates: false,
+            last_sent: Mutex::new(LastSent::default()),
+            disasm_options: DisassembleOptions {
+                pseudo: true,
+                ..Default::default()
+            },
+            disasm_window: (64, 256),
         }
     }
 }
@@ -49,21 +192,171 @@ impl State {
     }
 
     pub fn reset(&mut self) -> Updates {
-        self.inner = Default::default();
+        self.inner.reset();
         Updates::all()
     }
 
-    pub fn assemble(&mut self, code: &str, endian: EndianMode) -> Result<Updates, String> {
-        let segs = assemble(endian, code).map_err(|e| e.to_string())?;
-        let mem = create_memory(endian, &segs);
+    pub fn set_endian(&mut self, endian: EndianMode) -> Result<Updates, String> {
+        self.inner.set_endian(endian)
+    }
 
-        if HAS_JIT && endian == EndianMode::native() {
-            self.inner.exec = Executor::ExJit(Jit::new(mem));
-        } else {
-            self.inner.exec = Executor::ExInterpreter(Interpreter::new(mem));
+    pub fn set_abi_register_names(&mut self, enabled: bool) -> Updates {
+        self.inner.set_abi_names(enabled);
+        Updates::DISASSEMBLY
+    }
+
+    /// Takes effect from the next assemble.
+    pub fn set_strict_segments(&mut self, enabled: bool) {
+        self.inner.assembler_config.strict_segments = enabled;
+    }
+
+    /// Takes effect from the next assemble.
+    pub fn set_lint(&mut self, enabled: bool) {
+        self.inner.assembler_config.lint = enabled;
+    }
+
+    /// Takes effect from the next assemble.
+    pub fn set_fill_delay_slots(&mut self, enabled: bool) {
+        self.inner.assembler_config.fill_delay_slots = enabled;
+    }
+
+    /// The global label execution starts at, `main` by default. Programs that don't export
+    /// it start at the text base. Takes effect from the next assemble.
+    pub fn set_entry_label(&mut self, label: &str) {
+        // the assembler lowercases labels
+        self.inner.entry_label = label.to_ascii_lowercase();
+    }
+
+    pub fn set_force_interpreter(&mut self, enabled: bool) -> Updates {
+        self.inner.set_force_interpreter(enabled)
+    }
+
+    pub fn enable_trace(&mut self, enabled: bool) -> Updates {
+        self.inner.set_trace(enabled);
+        Updates::REGISTERS
+    }
+
+    /// Counts executed instructions by mnemonic, sent as `profile`, and loads and stores by
+    /// page. Compiled code isn't counted, so this runs everything in the interpreter.
+    pub fn set_profiling(&mut self, enabled: bool) -> Updates {
+        self.inner.set_profiling(enabled);
+        Updates::REGISTERS
+    }
+
+    /// Executions by mnemonic since profiling started or was last reset. Empty when
+    /// profiling is off or the pipeline runs the program.
+    pub fn profile(&self) -> HashMap<String, u64> {
+        self.inner.capture_profile()
+    }
+
+    /// Simulates a data cache of this shape in front of loads and stores, sending its hits
+    /// and misses as `cache`, or stops with `None`. Statistics start over each time. Runs
+    /// everything in the interpreter, like profiling.
+    pub fn set_cache(&mut self, config: Option<CacheConfig>) -> Result<Updates, String> {
+        if let Some(x) = config.filter(|x| !x.is_valid()) {
+            return Err(format!(
+                "block size {} and set count {} must be powers of two, with at least one way, \
+                 blocks of at most {} bytes and at most {} blocks in all",
+                x.block_size, x.sets, MAX_BLOCK_SIZE, MAX_BLOCKS
+            ));
         }
 
-        Ok(Updates::all())
+        self.inner.set_cache(config);
+        Ok(Updates::REGISTERS)
+    }
+
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.inner.capture_cache_stats()
+    }
+
+    /// Scores a 2-bit saturating counter per conditional branch, sending the totals as
+    /// `branches`. Turning it on again starts over. Runs everything in the interpreter.
+    pub fn set_branch_prediction(&mut self, enabled: bool) -> Updates {
+        self.inner.set_branch_prediction(enabled);
+        Updates::REGISTERS
+    }
+
+    pub fn branch_stats(&self) -> Option<BranchReport> {
+        self.inner.capture_branch_stats()
+    }
+
+    /// Loads and stores by page number while profiling, in page order.
+    pub fn memory_heatmap(&self) -> Vec<(u32, PageAccesses)> {
+        let heatmap = self.inner.exec.interpreter().map(|x| x.memory_heatmap());
+        heatmap.unwrap_or_default()
+    }
+
+    pub fn reset_profile(&mut self) -> Updates {
+        if let Some(x) = self.inner.exec.interpreter_mut() {
+            x.reset_profile();
+        }
+        Updates::REGISTERS
+    }
+
+    /// Whether words the assembler emitted as data show as `.word` in the disassembly,
+    /// rather than decoded as instructions.
+    pub fn set_data_as_words(&mut self, enabled: bool) -> Updates {
+        self.inner.set_data_as_words(enabled);
+        Updates::DISASSEMBLY
+    }
+
+    /// Whether notifications carry only the registers and disassembly that changed since
+    /// the previous one, as `dirtyRegs` rather than `regs`. The first notification after
+    /// turning it on, loading a program or resetting is still complete.
+    pub fn set_diff_updates(&mut self, enabled: bool) -> Updates {
+        self.inner.diff_updates = enabled;
+        *self.inner.last_sent.lock() = LastSent::default();
+        Updates::all()
+    }
+
+    /// Where [`State::run_steps`] stops before running the instruction. Kept across resets.
+    pub fn set_breakpoints(&mut self, addrs: Vec<u32>) {
+        self.inner.breakpoints = addrs;
+    }
+
+    /// Addresses whose word [`State::run_steps`] stops after an instruction changes. Kept
+    /// across resets.
+    pub fn set_watchpoints(&mut self, addrs: Vec<u32>) {
+        self.inner.watchpoints = addrs;
+    }
+
+    /// How many instructions to disassemble before and after the pc, each at most
+    /// `MAX_DISASM_WINDOW`.
+    pub fn set_disasm_window(&mut self, before: u32, after: u32) -> Updates {
+        self.inner.set_disasm_window(before, after);
+        Updates::DISASSEMBLY
+    }
+
+    pub fn set_delay_slots(&mut self, enabled: bool) -> Updates {
+        self.inner.set_delay_slots(enabled);
+        Updates::REGISTERS
+    }
+
+    pub fn assemble(&mut self, code: &str, endian: EndianMode) -> Result<Updates, String> {
+        self.inner.assemble(code, endian)
+    }
+
+    /// Assembles and links several files, `(name, source)`. There is no listing for them.
+    pub fn assemble_units(
+        &mut self,
+        units: &[(String, String)],
+        endian: EndianMode,
+    ) -> Result<Updates, String> {
+        self.inner.assemble_units(units, endian)
+    }
+
+    pub fn load_binary(&mut self, base: u32, bytes: &[u8]) -> Updates {
+        self.inner.load_binary(base, bytes)
+    }
+
+    pub fn snapshot(&self) -> Vec<u8> {
+        Snapshot::capture(self.inner.exec.as_arch()).encode()
+    }
+
+    pub fn restore(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let updates = self.inner.restore(bytes)?;
+        self.notify(updates);
+        Ok(())
     }
 
     pub fn edit_register(&mut self, r: RegisterName, val: u32) -> Updates {
@@ -72,38 +365,242 @@ impl State {
         Updates::REGISTERS
     }
 
+    pub fn edit_hi(&mut self, val: u32) -> Updates {
+        self.inner.clean_after_reset = false;
+        self.inner.exec.as_arch_mut().set_hi(val);
+        Updates::REGISTERS
+    }
+
+    pub fn edit_lo(&mut self, val: u32) -> Updates {
+        self.inner.clean_after_reset = false;
+        self.inner.exec.as_arch_mut().set_lo(val);
+        Updates::REGISTERS
+    }
+
+    pub fn edit_pc(&mut self, val: u32) -> Result<Updates, String> {
+        self.inner.edit_pc(val)
+    }
+
     pub fn read_memory(&self, page_idx: u32, output: &mut [u8]) {
-        let addr = page_idx * 4096;
-        let mem = self.inner.exec.as_arch().mem();
-        mem.read_into_slice(addr, output);
+        self.inner.read_memory(page_idx, output);
+    }
+
+    pub fn read_range(&self, addr: u32, output: &mut [u8]) {
+        self.inner.read_range(addr, output);
+    }
+
+    /// Reads `count` pages from `start_page` on into `output`, which must be exactly that
+    /// long. Unmapped pages read as zeros, or as the poison pattern under
+    /// `MemoryInit::Poison`. The last page of the address space, which no program can
+    /// reach, always reads as zeros.
+    pub fn read_pages(&self, start_page: u32, count: u32, output: &mut [u8]) {
+        self.inner.read_pages(start_page, count, output);
+    }
+
+    pub fn read_memory_words(&self, page_idx: u32, output: &mut [u32]) {
+        self.inner.read_memory_words(page_idx, output);
+    }
+
+    pub fn write_memory(&mut self, addr: u32, bytes: &[u8]) -> Updates {
+        self.inner.write_memory(addr, bytes)
+    }
+
+    /// `xxd`-style hex and ASCII view of `len` bytes at `addr`.
+    pub fn dump_memory(&self, addr: u32, len: u32) -> String {
+        hex_dump(self.inner.exec.as_arch().mem(), addr, len)
+    }
+
+    /// CRC-32 of the image [`State::export_binary`] lays out, even one too sparse to export.
+    pub fn image_checksum(&self) -> u32 {
+        segments_checksum(&self.inner.segments)
+    }
+
+    /// The base address and CRC-32 of each non-empty segment, in assembly order.
+    pub fn segment_checksums(&self) -> Vec<(u32, u32)> {
+        self.inner.segment_checksums()
+    }
+
+    /// The assembled text and data as one image, starting at the lowest segment address.
+    /// Fails if the segments are too far apart to fill the gaps with zeros.
+    pub fn export_binary(&self) -> Result<Vec<u8>, String> {
+        segments_to_binary(&self.inner.segments).map_err(|e| e.to_string())
+    }
+
+    /// Address, bytes and source of every line of the last assembled program.
+    /// Fails if the memory was not loaded from source.
+    pub fn listing(&self) -> Result<Vec<ListingLine>, String> {
+        let source = self.inner.source.as_ref().ok_or("no source to list")?;
+        assemble_listing(self.inner.endian, source, &self.inner.assembler_config)
+            .map_err(|e| e.to_string())
+    }
+
+    /// Labels exported with `.globl` by the last assembled program.
+    pub fn globals(&self) -> &HashMap<String, u32> {
+        &self.inner.globals
+    }
+
+    pub fn disassemble_image(
+        &self,
+        base: u32,
+        bytes: &[u8],
+        endian: EndianMode,
+    ) -> Vec<(u32, String)> {
+        self.inner.disassemble_image(base, bytes, endian)
+    }
+
+    pub fn label_address(&self, name: &str) -> Option<u32> {
+        self.inner.label_address(name)
+    }
+
+    /// Redundant code found in the last assembled program, if linting was on.
+    pub fn warnings(&self) -> &[Warning] {
+        &self.inner.warnings
+    }
+
+    pub fn export_intel_hex(&self) -> String {
+        to_intel_hex(&self.inner.segments)
     }
 
     pub fn step(&mut self) -> Result<Updates, String> {
-        self.inner.clean_after_reset = false;
-        if self.inner.exec.as_arch().pc() < 0x00001000 {
-            Ok(Updates::empty())
-        } else {
-            self.inner.exec.step().map_err(|x| format!("{:?}", x))?;
-            Ok(Updates::REGISTERS)
-        }
+        self.inner.step()
     }
 
-    pub fn exec(&mut self) -> Result<Updates, String> {
-        self.inner.clean_after_reset = false;
-        if self.inner.exec.as_arch().pc() < 0x00001000 {
-            Ok(Updates::empty())
+    /// Like `step`, but runs a call through to its return. Bounded by the run limit.
+    pub fn step_over(&mut self) -> Result<Updates, String> {
+        let max_steps = self.inner.run_limit.unwrap_or(RUN_UNTIL_MAX_STEPS);
+        self.inner.step_over(max_steps)
+    }
+
+    /// Runs for at most `ms` milliseconds, then notifies once. Sets `timedOut` if the
+    /// program is still going, so the caller can yield to its event loop and call again.
+    pub fn exec_with_timeout(&mut self, ms: u64) -> Result<(), String> {
+        let result = self.inner.exec_with_timeout(Duration::from_millis(ms));
+        self.notify(Updates::REGISTERS);
+        result.map(|_| ())
+    }
+
+    /// Steps until the PC reaches `addr`, then notifies once.
+    pub fn run_until(&mut self, addr: u32) -> Result<(), String> {
+        let result = self.inner.run_until(addr, RUN_UNTIL_MAX_STEPS);
+        self.notify(Updates::REGISTERS);
+        result.map(|_| ())
+    }
+
+    /// Steps exactly `n` times (or until the program ends), then notifies once.
+    pub fn run_steps(&mut self, n: u64) -> Result<(), String> {
+        let result = self.inner.run_steps(n);
+        self.notify(Updates::REGISTERS);
+        result.map(|_| ())
+    }
+
+    /// Assembles `code` and steps it up to `max_steps` times, then notifies once.
+    /// Nothing is notified if assembly fails.
+    pub fn assemble_and_run(
+        &mut self,
+        code: &str,
+        endian: EndianMode,
+        max_steps: u64,
+    ) -> Result<(), String> {
+        let (updates, result) = self.inner.assemble_and_run(code, endian, max_steps)?;
+        self.notify(updates);
+        result
+    }
+
+    pub fn run(&mut self, allow_jit: bool) -> Updates {
+        self.inner.start_run();
+        super::looper::start(allow_jit);
+        Updates::FLAG_RUNNING
+    }
+
+    /// Stops a free run so that [`State::resume`] can continue it. Does nothing when not
+    /// running.
+    pub fn pause(&mut self) -> Updates {
+        if super::looper::stop() {
+            self.inner.pause();
+            Updates::all()
         } else {
-            self.inner.exec.exec().map_err(|x| format!("{:?}", x))?;
-            Ok(Updates::REGISTERS)
+            Updates::FLAG_RUNNING
         }
     }
 
-    pub fn run(&self, allow_jit: bool) -> Updates {
+    /// Continues a paused run from the current pc, without restarting its run limit. Does
+    /// nothing unless paused.
+    pub fn resume(&mut self, allow_jit: bool) -> Updates {
+        if !self.inner.resume() {
+            return Updates::empty();
+        }
+
         super::looper::start(allow_jit);
         Updates::FLAG_RUNNING
     }
 
-    pub fn stop(&self) -> Updates {
+    /// Limits how many instructions one free run may execute. `None` runs until stopped.
+    pub fn set_run_limit(&mut self, limit: Option<u64>) {
+        self.inner.run_limit = limit;
+    }
+
+    /// How often a free run sends the frontend its progress. It always sends a last update
+    /// when it stops.
+    pub fn set_update_interval(&mut self, interval: Duration) {
+        self.inner.update_interval = interval;
+    }
+
+    pub fn update_interval(&self) -> Duration {
+        self.inner.update_interval
+    }
+
+    pub fn set_register_format(&mut self, format: RegisterFormat) -> Updates {
+        self.inner.register_format = format;
+        Updates::REGISTERS
+    }
+
+    /// Takes effect from the next assemble.
+    pub fn set_memory_init(&mut self, init: MemoryInit) {
+        self.inner.memory_config.init = init;
+    }
+
+    /// Loads and stores at `limit` or above become address errors, and the JIT is not used.
+    /// Takes effect from the next assemble.
+    pub fn set_memory_limit(&mut self, limit: Option<u32>) {
+        self.inner.memory_config.limit = limit;
+    }
+
+    /// Restarts the random syscalls from `seed`, now and after each assemble.
+    pub fn set_random_seed(&mut self, seed: u64) {
+        self.inner.random_seed = seed;
+        self.inner.exec.as_arch_mut().set_random_seed(seed);
+    }
+
+    /// One iteration of a free run. Fails when the program faults or the run limit is hit.
+    pub fn run_tick(&mut self, allow_jit: bool) -> Result<Updates, String> {
+        self.inner.run_tick(allow_jit)
+    }
+
+    /// Queues input for the program's read syscalls. A read that was waiting runs on the
+    /// next step or run.
+    pub fn provide_input(&mut self, text: &str) -> Updates {
+        self.inner.exec.as_arch_mut().provide_input(text);
+        self.inner.waiting_for_input = false;
+        Updates::empty()
+    }
+
+    pub fn push_key(&mut self, key: u8) -> Updates {
+        self.inner.exec.as_arch_mut().push_key(key);
+        Updates::empty()
+    }
+
+    /// Whether the program is paused at a read syscall until input is provided.
+    pub fn waiting_for_input(&self) -> bool {
+        self.inner.waiting_for_input
+    }
+
+    /// Records why a free run stopped, so the next notification reports it.
+    pub fn set_last_error(&mut self, err: String) {
+        self.inner.last_error = Some(err);
+    }
+
+    pub fn stop(&mut self) -> Updates {
+        self.inner.paused = false;
         if super::looper::stop() {
             Updates::all()
         } else {
@@ -116,12 +613,7 @@ impl State {
             return Updates::empty();
         }
 
-        let endian = self.inner.exec.as_arch().mem().endian();
-        let mut pipeline = Executor::ExPipeline(Pipeline::new(create_empty_memory(endian)));
-        swap(&mut pipeline, &mut self.inner.exec);
-
-        *self.inner.exec.as_arch_mut() = pipeline.into_arch();
-
+        self.inner.convert_to_pipeline();
         Updates::all()
     }
 
@@ -134,10 +626,19 @@ impl State {
 
         // cheap-to-collect ones
         let clean_after_reset = self.inner.clean_after_reset;
+        let endian = self.inner.capture_endian();
         let running = self.inner.capture_running();
         let can_use_jit = self.inner.capture_can_use_jit();
         let can_use_pipeline = self.inner.capture_can_use_pipeline();
+        let force_interpreter = self.inner.capture_force_interpreter();
+        let waiting_for_input = self.inner.waiting_for_input;
+        let timed_out = self.inner.timed_out;
+        let paused = self.inner.paused;
+        let stopped = self.inner.stopped;
         let pc = self.inner.capture_pc();
+        let (hi, lo) = self.inner.capture_hi_lo();
+        let last_error = self.inner.last_error.clone();
+        let error_kind = self.inner.error_kind;
 
         // expensive-to-collect ones
         let regs = if updates.contains(Updates::REGISTERS) {
@@ -146,6 +647,91 @@ impl State {
             [0; 32]
         };
 
+        let fp_regs = if updates.contains(Updates::REGISTERS) {
+            self.inner.capture_fp_regs()
+        } else {
+            [0; 32]
+        };
+
+        let dirty_regs = if updates.contains(Updates::REGISTERS) {
+            self.inner.diff_regs(&regs, pc)
+        } else {
+            None
+        };
+
+        let dirty_fp_regs = if updates.contains(Updates::REGISTERS) {
+            self.inner.diff_fp_regs(&fp_regs)
+        } else {
+            None
+        };
+
+        let changes = if updates.contains(Updates::REGISTERS) {
+            self.inner.capture_changes()
+        } else {
+            Vec::new()
+        };
+
+        let flags = if updates.contains(Updates::REGISTERS) {
+            self.inner.capture_flags()
+        } else {
+            None
+        };
+
+        let in_delay_slot = updates.contains(Updates::REGISTERS) && self.inner.in_delay_slot();
+
+        let call_stack = if updates.contains(Updates::REGISTERS) {
+            self.inner.capture_call_stack()
+        } else {
+            Vec::new()
+        };
+
+        let console_appended = if updates.contains(Updates::REGISTERS) {
+            self.inner.diff_console()
+        } else {
+            None
+        };
+        let console = if updates.contains(Updates::REGISTERS) && console_appended.is_none() {
+            self.inner.capture_console()
+        } else {
+            String::new()
+        };
+
+        let formatted = if updates.contains(Updates::REGISTERS) {
+            self.inner.capture_formatted_regs(&regs, hi, lo)
+        } else {
+            None
+        };
+
+        let jit_stats = if updates.contains(Updates::REGISTERS) {
+            self.inner.capture_jit_stats()
+        } else {
+            None
+        };
+
+        let trace = if updates.contains(Updates::REGISTERS) && self.inner.trace {
+            Some(self.inner.capture_trace())
+        } else {
+            None
+        };
+
+        let cache_stats = if updates.contains(Updates::REGISTERS) {
+            self.inner.capture_cache_stats()
+        } else {
+            None
+        };
+
+        let branch_stats = if updates.contains(Updates::REGISTERS) {
+            self.inner.capture_branch_stats().map(|x| x.total)
+        } else {
+            None
+        };
+
+        let profile = if updates.contains(Updates::REGISTERS) && self.inner.profiling {
+            Some(self.inner.capture_profile())
+        } else {
+            None
+        };
+
         let pipeline_detail = if updates.contains(Updates::REGISTERS) {
             Some(self.inner.capture_pipeline_detail())
         } else {
@@ -153,7 +739,9 @@ impl State {
         };
 
         let disasm_mapping = if updates.contains(Updates::DISASSEMBLY) {
-            self.inner.capture_disasm()
+            let mut mapping = self.inner.capture_disasm();
+            self.inner.diff_disasm(&mut mapping);
+            mapping
         } else {
             FxHashMap::default()
         };
@@ -163,10 +751,180 @@ impl State {
             let obj = cx.empty_object();
 
             if updates.contains(Updates::REGISTERS) {
-                let regs = js_array_numbers(&mut cx, regs.iter())?;
-                let pc = cx.number(pc);
-                obj.set(&mut cx, "regs", regs)?;
-                obj.set(&mut cx, "pc", pc)?;
+                match dirty_regs {
+                    Some((dirty, pc_changed)) => {
+                        let arr = cx.empty_array();
+                        for (i, (reg, val)) in dirty.iter().enumerate() {
+                            let tuple = cx.empty_array();
+                            let reg = cx.number(*reg);
+                            let val = cx.number(*val);
+                            tuple.set(&mut cx, 0, reg)?;
+                            tuple.set(&mut cx, 1, val)?;
+                            arr.set(&mut cx, i as u32, tuple)?;
+                        }
+                        obj.set(&mut cx, "dirtyRegs", arr)?;
+                        if pc_changed {
+                            let pc = cx.number(pc);
+                            obj.set(&mut cx, "pc", pc)?;
+                        }
+                    }
+                    None => {
+                        let regs = js_array_numbers(&mut cx, regs.iter())?;
+                        let pc = cx.number(pc);
+                        obj.set(&mut cx, "regs", regs)?;
+                        obj.set(&mut cx, "pc", pc)?;
+                    }
+                }
+
+                match dirty_fp_regs {
+                    Some(dirty) => {
+                        let arr = cx.empty_array();
+                        for (i, (reg, val)) in dirty.iter().enumerate() {
+                            let tuple = cx.empty_array();
+                            let reg = cx.number(*reg);
+                            let val = cx.number(*val);
+                            tuple.set(&mut cx, 0, reg)?;
+                            tuple.set(&mut cx, 1, val)?;
+                            arr.set(&mut cx, i as u32, tuple)?;
+                        }
+                        obj.set(&mut cx, "dirtyFpRegs", arr)?;
+                    }
+                    None => {
+                        let fp_regs = js_array_numbers(&mut cx, fp_regs.iter())?;
+                        obj.set(&mut cx, "fpRegs", fp_regs)?;
+                    }
+                }
+
+                let hi = cx.number(hi);
+                let lo = cx.number(lo);
+                obj.set(&mut cx, "hi", hi)?;
+                obj.set(&mut cx, "lo", lo)?;
+
+                let changed = cx.empty_array();
+                for (i, change) in changes.iter().enumerate() {
+                    let tuple = cx.empty_array();
+                    let reg = match change.reg {
+                        ChangedReg::Gpr(x) => cx.number(x.num()).upcast::<JsValue>(),
+                        ChangedReg::Fpr(x) => cx.string(format!("f{}", x.num())).upcast(),
+                        ChangedReg::Hi => cx.string("hi").upcast(),
+                        ChangedReg::Lo => cx.string("lo").upcast(),
+                        ChangedReg::Pc => cx.string("pc").upcast(),
+                    };
+                    let old = cx.number(change.old);
+                    let new = cx.number(change.new);
+                    tuple.set(&mut cx, 0, reg)?;
+                    tuple.set(&mut cx, 1, old)?;
+                    tuple.set(&mut cx, 2, new)?;
+                    changed.set(&mut cx, i as u32, tuple)?;
+                }
+                obj.set(&mut cx, "changed", changed)?;
+
+                let flags = match flags {
+                    Some(x) => {
+                        let obj = cx.empty_object();
+                        for (name, val) in [
+                            ("carry", x.carry),
+                            ("overflow", x.overflow),
+                            ("zero", x.zero),
+                            ("negative", x.negative),
+                        ] {
+                            let val = cx.boolean(val);
+                            obj.set(&mut cx, name, val)?;
+                        }
+                        obj.upcast::<JsValue>()
+                    }
+                    None => cx.null().upcast(),
+                };
+                obj.set(&mut cx, "flags", flags)?;
+                let in_delay_slot = cx.boolean(in_delay_slot);
+                obj.set(&mut cx, "inDelaySlot", in_delay_slot)?;
+
+                let frames = cx.empty_array();
+                for (i, (addr, label)) in call_stack.iter().enumerate() {
+                    let frame = cx.empty_object();
+                    let addr = cx.number(*addr);
+                    let label = match label {
+                        Some(x) => cx.string(x).upcast::<JsValue>(),
+                        None => cx.null().upcast(),
+                    };
+                    frame.set(&mut cx, "addr", addr)?;
+                    frame.set(&mut cx, "label", label)?;
+                    frames.set(&mut cx, i as u32, frame)?;
+                }
+                obj.set(&mut cx, "callStack", frames)?;
+
+                if let Some((regs, hi, lo)) = formatted {
+                    let arr = cx.empty_array();
+                    for (i, x) in regs.iter().enumerate() {
+                        let str = cx.string(x);
+                        arr.set(&mut cx, i as u32, str)?;
+                    }
+                    let hi = cx.string(hi);
+                    let lo = cx.string(lo);
+                    obj.set(&mut cx, "regsFormatted", arr)?;
+                    obj.set(&mut cx, "hiFormatted", hi)?;
+                    obj.set(&mut cx, "loFormatted", lo)?;
+                }
+
+                match console_appended {
+                    Some(x) => {
+                        let appended = cx.string(x);
+                        obj.set(&mut cx, "consoleAppend", appended)?;
+                    }
+                    None => {
+                        let console = cx.string(console);
+                        obj.set(&mut cx, "console", console)?;
+                    }
+                }
+
+                if let Some(x) = jit_stats {
+                    let stats = cx.empty_object();
+                    let compiled = cx.number(x.compiled as f64);
+                    let interpreted = cx.number(x.interpreted as f64);
+                    stats.set(&mut cx, "compiled", compiled)?;
+                    stats.set(&mut cx, "interpreted", interpreted)?;
+                    obj.set(&mut cx, "jitStats", stats)?;
+                }
+
+                if let Some(entries) = trace {
+                    let arr = cx.empty_array();
+                    for (i, entry) in entries.iter().enumerate() {
+                        let tuple = cx.empty_array();
+                        let pc = cx.number(entry.pc);
+                        let ins = cx.number(entry.ins);
+                        let disasm = cx.string(&entry.disasm);
+                        tuple.set(&mut cx, 0, pc)?;
+                        tuple.set(&mut cx, 1, ins)?;
+                        tuple.set(&mut cx, 2, disasm)?;
+                        if let Some((reg, val)) = entry.changed_reg {
+                            let reg = cx.number(reg.num());
+                            let val = cx.number(val);
+                            tuple.set(&mut cx, 3, reg)?;
+                            tuple.set(&mut cx, 4, val)?;
+                        }
+                        arr.set(&mut cx, i as u32, tuple)?;
+                    }
+                    obj.set(&mut cx, "trace", arr)?;
+                }
+
+                if let Some(x) = cache_stats {
+                    let stats = cache_stats_object(&mut cx, x)?;
+                    obj.set(&mut cx, "cache", stats)?;
+                }
+
+                if let Some(x) = branch_stats {
+                    let stats = branch_stats_object(&mut cx, x)?;
+                    obj.set(&mut cx, "branches", stats)?;
+                }
+
+                if let Some(x) = profile {
+                    let profile = cx.empty_object();
+                    for (mnemonic, count) in x {
+                        let count = cx.number(count as f64);
+                        profile.set(&mut cx, mnemonic.as_str(), count)?;
+                    }
+                    obj.set(&mut cx, "profile", profile)?;
+                }
 
                 if let Some(x) = pipeline_detail {
                     let str = cx.string(x);
@@ -207,8 +965,42 @@ impl State {
             {
                 let clean_after_reset = cx.boolean(clean_after_reset);
                 let can_use_pipeline = cx.boolean(can_use_pipeline);
+                let endian = cx.string(endian);
                 obj.set(&mut cx, "cleanAfterReset", clean_after_reset)?;
                 obj.set(&mut cx, "canUsePipeline", can_use_pipeline)?;
+                obj.set(&mut cx, "endian", endian)?;
+                let force_interpreter = cx.boolean(force_interpreter);
+                obj.set(&mut cx, "forceInterpreter", force_interpreter)?;
+                let waiting_for_input = cx.boolean(waiting_for_input);
+                obj.set(&mut cx, "waitingForInput", waiting_for_input)?;
+                let timed_out = cx.boolean(timed_out);
+                obj.set(&mut cx, "timedOut", timed_out)?;
+                let paused = cx.boolean(paused);
+                obj.set(&mut cx, "paused", paused)?;
+
+                let last_error = match last_error {
+                    Some(x) => cx.string(x).upcast::<JsValue>(),
+                    None => cx.null().upcast(),
+                };
+                obj.set(&mut cx, "lastError", last_error)?;
+                let error_kind = match error_kind {
+                    Some(x) => cx.string(x).upcast::<JsValue>(),
+                    None => cx.null().upcast(),
+                };
+                obj.set(&mut cx, "errorKind", error_kind)?;
+
+                let stopped = match stopped {
+                    Some((reason, val)) => {
+                        let obj = cx.empty_object();
+                        let reason = cx.string(reason);
+                        let val = cx.number(val);
+                        obj.set(&mut cx, "reason", reason)?;
+                        obj.set(&mut cx, "value", val)?;
+                        obj.upcast::<JsValue>()
+                    }
+                    None => cx.null().upcast(),
+                };
+                obj.set(&mut cx, "stopped", stopped)?;
             }
 
             callback
@@ -221,76 +1013,833 @@ impl State {
 }
 
 impl Inner {
-    fn needs_capture_disasm(&self) -> bool {
-        let range = self.disassembly_range.lock();
+    /// Starts over with no program, keeping the settings, on an empty memory that follows
+    /// them.
+    fn reset(&mut self) {
+        *self = Inner {
+            endian: self.endian,
+            assembler_config: self.assembler_config.clone(),
+            entry_label: self.entry_label.clone(),
+            delay_slots: self.delay_slots,
+            trace: self.trace,
+            profiling: self.profiling,
+            cache: self.cache,
+            branch_prediction: self.branch_prediction,
+            force_interpreter: self.force_interpreter,
+            run_limit: self.run_limit,
+            update_interval: self.update_interval,
+            random_seed: self.random_seed,
+            memory_config: self.memory_config,
+            register_format: self.register_format,
+            disasm_options: self.disasm_options,
+            disasm_window: self.disasm_window,
+            data_as_words: self.data_as_words,
+            diff_updates: self.diff_updates,
+            breakpoints: self.breakpoints.clone(),
+            watchpoints: self.watchpoints.clone(),
+            ..Default::default()
+        };
+        self.build_executor(create_empty_memory_with(self.endian, self.memory_config));
+    }
 
-        match range.as_ref() {
-            Some(x) => !x.contains(&self.exec.as_arch().pc()),
-            None => true,
-        }
+    fn segment_checksums(&self) -> Vec<(u32, u32)> {
+        self.segments
+            .iter()
+            .filter(|x| !x.data.is_empty())
+            .map(|x| (x.base_addr, x.checksum()))
+            .collect()
     }
 
-    fn capture_regs(&self) -> [u32; 32] {
-        let mut ret = [0; 32];
-        self.exec.as_arch().read_all_reg(&mut ret);
-        ret
+    /// Lists a raw image as if it were loaded at `base`, without loading it. Uses the
+    /// current disassembly options and the labels of the running program. A trailing
+    /// partial word is left out.
+    fn disassemble_image(&self, base: u32, bytes: &[u8], endian: EndianMode) -> Vec<(u32, String)> {
+        let words: Vec<u32> = bytes.chunks_exact(4).map(|x| endian.read_u32(x)).collect();
+        words
+            .iter()
+            .enumerate()
+            .map(|(i, &ins)| {
+                let addr = base.wrapping_add(i as u32 * 4);
+                let around = (
+                    i.checked_sub(1).map(|x| words[x]),
+                    words.get(i + 1).copied(),
+                );
+                let text = disassemble_with_symbols(
+                    ins,
+                    Some(addr),
+                    &self.disasm_options,
+                    &self.symbols,
+                    around,
+                );
+                (addr, text)
+            })
+            .collect()
     }
 
-    fn capture_pc(&self) -> u32 {
-        self.exec.as_arch().pc()
+    /// Where a label of the last assembled program ended up, exported or not.
+    fn label_address(&self, name: &str) -> Option<u32> {
+        // the assembler lowercases labels
+        self.labels.get(&name.to_ascii_lowercase()).copied()
     }
 
-    fn capture_disasm(&self) -> FxHashMap<u32, (u32, String)> {
-        let mut range = self.disassembly_range.lock();
-        let pc = self.exec.as_arch().pc();
-        let mem = self.exec.as_arch().mem();
-        let mut mapping = FxHashMap::default();
-        let mut min_addr = pc;
-        let mut max_addr = pc;
-
-        /* walk back */
-        {
-            let mut addr = pc - 4;
-            let mut nop_cnt: u32 = 0;
-            while addr > 4096 && nop_cnt < 16 {
-                let x = mem.read_u32(addr);
-
-                if x == 0x00000000 || x == 0x00000020 {
-                    nop_cnt += 1;
-                } else {
-                    nop_cnt = 0;
-                }
+    fn assemble(&mut self, code: &str, endian: EndianMode) -> Result<Updates, String> {
+        let asm = assemble_with(endian, code, &self.assembler_config).map_err(|e| e.to_string())?;
+        self.source = Some(code.into());
+        self.units.clear();
+        Ok(self.load_assembly(asm, endian))
+    }
+
+    fn assemble_units(
+        &mut self,
+        units: &[(String, String)],
+        endian: EndianMode,
+    ) -> Result<Updates, String> {
+        let borrowed: Vec<_> = units
+            .iter()
+            .map(|(x, y)| (x.as_str(), y.as_str()))
+            .collect();
+        let asm =
+            assemble_units(endian, &borrowed, &self.assembler_config).map_err(|e| e.to_string())?;
+        self.source = None;
+        self.units = units.to_vec();
+        Ok(self.load_assembly(asm, endian))
+    }
+
+    /// Starts running a freshly assembled program.
+    fn load_assembly(&mut self, asm: Assembly, endian: EndianMode) -> Updates {
+        let mem = create_memory_with(endian, &asm.segments, self.memory_config);
 
-                mapping.insert(addr, (x, disassemble(x)));
-                min_addr = addr;
-                addr -= 4;
+        self.endian = endian;
+        self.globals = asm.globals;
+        self.labels = asm.labels;
+        self.warnings = asm.warnings;
+        self.symbols = HashMap::new();
+        for (name, addr) in &self.labels {
+            // of several labels at one address, show the first in name order
+            let entry = self.symbols.entry(*addr).or_insert_with(|| name.clone());
+            if name < entry {
+                *entry = name.clone();
             }
         }
+        self.segments = asm.segments;
+        self.restored.clear();
+        self.data_ranges = asm.data;
+        self.build_executor(mem);
 
-        /* walk forward */
-        {
-            let mut addr = pc & (!0xfff);
-            let mut nop_cnt: u32 = 0;
-            while addr < 0x1000_0000 && nop_cnt < 256 {
-                let x = mem.read_u32(addr);
+        // `$gp`-relative code from the assembler expects this
+        let gp = self.assembler_config.gp();
+        self.exec.as_arch_mut().set_reg(RegisterName::new(28), gp);
 
-                if x == 0x00000000 || x == 0x00000020 {
-                    nop_cnt += 1;
-                } else {
-                    nop_cnt = 0;
-                }
+        // without an entry label, start where the first `.text` does
+        let pc = match self.globals.get(&self.entry_label) {
+            Some(x) => *x,
+            None => self.assembler_config.text_base,
+        };
+        self.exec.as_arch_mut().set_pc(pc);
+
+        Updates::all()
+    }
+
+    /// The outer error is from assembling, the inner one from running.
+    fn assemble_and_run(
+        &mut self,
+        code: &str,
+        endian: EndianMode,
+        max_steps: u64,
+    ) -> Result<(Updates, Result<(), String>), String> {
+        let updates = self.assemble(code, endian)?;
+        self.run_started_at = 0;
+        self.last_error = None;
+
+        let result = self.run_steps(max_steps);
+        Ok((updates, result.map(|_| ())))
+    }
+
+    /// Replaces the program with a raw image, as if it had been assembled at `base`.
+    fn load_binary(&mut self, base: u32, bytes: &[u8]) -> Updates {
+        let mut seg = Segment::new(base, self.endian);
+        seg.append_bytes(bytes);
+        let segs = vec![seg];
+        let mem = create_memory_with(self.endian, &segs, self.memory_config);
+
+        self.forget_program();
+        self.segments = segs;
+        self.build_executor(mem);
+
+        if self.assembler_config.text_range.contains(&base) {
+            self.exec.as_arch_mut().set_pc(base);
+        }
+        Updates::all()
+    }
+
+    fn restore(&mut self, bytes: &[u8]) -> Result<Updates, String> {
+        let snapshot = Snapshot::decode(bytes)?;
+        let segs = snapshot.segments();
+        let mem = create_memory_with(snapshot.endian, &segs, self.memory_config);
+
+        self.clean_after_reset = false;
+        self.endian = snapshot.endian;
+        self.forget_program();
+        self.restored = segs;
+        self.build_executor(mem);
+        snapshot.apply_regs(self.exec.as_arch_mut());
+
+        Ok(Updates::all())
+    }
+
+    /// Drops the source, labels and segments of the last program, so that the listing,
+    /// export and checksums don't describe it once memory is replaced some other way.
+    fn forget_program(&mut self) {
+        self.source = None;
+        self.units.clear();
+        self.globals.clear();
+        self.labels.clear();
+        self.warnings.clear();
+        self.symbols.clear();
+        self.segments.clear();
+        self.restored.clear();
+        self.data_ranges.clear();
+    }
+
+    fn build_executor(&mut self, mem: Box<dyn Memory>) {
+        self.exec = self.make_executor(Interpreter::new(mem));
+        let arch = self.exec.as_arch_mut();
+        arch.set_random_seed(self.random_seed);
+        arch.set_user_space(self.assembler_config.user_space());
+        self.waiting_for_input = false;
+        self.timed_out = false;
+        self.paused = false;
+        // the new executor counts from zero
+        self.run_started_at = 0;
+        self.stopped = None;
+        self.error_kind = None;
+        self.set_delay_slots(self.delay_slots);
+        self.set_trace(self.trace);
+        self.set_profiling(self.profiling);
+        self.set_cache(self.cache);
+        self.set_branch_prediction(self.branch_prediction);
+        *self.disassembly_range.lock() = None;
+        // labels and data ranges may have changed with the program
+        self.disasm_cache.lock().clear();
+        *self.last_sent.lock() = LastSent::default();
+    }
+
+    /// Uses the JIT whenever the memory allows it and it's not forced off, and the
+    /// interpreter otherwise.
+    fn make_executor(&self, interpreter: Interpreter) -> Executor {
+        let mem = interpreter.as_arch().mem();
+        let can_use_jit = mem.endian() == EndianMode::native() && mem.fastmem_addr().is_some();
+
+        if HAS_JIT && can_use_jit && !self.force_interpreter {
+            Executor::ExJit(Jit::from_interpreter(interpreter))
+        } else {
+            Executor::ExInterpreter(interpreter)
+        }
+    }
+
+    /// Swaps between the JIT and the interpreter in place, keeping the machine state and
+    /// the interpreter with its trace, profile and statistics.
+    fn set_force_interpreter(&mut self, enabled: bool) -> Updates {
+        self.force_interpreter = enabled;
+
+        if let Executor::ExPipeline(_) = self.exec {
+            return Updates::empty();
+        }
+
+        let placeholder =
+            Executor::ExInterpreter(Interpreter::new(create_empty_memory(self.endian)));
+        let interpreter = match replace(&mut self.exec, placeholder) {
+            Executor::ExInterpreter(x) => x,
+            Executor::ExJit(x) => x.into_interpreter(),
+            Executor::ExPipeline(_) => unreachable!(),
+        };
+        self.exec = self.make_executor(interpreter);
+
+        Updates::FLAG_CAN_USE_JIT
+    }
+
+    /// Switches the byte order, re-assembling the current program if there is one.
+    fn set_endian(&mut self, endian: EndianMode) -> Result<Updates, String> {
+        self.endian = endian;
+
+        match self.source.clone() {
+            Some(x) => self.assemble(&x, endian),
+            None if !self.units.is_empty() => self.assemble_units(&self.units.clone(), endian),
+            None if !self.restored.is_empty() => {
+                let mem = create_memory_with(endian, &self.restored, self.memory_config);
+                self.build_executor(mem);
+                Ok(Updates::all())
+            }
+            None if !self.segments.is_empty() => {
+                let mem = create_memory_with(endian, &self.segments, self.memory_config);
+                self.build_executor(mem);
+                Ok(Updates::all())
+            }
+            None => {
+                self.build_executor(create_empty_memory_with(endian, self.memory_config));
+                Ok(Updates::all())
+            }
+        }
+    }
+
+    fn set_delay_slots(&mut self, enabled: bool) {
+        self.delay_slots = enabled;
+        if let Some(x) = self.exec.interpreter_mut() {
+            x.set_delay_slots(enabled);
+        }
+    }
+
+    fn set_trace(&mut self, enabled: bool) {
+        self.trace = enabled;
+        if let Some(x) = self.exec.interpreter_mut() {
+            x.set_trace(enabled);
+        }
+    }
+
+    fn capture_changes(&self) -> Vec<RegChange> {
+        let changes = self.exec.interpreter().map(|x| x.last_changes());
+        changes.map(|x| x.to_vec()).unwrap_or_default()
+    }
+
+    fn capture_flags(&self) -> Option<ArithFlags> {
+        self.exec.interpreter().and_then(|x| x.last_flags())
+    }
+
+    fn in_delay_slot(&self) -> bool {
+        self.exec.interpreter().is_some_and(|x| x.in_delay_slot())
+    }
+
+    /// Return addresses, innermost last, each with the nearest label at or before it.
+    fn capture_call_stack(&self) -> Vec<(u32, Option<String>)> {
+        let stack = self.exec.interpreter().map(|x| x.call_stack());
+        stack
+            .unwrap_or_default()
+            .iter()
+            .map(|&x| (x, self.nearest_label(x)))
+            .collect()
+    }
+
+    /// `name` or `name+0x10` for the closest label at or before `addr`.
+    fn nearest_label(&self, addr: u32) -> Option<String> {
+        let (name, base) = self
+            .labels
+            .iter()
+            .filter(|(_, &base)| base <= addr)
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))?;
+
+        match addr - base {
+            0 => Some(name.clone()),
+            x => Some(format!("{}+0x{:x}", name, x)),
+        }
+    }
+
+    fn set_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+        if let Some(x) = self.exec.interpreter_mut() {
+            x.set_profiling(enabled);
+        }
+    }
+
+    fn set_cache(&mut self, config: Option<CacheConfig>) {
+        self.cache = config;
+        if let Some(x) = self.exec.interpreter_mut() {
+            x.set_cache(config);
+        }
+    }
+
+    fn set_branch_prediction(&mut self, enabled: bool) {
+        self.branch_prediction = enabled;
+        if let Some(x) = self.exec.interpreter_mut() {
+            x.set_branch_prediction(enabled);
+        }
+    }
+
+    fn capture_branch_stats(&self) -> Option<BranchReport> {
+        self.exec.interpreter().and_then(|x| x.branch_stats())
+    }
+
+    fn capture_cache_stats(&self) -> Option<CacheStats> {
+        self.exec.interpreter().and_then(|x| x.cache_stats())
+    }
+
+    fn capture_profile(&self) -> HashMap<String, u64> {
+        let profile = self.exec.interpreter().map(|x| x.profile());
+        profile.unwrap_or_default()
+    }
+
+    fn capture_trace(&self) -> Vec<TraceEntry> {
+        let trace = self.exec.interpreter().and_then(|x| x.trace());
+        trace
+            .map(|x| x.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn edit_pc(&mut self, val: u32) -> Result<Updates, String> {
+        if val & 3 != 0 {
+            return Err(format!("pc 0x{:08x} is not word-aligned", val));
+        }
+        // restored memory has no program segments, but its text pages hold code
+        let in_restored_text = self.assembler_config.text_range.contains(&val)
+            && self.restored.iter().any(|x| x.contains(val));
+        if !in_restored_text && !self.segments.iter().any(|x| x.contains(val)) {
+            return Err(format!("pc 0x{:08x} is outside of any segment", val));
+        }
+
+        self.clean_after_reset = false;
+        self.exec.as_arch_mut().set_pc(val);
+        Ok(Updates::REGISTERS)
+    }
+
+    fn read_memory(&self, page_idx: u32, output: &mut [u8]) {
+        self.read_pages(page_idx, 1, output);
+    }
+
+    fn read_pages(&self, start_page: u32, count: u32, output: &mut [u8]) {
+        assert_eq!(
+            output.len(),
+            count as usize * 4096,
+            "one page is 4096 bytes"
+        );
+        let end_page = start_page as u64 + count as u64;
+        assert!(end_page <= LAST_PAGE as u64 + 1, "cannot read past memory");
+
+        // fastmem can't take a slice that ends at 4GiB
+        let (output, last) = if end_page == LAST_PAGE as u64 + 1 {
+            output.split_at_mut(output.len() - 4096)
+        } else {
+            (output, &mut [][..])
+        };
+        last.fill(0);
+
+        let mem = self.exec.as_arch().mem();
+        mem.read_into_slice(start_page.wrapping_mul(4096), output);
+    }
+
+    /// Like `read_memory`, but as words in the program's byte order, so they read the same
+    /// as `lw` would see them whatever the host's order.
+    fn read_memory_words(&self, page_idx: u32, output: &mut [u32]) {
+        let mut bytes = vec![0; output.len() * 4];
+        self.read_pages(page_idx, 1, &mut bytes);
+
+        let mem = self.exec.as_arch().mem();
+        for (x, chunk) in output.iter_mut().zip(bytes.chunks_exact(4)) {
+            *x = mem.endian().read_u32(chunk);
+        }
+    }
+
+    /// Reads any window of memory. Unmapped bytes read as zero, and the window wraps at 4GiB.
+    fn read_range(&self, addr: u32, output: &mut [u8]) {
+        let mem = self.exec.as_arch().mem();
 
-                mapping.insert(addr, (x, disassemble(x)));
-                max_addr = addr;
-                addr += 4;
+        if (addr as u64) + (output.len() as u64) < (1 << 32) {
+            mem.read_into_slice(addr, output);
+        } else {
+            for (i, x) in output.iter_mut().enumerate() {
+                *x = mem.read_u8(addr.wrapping_add(i as u32));
             }
         }
+    }
+
+    fn set_abi_names(&mut self, enabled: bool) {
+        self.disasm_options.abi_names = enabled;
+        *self.disassembly_range.lock() = None;
+        self.disasm_cache.lock().clear();
+        self.last_sent.lock().disasm.clear();
+    }
+
+    fn set_disasm_window(&mut self, before: u32, after: u32) {
+        self.disasm_window = (before.min(MAX_DISASM_WINDOW), after.min(MAX_DISASM_WINDOW));
+        *self.disassembly_range.lock() = None;
+    }
+
+    fn set_data_as_words(&mut self, enabled: bool) {
+        self.data_as_words = enabled;
+        *self.disassembly_range.lock() = None;
+        self.disasm_cache.lock().clear();
+        self.last_sent.lock().disasm.clear();
+    }
+
+    /// Writes raw bytes in memory order, so the caller decides the byte order of any words.
+    fn write_memory(&mut self, addr: u32, bytes: &[u8]) -> Updates {
+        self.clean_after_reset = false;
+        self.exec
+            .as_arch_mut()
+            .mem_mut()
+            .write_from_slice(addr, bytes);
+        self.exec.invalidate_range(addr, bytes.len() as u32);
+
+        // the written range may be on screen
+        *self.disassembly_range.lock() = None;
+        Updates::DISASSEMBLY
+    }
+
+    fn is_finished(&self) -> bool {
+        self.exec.as_arch().is_finished()
+    }
+
+    fn step(&mut self) -> Result<Updates, String> {
+        self.clean_after_reset = false;
+        if self.is_finished() {
+            Ok(Updates::empty())
+        } else {
+            let result = self.exec.step();
+            self.finish_step(result)
+        }
+    }
+
+    fn exec(&mut self) -> Result<Updates, String> {
+        self.clean_after_reset = false;
+        if self.is_finished() {
+            Ok(Updates::empty())
+        } else {
+            let result = self.exec.exec();
+            self.finish_step(result)
+        }
+    }
+
+    /// Waiting for input is not an error; it only sets `waiting_for_input`.
+    fn finish_step(&mut self, result: Result<(), ExecuteError>) -> Result<Updates, String> {
+        self.waiting_for_input = matches!(result, Err(ExecuteError::NeedsInput { .. }));
+        self.error_kind = match &result {
+            Err(x) if !self.waiting_for_input => Some(x.kind()),
+            _ => None,
+        };
+
+        match result {
+            Err(x) if !self.waiting_for_input => Err(x.to_string()),
+            _ => Ok(Updates::REGISTERS),
+        }
+    }
+
+    /// Starts counting a new free run toward its run limit.
+    fn start_run(&mut self) {
+        self.run_started_at = self.exec.as_arch().retired();
+        self.last_error = None;
+        self.paused = false;
+    }
+
+    /// Keeps a stopped free run resumable.
+    fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Moves the machine state into the pipeline executor.
+    fn convert_to_pipeline(&mut self) {
+        let endian = self.exec.as_arch().mem().endian();
+        let mut pipeline = Executor::ExPipeline(Pipeline::new(create_empty_memory(endian)));
+        swap(&mut pipeline, &mut self.exec);
+
+        *self.exec.as_arch_mut() = pipeline.into_arch();
+    }
+
+    /// Leaves the paused state, returning whether there was a run to resume.
+    fn resume(&mut self) -> bool {
+        let paused = self.paused;
+        self.paused = false;
+        if paused {
+            self.last_error = None;
+        }
+        paused
+    }
+
+    fn run_tick(&mut self, allow_jit: bool) -> Result<Updates, String> {
+        let updates = if allow_jit {
+            self.exec()?
+        } else {
+            self.step()?
+        };
+
+        if let Some(limit) = self.run_limit {
+            if self
+                .exec
+                .as_arch()
+                .retired()
+                .saturating_sub(self.run_started_at)
+                >= limit
+            {
+                return Err(format!("step limit exceeded ({} instructions)", limit));
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// Steps at least once, stopping when the PC equals `addr`.
+    /// Fails if that takes more than `max_steps` steps, or the program ends first.
+    fn run_until(&mut self, addr: u32, max_steps: u64) -> Result<Updates, String> {
+        let mut updates = Updates::empty();
+
+        for _ in 0..max_steps {
+            if self.is_finished() {
+                return Err(format!("program ended before reaching 0x{:08x}", addr));
+            }
+
+            updates |= self.step()?;
+            if self.exec.as_arch().pc() == addr {
+                return Ok(updates);
+            }
+            if self.waiting_for_input {
+                return Err(format!("waiting for input before reaching 0x{:08x}", addr));
+            }
+        }
+
+        Err(format!(
+            "did not reach 0x{:08x} within {} steps",
+            addr, max_steps
+        ))
+    }
+
+    /// On `jal`, `jalr` or a linking branch, steps until the call returns past it, or
+    /// passes it if the branch isn't taken. A recursive call returning to the same place
+    /// doesn't count, as `$sp` is still below where it started. Otherwise steps once.
+    fn step_over(&mut self, max_steps: u64) -> Result<Updates, String> {
+        let arch = self.exec.as_arch();
+        let pc = arch.pc();
+        let sp = arch.reg(RegisterName::new(29));
+        let is_call = match Instruction::decode(arch.mem().read_u32(pc)) {
+            Instruction::jal(_) | Instruction::bgezal(_) | Instruction::bltzal(_) => true,
+            Instruction::jalr(x) => !x.rd.is_zero(),
+            _ => false,
+        };
+        if !is_call {
+            return self.step();
+        }
+
+        let ret = pc.wrapping_add(if self.delay_slots { 8 } else { 4 });
+        let mut updates = Updates::empty();
+        for _ in 0..max_steps {
+            if self.is_finished() {
+                return Err("program ended inside the call".into());
+            }
+
+            updates |= self.step()?;
+            let arch = self.exec.as_arch();
+            if arch.pc() == ret && arch.reg(RegisterName::new(29)) >= sp {
+                return Ok(updates);
+            }
+            if self.waiting_for_input {
+                return Ok(updates);
+            }
+        }
+
+        Err(format!("call did not return within {} steps", max_steps))
+    }
+
+    fn exec_with_timeout(&mut self, timeout: Duration) -> Result<Updates, String> {
+        self.timed_out = false;
+        if self.is_finished() {
+            return Ok(Updates::empty());
+        }
+
+        self.clean_after_reset = false;
+        let result = self.exec.run_for(timeout);
+        self.waiting_for_input = matches!(result, RunResult::NeedsInput);
+        self.error_kind = match &result {
+            RunResult::Error(x) => Some(x.kind()),
+            _ => None,
+        };
+        self.timed_out = matches!(result, RunResult::TimedOut);
+        self.stopped = stop_reason(&result);
+
+        match result {
+            RunResult::Error(x) => Err(x.to_string()),
+            _ => Ok(Updates::REGISTERS),
+        }
+    }
+
+    /// Steps `n` times, stopping early if the program ends or waits for input.
+    fn run_steps(&mut self, n: u64) -> Result<Updates, String> {
+        if n == 0 || self.is_finished() {
+            return Ok(Updates::empty());
+        }
+
+        self.clean_after_reset = false;
+        let result = self
+            .exec
+            .run_to_completion(&self.breakpoints, &self.watchpoints, n);
+        self.waiting_for_input = matches!(result, RunResult::NeedsInput);
+        self.error_kind = match &result {
+            RunResult::Error(x) => Some(x.kind()),
+            _ => None,
+        };
+        self.stopped = stop_reason(&result);
+
+        match result {
+            RunResult::Error(x) => Err(x.to_string()),
+            _ => Ok(Updates::REGISTERS),
+        }
+    }
+
+    fn needs_capture_disasm(&self) -> bool {
+        let range = self.disassembly_range.lock();
+
+        match range.as_ref() {
+            Some(x) => !x.contains(&self.exec.as_arch().pc()),
+            None => true,
+        }
+    }
+
+    fn capture_regs(&self) -> [u32; 32] {
+        let mut ret = [0; 32];
+        self.exec.as_arch().read_all_reg(&mut ret);
+        ret
+    }
+
+    /// With diff updates on, the registers that changed since the last notification as
+    /// `(number, value)`, and whether the pc did. `None` when everything should be sent.
+    fn diff_regs(&self, regs: &[u32; 32], pc: u32) -> Option<(Vec<(u8, u32)>, bool)> {
+        if !self.diff_updates {
+            return None;
+        }
+
+        let mut last = self.last_sent.lock();
+        let prev = last.regs.replace((*regs, pc))?;
+        let dirty = (0..32u8)
+            .filter(|&i| regs[i as usize] != prev.0[i as usize])
+            .map(|i| (i, regs[i as usize]))
+            .collect();
+        Some((dirty, pc != prev.1))
+    }
+
+    /// With diff updates on, the FP registers that changed since the last notification as
+    /// `(number, value)`. `None` when all of them should be sent.
+    fn diff_fp_regs(&self, fp_regs: &[u32; 32]) -> Option<Vec<(u8, u32)>> {
+        if !self.diff_updates {
+            return None;
+        }
+
+        let mut last = self.last_sent.lock();
+        let prev = last.fp_regs.replace(*fp_regs)?;
+        let dirty = (0..32u8)
+            .filter(|&i| fp_regs[i as usize] != prev[i as usize])
+            .map(|i| (i, fp_regs[i as usize]))
+            .collect();
+        Some(dirty)
+    }
+
+    /// With diff updates on, drops the addresses whose word was already sent.
+    fn diff_disasm(&self, mapping: &mut FxHashMap<u32, (u32, String)>) {
+        if !self.diff_updates {
+            return;
+        }
+
+        let mut last = self.last_sent.lock();
+        mapping.retain(|addr, (word, _)| last.disasm.insert(*addr, *word) != Some(*word));
+    }
+
+    fn capture_fp_regs(&self) -> [u32; 32] {
+        let mut ret = [0; 32];
+        ret.copy_from_slice(self.exec.as_arch().fprs());
+        ret
+    }
+
+    /// The registers, hi and lo as text, unless the format is raw.
+    fn capture_formatted_regs(
+        &self,
+        regs: &[u32; 32],
+        hi: u32,
+        lo: u32,
+    ) -> Option<(Vec<String>, String, String)> {
+        let format = self.register_format;
+        let regs = regs
+            .iter()
+            .map(|&x| format.format(x))
+            .collect::<Option<_>>()?;
+        Some((regs, format.format(hi)?, format.format(lo)?))
+    }
+
+    fn capture_console(&self) -> String {
+        self.exec.as_arch().console()
+    }
+
+    /// With diff updates on, the console output written since the last notification.
+    /// `None` when all of it should be sent.
+    fn diff_console(&self) -> Option<String> {
+        if !self.diff_updates {
+            return None;
+        }
+
+        let arch = self.exec.as_arch();
+        let written = arch.console_written();
+        let prev = self.last_sent.lock().console_written.replace(written)?;
+        arch.console_tail(written.checked_sub(prev)?)
+    }
+
+    fn capture_pc(&self) -> u32 {
+        self.exec.as_arch().pc()
+    }
+
+    fn capture_hi_lo(&self) -> (u32, u32) {
+        let arch = self.exec.as_arch();
+        (arch.hi(), arch.lo())
+    }
+
+    /// The span covered by text segments, as `start..end`.
+    fn text_region(&self) -> Option<(u64, u64)> {
+        let text = self.segments.iter().filter(|x| {
+            self.assembler_config.text_range.contains(&x.base_addr) && !x.data.is_empty()
+        });
+        let start = text.clone().map(|x| x.base_addr as u64).min()?;
+        let end = text
+            .map(|x| x.base_addr as u64 + x.data.len() as u64)
+            .max()?;
+        Some((start, end))
+    }
+
+    /// Disassembles the requested window around the pc. The window is clamped to the text
+    /// region while the pc is inside it.
+    fn capture_disasm(&self) -> FxHashMap<u32, (u32, String)> {
+        let mut range = self.disassembly_range.lock();
+        let pc = self.exec.as_arch().pc() as u64;
+        let mem = self.exec.as_arch().mem();
+        let (before, after) = self.disasm_window;
+
+        let mut start = pc.saturating_sub(before as u64 * 4);
+        let mut end = (pc + after as u64 * 4).min(u32::MAX as u64 & !3);
+        if let Some((text_start, text_end)) = self.text_region() {
+            if text_start <= pc && pc < text_end {
+                start = start.max(text_start);
+                end = end.min(text_end - 4);
+            }
+        }
+
+        let mut cache = self.disasm_cache.lock();
+        if cache.len() > DISASM_CACHE_CAPACITY {
+            cache.clear();
+        }
+
+        let mut mapping = FxHashMap::default();
+        for addr in (start..=end).step_by(4) {
+            let addr = addr as u32;
+            let x = mem.read_u32(addr);
+            let around = [
+                mem.read_u32(addr.wrapping_sub(4)),
+                x,
+                mem.read_u32(addr.wrapping_add(4)),
+            ];
+            let text = cache
+                .entry((addr, around))
+                .or_insert_with(|| self.disassemble(addr, around));
+            mapping.insert(addr, (x, text.clone()));
+        }
 
-        *range = Some(min_addr..=max_addr);
+        *range = Some(start as u32..=end as u32);
 
         mapping
     }
 
+    /// Disassembles the middle of three consecutive words starting at `addr - 4`.
+    fn disassemble(&self, addr: u32, words: [u32; 3]) -> String {
+        let [prev, ins, next] = words;
+        let is_data = |x: &Range<u32>| x.start < addr.saturating_add(4) && addr < x.end;
+        if self.data_as_words && self.data_ranges.iter().any(is_data) {
+            return format!(".word 0x{:08x}", ins);
+        }
+
+        let around = (Some(prev), Some(next));
+        disassemble_with_symbols(ins, Some(addr), &self.disasm_options, &self.symbols, around)
+    }
+
     fn capture_pipeline_detail(&self) -> String {
         let detail = if let Executor::ExPipeline(x) = &self.exec {
             x.get_pipeline_detail()
@@ -301,10 +1850,29 @@ impl Inner {
         serde_json::to_string(&detail).unwrap_or_else(|_| "".into())
     }
 
+    fn capture_endian(&self) -> &'static str {
+        // ask the executor, so this always matches what is actually running
+        match self.exec.as_arch().mem().endian() {
+            EndianMode::Little => "little",
+            EndianMode::Big => "big",
+        }
+    }
+
+    fn capture_force_interpreter(&self) -> bool {
+        self.force_interpreter
+    }
+
     fn capture_running(&self) -> bool {
         super::looper::is_running()
     }
 
+    fn capture_jit_stats(&self) -> Option<JitStats> {
+        match &self.exec {
+            Executor::ExJit(x) => Some(x.stats()),
+            _ => None,
+        }
+    }
+
     fn capture_can_use_jit(&self) -> bool {
         match self.exec {
             Executor::ExInterpreter(_) => false,
@@ -322,6 +1890,17 @@ impl Inner {
     }
 }
 
+/// The `stopped` reported for a run's result, with the exit code or address that goes
+/// with it.
+fn stop_reason(result: &RunResult) -> Option<(&'static str, u32)> {
+    match *result {
+        RunResult::Exited(code) => Some(("exit", code)),
+        RunResult::Breakpoint(addr) => Some(("breakpoint", addr)),
+        RunResult::Watchpoint(addr) => Some(("watchpoint", addr)),
+        _ => None,
+    }
+}
+
 fn js_array_numbers<'a, 'b, C: Context<'a>>(
     cx: &mut C,
     iter: impl Iterator<Item = &'b u32>,
@@ -337,3 +1916,1007 @@ fn js_array_numbers<'a, 'b, C: Context<'a>>(
 
     Ok(a)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::assembler::assemble;
+    use crate::component::FpRegisterName;
+    use crate::memory::{crc32, create_memory_fastmem};
+
+    const TEXT_ADDR: u32 = 0x00400024;
+
+    fn init_inner(asm: &str) -> Inner {
+        let mut inner = Inner::default();
+        let code = format!(".text\n{}", asm);
+        let _ = inner.assemble(&code, EndianMode::native()).unwrap();
+        inner
+    }
+
+    #[test]
+    fn fp_regs() {
+        let mut inner = init_inner("mul.s $f3, $f1, $f2");
+        let arch = inner.exec.as_arch_mut();
+        arch.set_fpr(FpRegisterName::new(1), 3.0f32.to_bits());
+        arch.set_fpr(FpRegisterName::new(2), (-0.5f32).to_bits());
+        let _ = inner.step().unwrap();
+
+        let fp_regs = inner.capture_fp_regs();
+        assert_eq!(fp_regs[3], (-1.5f32).to_bits());
+        assert_eq!(fp_regs[1], 3.0f32.to_bits());
+        assert_eq!(fp_regs[0], 0);
+    }
+
+    #[test]
+    fn run_until_reaches_target() {
+        let mut inner = init_inner(
+            "
+            addi $t0, $zero, 1
+            addi $t0, $t0, 1
+            addi $t0, $t0, 1
+            addi $t0, $t0, 1",
+        );
+
+        let _ = inner.run_until(TEXT_ADDR + 12, 100).unwrap();
+
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 12);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 3);
+    }
+
+    #[test]
+    fn read_int_waits_for_input() {
+        let mut inner = init_inner(
+            "
+            li $v0, 5
+            syscall
+            addi $v0, $v0, 1",
+        );
+
+        let _ = inner.run_steps(10).unwrap();
+        assert!(inner.waiting_for_input);
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 4);
+
+        inner.exec.as_arch_mut().provide_input("41");
+        let _ = inner.run_steps(2).unwrap();
+        assert!(!inner.waiting_for_input);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(2)), 42);
+    }
+
+    #[test]
+    fn step_reports_address_error() {
+        let mut inner = init_inner("sw $zero, 0($zero)");
+
+        let err = inner.step().unwrap_err();
+        assert_eq!(err, "address error on store at 0x00000000");
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR);
+    }
+
+    #[test]
+    fn starts_at_entry_label() {
+        let code = "
+            .globl main
+            nop
+            nop
+            main:
+            addi $t0, $zero, 1";
+
+        let inner = init_inner(code);
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 8);
+
+        // a label that isn't exported, or isn't there, leaves the text base
+        let inner = init_inner(&code.replace(".globl main", ""));
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR);
+
+        let mut inner = Inner {
+            entry_label: "__start".into(),
+            ..Default::default()
+        };
+        let code = format!(".text\n{}", code);
+        let _ = inner.assemble(&code, EndianMode::native()).unwrap();
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR);
+    }
+
+    #[test]
+    fn call_stack_resolves_labels() {
+        let mut inner = init_inner(
+            "
+            main:
+            jal outer
+            nop
+            outer:
+            jal inner
+            nop
+            inner:
+            nop",
+        );
+
+        let _ = inner.run_steps(2).unwrap();
+        let stack = inner.capture_call_stack();
+        assert_eq!(
+            stack,
+            [
+                (TEXT_ADDR + 4, Some("main+0x4".into())),
+                (TEXT_ADDR + 12, Some("outer+0x4".into())),
+            ]
+        );
+    }
+
+    #[test]
+    fn assemble_and_run_to_completion() {
+        let mut inner = Inner::default();
+        let code = "
+            .text
+            li $t0, 0
+            li $t1, 5
+            loop:
+            add $t0, $t0, $t1
+            addi $t1, $t1, -1
+            bne $t1, $zero, loop
+            li $v0, 10
+            syscall";
+
+        let (_, result) = inner
+            .assemble_and_run(code, EndianMode::native(), 1000)
+            .unwrap();
+        assert!(result.is_ok());
+        assert!(inner.is_finished());
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 15);
+        assert_eq!(inner.exec.as_arch().retired(), 19);
+
+        let err = inner.assemble_and_run("bogus", EndianMode::native(), 1000);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn run_steps_reports_stop() {
+        let mut inner = init_inner("nop\nnop\nli $a0, 3\nli $v0, 17\nsyscall");
+        inner.breakpoints = vec![TEXT_ADDR + 8];
+
+        let _ = inner.run_steps(100).unwrap();
+        assert_eq!(inner.stopped, Some(("breakpoint", TEXT_ADDR + 8)));
+        let _ = inner.run_steps(1).unwrap();
+        assert_eq!(inner.stopped, None);
+        let _ = inner.run_steps(100).unwrap();
+        assert_eq!(inner.stopped, Some(("exit", 3)));
+    }
+
+    #[test]
+    fn disassemble_image() {
+        let mut inner = init_inner("nop");
+        let bytes = [0x01, 0x09, 0x80, 0x20, 0x00, 0x00, 0x00, 0x00, 0xff];
+        let pc = inner.exec.as_arch().pc();
+
+        let listing = inner.disassemble_image(0x1000, &bytes, EndianMode::Big);
+        assert_eq!(
+            listing,
+            [
+                (0x1000, "add $s0, $t0, $t1".to_string()),
+                (0x1004, "nop".to_string())
+            ]
+        );
+
+        inner.disasm_options.abi_names = false;
+        let listing = inner.disassemble_image(0, &bytes[..4], EndianMode::Big);
+        assert_eq!(listing[0].1, "add $16, $8, $9");
+        assert_eq!(inner.exec.as_arch().pc(), pc);
+    }
+
+    #[test]
+    fn error_kind() {
+        let mut inner = init_inner("lw $t0, 1($zero)\nnop");
+        assert!(inner.step().is_err());
+        assert_eq!(inner.error_kind, Some("unaligned"));
+
+        inner.exec.as_arch_mut().set_pc(TEXT_ADDR + 4);
+        let _ = inner.step().unwrap();
+        assert_eq!(inner.error_kind, None);
+    }
+
+    #[test]
+    fn segment_checksums() {
+        let mut inner = Inner::default();
+        let code = ".data\n.ascii \"123456789\"\n.text\n.data 0x10001000";
+        let _ = inner.assemble(code, EndianMode::native()).unwrap();
+
+        assert_eq!(inner.segment_checksums(), [(0x1000_0000, 0xcbf43926)]);
+    }
+
+    #[test]
+    fn assemble_units_runs_linked_program() {
+        let mut inner = Inner::default();
+        let units = [
+            (
+                "main.s".to_string(),
+                ".text\n.globl main\nmain: jal twice\nli $v0, 10\nsyscall".to_string(),
+            ),
+            (
+                "lib.s".to_string(),
+                ".text\n.globl twice\ntwice: add $v1, $a0, $a0\njr $ra".to_string(),
+            ),
+        ];
+        let _ = inner.assemble_units(&units, EndianMode::native()).unwrap();
+        inner.exec.as_arch_mut().set_reg(RegisterName::new(4), 21);
+
+        let _ = inner.run_steps(100).unwrap();
+        assert!(inner.is_finished());
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(3)), 42);
+
+        let _ = inner.set_endian(EndianMode::native()).unwrap();
+        assert_eq!(inner.label_address("twice"), Some(TEXT_ADDR + 12));
+    }
+
+    #[test]
+    fn pause_and_resume() {
+        let mut inner = init_inner(
+            "li $t0, 3\nloop: addi $t0, $t0, -1\nbne $t0, $zero, loop\nli $v0, 10\nsyscall",
+        );
+        inner.run_limit = Some(100);
+
+        // as a free run does, until the frontend pauses it
+        inner.start_run();
+        for _ in 0..3 {
+            let _ = inner.run_tick(false).unwrap();
+        }
+        inner.pause();
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 4);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 2);
+
+        assert!(inner.resume());
+        assert!(!inner.resume());
+        while !inner.is_finished() {
+            let _ = inner.run_tick(false).unwrap();
+        }
+        assert_eq!(inner.run_started_at, 0);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 0);
+    }
+
+    #[test]
+    fn label_address() {
+        let mut inner = Inner::default();
+        let code = "
+            .data
+            pad: .word 0
+            counter: .word 0
+            .text
+            main: nop";
+        let _ = inner.assemble(code, EndianMode::native()).unwrap();
+
+        assert_eq!(inner.label_address("counter"), Some(0x1000_0004));
+        assert_eq!(inner.label_address("Main"), Some(TEXT_ADDR));
+        assert_eq!(inner.label_address("missing"), None);
+    }
+
+    #[test]
+    fn gp_relative_load() {
+        let mut inner = Inner::default();
+        let code = "
+            .data
+            x: .word 42
+            .text
+            la $t1, x
+            lw $t0, x
+            li $v0, 10
+            syscall";
+
+        let (_, result) = inner
+            .assemble_and_run(code, EndianMode::native(), 100)
+            .unwrap();
+        assert!(result.is_ok());
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 42);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(9)), 0x1000_0000);
+    }
+
+    #[test]
+    fn step_over_runs_call() {
+        let mut inner = init_inner(
+            "
+            jal double
+            addi $t1, $t0, 0
+            j 0
+            double:
+            add $t0, $a0, $a0
+            jr $ra",
+        );
+        inner.exec.as_arch_mut().set_reg(RegisterName::new(4), 21);
+
+        let _ = inner.step_over(100).unwrap();
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 4);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 42);
+
+        // not a call, so just one step
+        let _ = inner.step_over(100).unwrap();
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 8);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(9)), 42);
+    }
+
+    #[test]
+    fn exec_with_timeout_yields() {
+        let mut inner = init_inner("loop: addi $t0, $t0, 1\nj loop");
+
+        let _ = inner.exec_with_timeout(Duration::from_millis(10)).unwrap();
+        assert!(inner.timed_out);
+        assert!(inner.exec.as_arch().reg(RegisterName::new(8)) > 0);
+    }
+
+    #[test]
+    fn run_until_hits_step_cap() {
+        let mut inner = init_inner(
+            "
+            loop:
+            j loop",
+        );
+
+        assert!(inner.run_until(0x00500000, 1000).is_err());
+    }
+
+    #[test]
+    fn run_until_stops_when_program_ends() {
+        let mut inner = init_inner("j 0x00000000");
+
+        assert!(inner.run_until(TEXT_ADDR + 4, 1000).is_err());
+    }
+
+    #[test]
+    fn delay_slots_survive_assemble() {
+        let mut inner = Inner::default();
+        inner.set_delay_slots(true);
+        let _ = inner
+            .assemble(
+                ".text\nj 0x00401000\naddi $t0, $zero, 1",
+                EndianMode::native(),
+            )
+            .unwrap();
+
+        let _ = inner.run_steps(2).unwrap();
+
+        assert_eq!(inner.exec.as_arch().pc(), 0x00401000);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 1);
+    }
+
+    #[test]
+    fn disasm_names_jump_targets() {
+        let inner = init_inner("loop: nop\nj loop");
+        let mapping = inner.capture_disasm();
+        assert_eq!(mapping[&(TEXT_ADDR + 4)].1, "j loop");
+    }
+
+    #[test]
+    fn disasm_register_names() {
+        let mut inner = init_inner("add $t0, $ra, $zero");
+        let mapping = inner.capture_disasm();
+        assert_eq!(mapping[&TEXT_ADDR].1, "add $t0, $ra, $zero");
+
+        inner.set_abi_names(false);
+        let mapping = inner.capture_disasm();
+        assert_eq!(mapping[&TEXT_ADDR].1, "add $8, $31, $0");
+    }
+
+    #[test]
+    fn write_memory_readback() {
+        let mut inner = init_inner("nop");
+        let word = 0x12345678_u32.to_be_bytes();
+        let _ = inner.write_memory(0x10000004, &word);
+        assert!(!inner.clean_after_reset);
+
+        let mut page = vec![0; 4096];
+        inner.read_memory(0x10000000 / 4096, &mut page);
+        assert_eq!(&page[4..8], &word);
+        assert_eq!(&page[0..4], &[0; 4]);
+    }
+
+    #[test]
+    fn edit_pc_resumes() {
+        let mut inner = init_inner(
+            "
+            addi $t0, $zero, 1
+            addi $t1, $zero, 2
+            addi $t2, $zero, 3",
+        );
+
+        let _ = inner.edit_pc(TEXT_ADDR + 8).unwrap();
+        let _ = inner.step().unwrap();
+
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 12);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 0);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(9)), 0);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(10)), 3);
+    }
+
+    #[test]
+    fn edit_pc_validation() {
+        let mut inner = init_inner("nop\nnop");
+
+        assert!(inner.edit_pc(TEXT_ADDR + 2).is_err());
+        assert!(inner.edit_pc(TEXT_ADDR + 8).is_err());
+        assert!(inner.edit_pc(0x00001000).is_err());
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR);
+
+        let _ = inner.edit_pc(TEXT_ADDR + 4).unwrap();
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 4);
+    }
+
+    #[test]
+    fn read_range_across_pages() {
+        let mut inner = init_inner("nop");
+        let bytes = (0..16).collect::<Vec<u8>>();
+        let _ = inner.write_memory(0x10000ff8, &bytes);
+
+        let mut output = [0xff; 24];
+        inner.read_range(0x10000ff4, &mut output);
+
+        assert_eq!(&output[..4], &[0; 4]);
+        assert_eq!(&output[4..20], bytes.as_slice());
+        assert_eq!(&output[20..], &[0; 4]);
+    }
+
+    #[test]
+    fn read_pages_with_gap() {
+        let mut inner = init_inner("nop");
+        let _ = inner.write_memory(0x10010ffc, &[1, 2, 3, 4]);
+        let _ = inner.write_memory(0x10012000, &[5, 6]);
+
+        let mut output = vec![0xff; 3 * 4096];
+        inner.read_pages(0x10010, 3, &mut output);
+
+        // the pages follow each other, with the unwritten one in the middle zeroed
+        assert_eq!(&output[4092..4096], &[1, 2, 3, 4]);
+        assert!(output[4096..8192].iter().all(|&x| x == 0));
+        assert_eq!(&output[8192..8194], &[5, 6]);
+
+        let mut page = [0; 4096];
+        inner.read_memory(0x10012, &mut page);
+        assert_eq!(&output[8192..], &page[..]);
+    }
+
+    #[test]
+    fn read_last_pages() {
+        // fastmem is what refuses slices that reach 4GiB
+        let mut inner = Inner::default();
+        inner.build_executor(create_memory_fastmem(EndianMode::native(), &[]));
+
+        let mut output = vec![0xff; 2 * 4096];
+        inner.read_pages(LAST_PAGE - 1, 2, &mut output);
+        assert!(output.iter().all(|&x| x == 0));
+
+        let mut page = [0xff; 4096];
+        inner.read_memory(LAST_PAGE, &mut page);
+        assert!(page.iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    fn read_memory_words_last_page() {
+        let mut inner = Inner::default();
+        inner.build_executor(create_memory_fastmem(EndianMode::native(), &[]));
+
+        let mut words = [0xffff_ffff; 1024];
+        inner.read_memory_words(LAST_PAGE, &mut words);
+        assert!(words.iter().all(|&x| x == 0));
+    }
+
+    #[test]
+    fn read_memory_words_big_endian() {
+        let mut inner = Inner::default();
+        let _ = inner
+            .assemble(".data\n.word 0x01020304", EndianMode::Big)
+            .unwrap();
+
+        let mut bytes = [0; 4];
+        inner.read_range(0x10000000, &mut bytes);
+        assert_eq!(bytes, [1, 2, 3, 4]);
+
+        let mut words = [0; 1024];
+        inner.read_memory_words(0x10000000 / 4096, &mut words);
+        assert_eq!(words[0], 0x01020304);
+        assert_eq!(words[1], 0);
+    }
+
+    #[test]
+    fn poison_fills_unwritten_memory() {
+        let mut inner = Inner {
+            memory_config: MemoryConfig {
+                init: MemoryInit::Poison,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let code = ".data\n.word 1\n.text\nlui $t1, 0x1001\nlw $t0, 0($t1)";
+        let _ = inner.assemble(code, EndianMode::native()).unwrap();
+        let _ = inner.run_steps(2).unwrap();
+
+        let arch = inner.exec.as_arch();
+        assert_eq!(arch.reg(RegisterName::new(8)), 0xdeadbeef);
+        assert_eq!(arch.mem().read_u32(0x10000000), 1);
+        assert_eq!(arch.mem().read_u32(0x10000004), 0xdeadbeef);
+    }
+
+    #[test]
+    fn read_range_wraps() {
+        let mut inner = init_inner("nop");
+        let _ = inner.write_memory(0, &[1, 2]);
+        let _ = inner.write_memory(0xfffffffe, &[3, 4]);
+
+        let mut output = [0; 4];
+        inner.read_range(0xfffffffe, &mut output);
+        assert_eq!(output, [3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn custom_text_base_entry() {
+        let mut inner = Inner {
+            assembler_config: AssemblerConfig {
+                text_base: 0x0080_0000,
+                data_base: 0x2000_0000,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let _ = inner.assemble(".text\nnop", EndianMode::native()).unwrap();
+
+        assert_eq!(inner.exec.as_arch().pc(), 0x0080_0000);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(28)), 0x2000_8000);
+    }
+
+    #[test]
+    fn reset_keeps_memory_settings() {
+        let mut inner = Inner {
+            memory_config: MemoryConfig {
+                limit: Some(0x2000_0000),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let _ = inner.assemble(".text\nnop", EndianMode::Big).unwrap();
+
+        inner.reset();
+        assert_eq!(inner.exec.as_arch().mem().endian(), EndianMode::Big);
+        assert!(!inner.capture_can_use_jit());
+        assert!(inner.clean_after_reset);
+    }
+
+    #[test]
+    fn set_endian_reassembles() {
+        let mut inner = Inner::default();
+        let _ = inner
+            .assemble(".data\n.word 0x01020304", EndianMode::Little)
+            .unwrap();
+
+        let mut output = [0; 4];
+        inner.read_range(0x10000000, &mut output);
+        assert_eq!(output, [4, 3, 2, 1]);
+        assert_eq!(inner.capture_endian(), "little");
+
+        let _ = inner.set_endian(EndianMode::Big).unwrap();
+        inner.read_range(0x10000000, &mut output);
+        assert_eq!(output, [1, 2, 3, 4]);
+        assert_eq!(inner.capture_endian(), "big");
+        assert_eq!(inner.exec.as_arch().mem().endian(), EndianMode::Big);
+        assert!(!inner.capture_can_use_jit());
+
+        let _ = inner.set_endian(EndianMode::Little).unwrap();
+        inner.read_range(0x10000000, &mut output);
+        assert_eq!(output, [4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn load_binary_steps() {
+        let mut inner = Inner::default();
+        let mut bytes = Vec::new();
+        for ins in [0x20080001u32, 0x21090002] {
+            bytes.extend_from_slice(&ins.to_ne_bytes());
+        }
+
+        let _ = inner.load_binary(0x00400000, &bytes);
+        assert_eq!(inner.exec.as_arch().pc(), 0x00400000);
+        assert_eq!(inner.capture_disasm()[&0x00400000].1, "addi $t0, $zero, 1");
+
+        let _ = inner.run_steps(2).unwrap();
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 1);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(9)), 3);
+    }
+
+    #[test]
+    fn load_binary_replaces_program() {
+        let mut inner = init_inner(
+            "
+            .globl main
+            main: addi $t0, $zero, 1
+            .data
+            value: .word 5",
+        );
+        let bytes = 0x21090002u32.to_ne_bytes();
+
+        let _ = inner.load_binary(0x00400000, &bytes);
+        assert!(inner.source.is_none());
+        assert!(inner.globals.is_empty());
+        assert!(inner.labels.is_empty());
+        assert!(inner.symbols.is_empty());
+        assert!(inner.data_ranges.is_empty());
+        assert_eq!(segments_to_binary(&inner.segments).unwrap(), bytes);
+        assert_eq!(segments_checksum(&inner.segments), crc32(&bytes));
+        assert_eq!(inner.segment_checksums(), [(0x00400000, crc32(&bytes))]);
+    }
+
+    #[test]
+    fn snapshot_roundtrip() {
+        let mut inner = init_inner(
+            "
+            addi $t0, $zero, 1
+            lui $t1, 0x1000
+            sw $t0, 0($t1)
+            addi $t0, $t0, 1
+            sw $t0, 0($t1)",
+        );
+
+        let _ = inner.run_steps(3).unwrap();
+        inner.exec.as_arch_mut().set_hi(1);
+        inner.exec.as_arch_mut().set_fpr(FpRegisterName::new(2), 5);
+        let saved = Snapshot::capture(inner.exec.as_arch()).encode();
+        let mut regs = [0; 32];
+        inner.exec.as_arch().read_all_reg(&mut regs);
+        let pc = inner.exec.as_arch().pc();
+
+        let _ = inner.run_steps(2).unwrap();
+        inner.exec.as_arch_mut().set_hi(2);
+        inner.exec.as_arch_mut().set_lo(2);
+        inner.exec.as_arch_mut().set_fpr(FpRegisterName::new(2), 6);
+        let _ = inner.restore(&saved).unwrap();
+
+        let arch = inner.exec.as_arch();
+        let mut restored = [0; 32];
+        arch.read_all_reg(&mut restored);
+        assert_eq!(restored, regs);
+        assert_eq!(arch.pc(), pc);
+        assert_eq!(arch.hi(), 1);
+        assert_eq!(arch.lo(), 0);
+        assert_eq!(arch.fpr(FpRegisterName::new(2)), 5);
+        assert_eq!(arch.mem().read_u32(0x10000000), 1);
+        assert_eq!(Snapshot::capture(arch).encode(), saved);
+    }
+
+    #[test]
+    fn restore_keeps_dump_out_of_program() {
+        let mut inner = init_inner(
+            "
+            addi $t0, $zero, 1
+            sw $t0, 0($sp)",
+        );
+
+        let _ = inner.run_steps(2).unwrap();
+        let saved = Snapshot::capture(inner.exec.as_arch()).encode();
+        let _ = inner.restore(&saved).unwrap();
+
+        assert!(inner.segments.is_empty());
+        assert_eq!(inner.text_region(), None);
+        assert!(inner.edit_pc(0x7fffe000).is_err());
+        assert!(inner.edit_pc(TEXT_ADDR).is_ok());
+
+        let sp = inner.exec.as_arch().reg(RegisterName::new(29));
+        let _ = inner.set_endian(EndianMode::native()).unwrap();
+        assert_eq!(inner.exec.as_arch().mem().read_u32(sp), 1);
+    }
+
+    fn addresses(mapping: &FxHashMap<u32, (u32, String)>) -> Vec<u32> {
+        let mut ret: Vec<u32> = mapping.keys().copied().collect();
+        ret.sort_unstable();
+        ret
+    }
+
+    #[test]
+    fn disasm_window() {
+        let mut inner = init_inner(&"nop\n".repeat(32));
+        inner.disasm_window = (2, 3);
+        let _ = inner.run_steps(4).unwrap();
+
+        let pc = TEXT_ADDR + 16;
+        let expected: Vec<u32> = (-2..=3).map(|x| (pc as i32 + x * 4) as u32).collect();
+        assert_eq!(addresses(&inner.capture_disasm()), expected);
+        assert!(!inner.needs_capture_disasm());
+    }
+
+    #[test]
+    fn disasm_window_bounded() {
+        let mut inner = init_inner(&"nop\n".repeat(8));
+        inner.set_disasm_window(u32::MAX, u32::MAX);
+        assert_eq!(inner.disasm_window, (MAX_DISASM_WINDOW, MAX_DISASM_WINDOW));
+
+        // and no further than the text
+        let _ = inner.run_steps(2).unwrap();
+        let expected: Vec<u32> = (0..8).map(|x| TEXT_ADDR + x * 4).collect();
+        assert_eq!(addresses(&inner.capture_disasm()), expected);
+    }
+
+    #[test]
+    fn disasm_window_through_nops() {
+        // well into the page, with nop padding on both sides of the pc
+        let mut inner = init_inner(&"nop\n".repeat(2048));
+        inner.disasm_window = (3, 5);
+        let _ = inner.run_steps(1500).unwrap();
+
+        let pc = TEXT_ADDR + 1500 * 4;
+        let expected: Vec<u32> = (-3..=5).map(|x| (pc as i32 + x * 4) as u32).collect();
+        assert_eq!(addresses(&inner.capture_disasm()), expected);
+    }
+
+    #[test]
+    fn disasm_cache() {
+        let mut inner = init_inner("nop\nnop\nnop");
+        let _ = inner.capture_disasm();
+        assert_eq!(inner.disasm_cache.lock().len(), 3);
+
+        // unchanged words come from the cache
+        let key = (TEXT_ADDR + 4, [0; 3]);
+        inner.disasm_cache.lock().insert(key, "cached".into());
+        assert_eq!(inner.capture_disasm()[&(TEXT_ADDR + 4)].1, "cached");
+
+        // a changed word misses, as do its neighbours, and the old entries just aren't looked
+        // up any more
+        let _ = inner.write_memory(TEXT_ADDR + 4, &0x01095020u32.to_ne_bytes());
+        assert_eq!(
+            inner.capture_disasm()[&(TEXT_ADDR + 4)].1,
+            "add $t2, $t0, $t1"
+        );
+        assert_eq!(inner.disasm_cache.lock().len(), 6);
+
+        // so does everything once the program changes
+        let _ = inner.assemble(".text\nnop", EndianMode::native()).unwrap();
+        assert!(inner.disasm_cache.lock().is_empty());
+    }
+
+    #[test]
+    fn diff_updates() {
+        let mut inner = init_inner("add $t0, $t1, $t2\nnop");
+        inner.diff_updates = true;
+        let arch = inner.exec.as_arch_mut();
+        arch.set_reg(RegisterName::new(9), 1);
+        arch.set_reg(RegisterName::new(10), 2);
+
+        // the first one is complete
+        let regs = inner.capture_regs();
+        assert_eq!(inner.diff_regs(&regs, inner.capture_pc()), None);
+        let mut disasm = inner.capture_disasm();
+        inner.diff_disasm(&mut disasm);
+        assert_eq!(disasm.len(), 2);
+        let fp_regs = inner.capture_fp_regs();
+        assert_eq!(inner.diff_fp_regs(&fp_regs), None);
+
+        let _ = inner.step().unwrap();
+        let regs = inner.capture_regs();
+        assert_eq!(
+            inner.diff_regs(&regs, inner.capture_pc()),
+            Some((vec![(8, 3)], true))
+        );
+        let mut disasm = inner.capture_disasm();
+        inner.diff_disasm(&mut disasm);
+        assert!(disasm.is_empty());
+
+        inner.exec.as_arch_mut().set_fpr(FpRegisterName::new(4), 1);
+        let fp_regs = inner.capture_fp_regs();
+        assert_eq!(inner.diff_fp_regs(&fp_regs), Some(vec![(4, 1)]));
+
+        // a new program starts over
+        let _ = inner.assemble(".text\nnop", EndianMode::native()).unwrap();
+        let regs = inner.capture_regs();
+        assert_eq!(inner.diff_regs(&regs, inner.capture_pc()), None);
+    }
+
+    #[test]
+    fn diff_console() {
+        let mut inner = init_inner("lui $8, 0xffff\nli $9, 72\nsb $9, 12($8)\nsb $9, 12($8)");
+        inner.diff_updates = true;
+        let _ = inner.run_steps(3).unwrap();
+
+        // the first one is complete, later ones only carry what's new
+        assert_eq!(inner.diff_console(), None);
+        assert_eq!(inner.diff_console().unwrap(), "");
+        let _ = inner.step().unwrap();
+        assert_eq!(inner.diff_console().unwrap(), "H");
+        assert_eq!(inner.capture_console(), "HH");
+    }
+
+    #[test]
+    fn disasm_window_clamped() {
+        let mut inner = init_inner("nop\nnop\nnop");
+        inner.disasm_window = (8, 8);
+
+        assert_eq!(
+            addresses(&inner.capture_disasm()),
+            [TEXT_ADDR, TEXT_ADDR + 4, TEXT_ADDR + 8]
+        );
+    }
+
+    #[test]
+    fn disasm_shows_data_as_words() {
+        let mut inner = init_inner("nop\n.word 0x12345678\nnop");
+
+        let disasm = inner.capture_disasm();
+        assert_eq!(disasm[&TEXT_ADDR].1, "nop");
+        assert_eq!(disasm[&(TEXT_ADDR + 4)].1, ".word 0x12345678");
+        assert_eq!(disasm[&(TEXT_ADDR + 8)].1, "nop");
+
+        inner.set_data_as_words(false);
+        assert_ne!(
+            inner.capture_disasm()[&(TEXT_ADDR + 4)].1,
+            ".word 0x12345678"
+        );
+    }
+
+    #[test]
+    fn trace_survives_assemble() {
+        let mut inner = Inner::default();
+        inner.set_trace(true);
+        let _ = inner
+            .assemble(".text\naddi $t0, $zero, 1", EndianMode::native())
+            .unwrap();
+
+        let _ = inner.run_steps(1).unwrap();
+
+        let trace = inner.capture_trace();
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].changed_reg, Some((RegisterName::new(8), 1)));
+    }
+
+    #[test]
+    fn profile_survives_assemble() {
+        let mut inner = Inner::default();
+        inner.set_profiling(true);
+        let code = ".text\nli $t0, 3\nloop: addi $t0, $t0, -1\nbne $t0, $zero, loop";
+        let _ = inner.assemble(code, EndianMode::native()).unwrap();
+
+        let _ = inner.run_steps(7).unwrap();
+
+        let profile = inner.capture_profile();
+        assert_eq!(profile["addi"], 3);
+        assert_eq!(profile["bne"], 3);
+    }
+
+    #[test]
+    fn cache_survives_assemble() {
+        let mut inner = Inner::default();
+        inner.set_cache(Some(CacheConfig {
+            block_size: 16,
+            sets: 4,
+            ways: 2,
+        }));
+        let code = ".text\nlui $t1, 0x1001\nlw $t0, 0($t1)\nlw $t0, 4($t1)";
+        let _ = inner.assemble(code, EndianMode::native()).unwrap();
+        let _ = inner.run_steps(3).unwrap();
+
+        let stats = inner.capture_cache_stats().unwrap();
+        assert_eq!(stats, CacheStats { hits: 1, misses: 1 });
+    }
+
+    #[test]
+    fn branch_prediction_survives_assemble() {
+        let mut inner = Inner::default();
+        inner.set_branch_prediction(true);
+        let code = ".text\nli $t0, 3\nloop: addi $t0, $t0, -1\nbgtz $t0, loop";
+        let _ = inner.assemble(code, EndianMode::native()).unwrap();
+        let _ = inner.run_steps(7).unwrap();
+
+        let report = inner.capture_branch_stats().unwrap();
+        assert_eq!(report.total.predicted, 1);
+        assert_eq!(report.total.mispredicted, 2);
+    }
+
+    fn native_endian_name() -> &'static str {
+        match EndianMode::native() {
+            EndianMode::Little => "little",
+            EndianMode::Big => "big",
+        }
+    }
+
+    #[test]
+    fn force_interpreter() {
+        let segs = assemble(EndianMode::native(), ".text\naddi $t0, $zero, 1").unwrap();
+        let mut inner = Inner::default();
+        inner.build_executor(create_memory_fastmem(EndianMode::native(), &segs));
+        assert_eq!(inner.capture_can_use_jit(), HAS_JIT);
+
+        assert!(!inner.capture_force_interpreter());
+        assert_eq!(inner.capture_endian(), native_endian_name());
+
+        let _ = inner.set_force_interpreter(true);
+        assert!(!inner.capture_can_use_jit());
+        assert!(inner.capture_force_interpreter());
+        assert_eq!(inner.capture_endian(), native_endian_name());
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR);
+
+        // assembling again keeps honoring the flag
+        let _ = inner
+            .assemble(".text\naddi $t0, $zero, 1", EndianMode::native())
+            .unwrap();
+        assert!(!inner.capture_can_use_jit());
+
+        let _ = inner.run_steps(1).unwrap();
+        let _ = inner.set_force_interpreter(false);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 1);
+    }
+
+    #[test]
+    fn force_interpreter_keeps_trace_and_profile() {
+        let segs = assemble(EndianMode::native(), ".text\nnop\nnop\nnop").unwrap();
+        let mut inner = Inner::default();
+        inner.build_executor(create_memory_fastmem(EndianMode::native(), &segs));
+        inner.set_trace(true);
+        inner.set_profiling(true);
+        let _ = inner.run_steps(2).unwrap();
+
+        let _ = inner.set_force_interpreter(true);
+        assert_eq!(inner.capture_trace().len(), 2);
+        assert_eq!(inner.capture_profile().values().sum::<u64>(), 2);
+
+        let _ = inner.set_force_interpreter(false);
+        let _ = inner.run_steps(1).unwrap();
+        assert_eq!(inner.capture_trace().len(), 3);
+        assert_eq!(inner.capture_profile().values().sum::<u64>(), 3);
+    }
+
+    #[test]
+    fn run_limit_stops_infinite_loop() {
+        let mut inner = init_inner("loop:\nj loop");
+        inner.run_limit = Some(1000);
+
+        let mut ticks = 0;
+        let err = loop {
+            match inner.run_tick(true) {
+                Ok(_) => ticks += 1,
+                Err(e) => break e,
+            }
+            assert!(ticks < 2000, "run limit did not trigger");
+        };
+
+        assert!(err.contains("step limit exceeded"));
+        assert_eq!(inner.exec.as_arch().retired(), 1000);
+    }
+
+    #[test]
+    fn run_limit_after_reassemble() {
+        let mut inner = init_inner("loop:\nj loop");
+        inner.run_limit = Some(1000);
+        for _ in 0..50 {
+            let _ = inner.step().unwrap();
+        }
+
+        // a new program mid-run starts the count over
+        inner.start_run();
+        let _ = inner
+            .assemble(".text\nloop:\nj loop", EndianMode::native())
+            .unwrap();
+        assert!(inner.run_tick(false).is_ok());
+        assert_eq!(inner.run_started_at, 0);
+    }
+
+    #[test]
+    fn run_limit_in_pipeline() {
+        let mut inner = init_inner("loop:\nj loop\nnop");
+        inner.convert_to_pipeline();
+        inner.run_limit = Some(100);
+        inner.start_run();
+
+        let mut ticks = 0;
+        let err = loop {
+            match inner.run_tick(false) {
+                Ok(_) => ticks += 1,
+                Err(e) => break e,
+            }
+            assert!(ticks < 1000, "run limit did not trigger");
+        };
+        assert!(err.contains("step limit exceeded"));
+    }
+
+    #[test]
+    fn run_steps_exact() {
+        let mut inner = init_inner(
+            "
+            addi $t0, $zero, 1
+            addi $t0, $t0, 1
+            addi $t0, $t0, 1
+            addi $t0, $t0, 1",
+        );
+
+        let _ = inner.run_steps(2).unwrap();
+
+        assert_eq!(inner.exec.as_arch().pc(), TEXT_ADDR + 8);
+        assert_eq!(inner.exec.as_arch().reg(RegisterName::new(8)), 2);
+    }
+}
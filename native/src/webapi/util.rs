@@ -1,4 +1,5 @@
 use super::state::State;
+use crate::executor::{BranchStats, CacheStats};
 use neon::prelude::*;
 use parking_lot::{MappedMutexGuard, MutexGuard};
 
@@ -20,3 +21,30 @@ pub fn log_console<'c, C: Context<'c>>(cx: &mut C, msg: impl AsRef<str>) {
         .and_then(|x: Handle<JsFunction>| x.call_with(cx).arg(cx.string(msg)).apply(cx))
         .map(|_: Handle<JsUndefined>| ());
 }
+
+/// Cache statistics as `{ hits, misses, missRate }`.
+pub fn cache_stats_object<'a, C: Context<'a>>(cx: &mut C, x: CacheStats) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let hits = cx.number(x.hits as f64);
+    let misses = cx.number(x.misses as f64);
+    let miss_rate = cx.number(x.miss_rate());
+    obj.set(cx, "hits", hits)?;
+    obj.set(cx, "misses", misses)?;
+    obj.set(cx, "missRate", miss_rate)?;
+    Ok(obj)
+}
+
+/// Branch predictor statistics as `{ predicted, mispredicted, accuracy }`.
+pub fn branch_stats_object<'a, C: Context<'a>>(
+    cx: &mut C,
+    x: BranchStats,
+) -> JsResult<'a, JsObject> {
+    let obj = cx.empty_object();
+    let predicted = cx.number(x.predicted as f64);
+    let mispredicted = cx.number(x.mispredicted as f64);
+    let accuracy = cx.number(x.accuracy());
+    obj.set(cx, "predicted", predicted)?;
+    obj.set(cx, "mispredicted", mispredicted)?;
+    obj.set(cx, "accuracy", accuracy)?;
+    Ok(obj)
+}
@@ -6,11 +6,112 @@ export interface NativeLib {
   reset: () => void
 
   assemble: (code: string, endian: string) => string | null
+  // links several files; each sees its own labels and every unit's .globl ones
+  assembleUnits: (units: Array<{ name: string, code: string }>, endian: string) => string | null
+  // assembles, then steps up to maxSteps times; returns the assembly or runtime error
+  assembleAndRun: (code: string, endian: string, maxSteps: number) => string | null
   editRegister: (idx: number, value: number) => void
+  editHi: (value: number) => void
+  editLo: (value: number) => void
+  editPc: (value: number) => string | null
   readMemory: (pageIdx: number, dst: Uint8Array) => Uint8Array | null
+  readRange: (addr: number, dst: Uint8Array) => Uint8Array
+  // count consecutive pages into dst, which holds 4096 bytes per page; null if out of range
+  readPages: (startPage: number, count: number, dst: Uint8Array) => Uint8Array | null
+  // one page as 1024 words in the program's byte order
+  readMemoryWords: (pageIdx: number, dst: Uint32Array) => Uint32Array | null
+  writeMemory: (addr: number, src: Uint8Array) => void
+  dumpMemory: (addr: number, len: number) => string
+  loadBinary: (base: number, src: Uint8Array) => void
+  snapshot: () => Uint8Array
+  restore: (src: Uint8Array) => string | null
+  getGlobals: () => { [name: string]: number }
+  // any label of the last assembled program, exported or not
+  getLabelAddress: (name: string) => number | null
+  // redundant code found by the last assemble, with 1-based lines; needs setLint(true)
+  getWarnings: () => Array<{ line: number, message: string }>
+  disassembleRegion: (base: number, words: Uint32Array) => Array<{ addr: number, text: string }>
+  // a raw image in the given byte order, with the current options and program labels
+  disassembleImage: (base: number, bytes: Uint8Array, endian: string) => Array<{ addr: number, text: string }>
+  // the error instead when the segments are over 1MiB apart, as text and data are by default
+  exportBinary: () => Uint8Array | string
+  // CRC-32 of exportBinary's image, and of each non-empty segment
+  getChecksums: () => { image: number, segments: Array<{ base: number, crc: number }> }
+  exportIntelHex: () => string
+  getListing: () => Array<{ addr: number | null, bytes: Uint8Array, source: string }> | null
   step: () => void
+  // like step, but runs a jal/jalr through to its return
+  stepOver: () => void
+  runUntil: (addr: number) => void
+  // stops early at a breakpoint, after a store changes a watched word, or when the program
+  // exits; stopped in the state says which
+  runSteps: (n: number) => void
+  setBreakpoints: (addrs: number[]) => void
+  setWatchpoints: (addrs: number[]) => void
+  // runs for up to ms milliseconds; timedOut in the state says whether to call again
+  execWithTimeout: (ms: number) => string | null
   run: (useJit: boolean) => void
   stop: () => void
+  // pause keeps a free run resumable, with its run limit counted so far
+  pause: () => void
+  resume: (useJit: boolean) => void
+  provideInput: (text: string) => void
+  // a byte for the memory-mapped keyboard at 0xffff0000; replaces one not yet read
+  pushKey: (key: number) => void
+  // 0 removes the limit
+  setRunLimit: (limit: number) => void
+  // milliseconds between updates during a run; 10 by default
+  setUpdateInterval: (ms: number) => void
+  // seeds the random syscalls (40-42), now and for each later program
+  setRandomSeed: (seed: number) => void
+  // 'poison' fills memory the program hasn't written with 0xdeadbeef; from the next assemble
+  setMemoryInit: (init: 'zero' | 'poison') => void
+  // loads and stores at this address or above are address errors; 0 removes the limit.
+  // Turns the JIT off, from the next assemble
+  setMemoryLimit: (limit: number) => void
+  // 'raw' sends only numbers; the others add regsFormatted, hiFormatted and loFormatted
+  setRegisterFormat: (format: 'raw' | 'signed' | 'unsigned' | 'hex') => void
+  setEndian: (endian: 'big' | 'little') => string | null
+  setAbiRegisterNames: (enabled: boolean) => void
+  setDelaySlots: (enabled: boolean) => void
+  // instructions around the pc, each side at most 2048, and no further than the text
+  setDisasmWindow: (before: number, after: number) => void
+  // words emitted by data directives show as `.word 0x...`; on by default
+  setDataAsWords: (enabled: boolean) => void
+  // later states carry dirtyRegs and only new disasm entries, and pc only when it moved;
+  // the first one after enabling, assembling or resetting is still complete
+  setDiffUpdates: (enabled: boolean) => void
+  enableTrace: (enabled: boolean) => void
+  // counts executed instructions by mnemonic, sent as profile, and loads and stores by
+  // page; runs without the JIT
+  setProfiling: (enabled: boolean) => void
+  getProfile: () => { [mnemonic: string]: number }
+  // pages with any loads or stores while profiling, in address order
+  getMemoryHeatmap: () => Array<{ page: number, reads: number, writes: number }>
+  resetProfile: () => void
+  // a data cache model that only counts hits and misses; null removes it. Block size and
+  // sets are powers of two, blocks are at most 4096 bytes and there are at most 65536 in
+  // all. Returns an error for a bad shape, and runs without the JIT
+  setCache: (config: { blockSize: number, sets: number, ways: number } | null) => string | null
+  getCacheStats: () => ICacheStats | null
+  // scores a 2-bit counter per conditional branch without changing what runs; runs without
+  // the JIT. Turning it on again starts over
+  setBranchPrediction: (enabled: boolean) => void
+  getBranchStats: () => {
+    total: IBranchStats
+    branches: Array<IBranchStats & { addr: number }>
+  } | null
+  setForceInterpreter: (enabled: boolean) => void
+  setStrictSegments: (enabled: boolean) => void
+  setLint: (enabled: boolean) => void
+  // puts a nop after every branch and jump; from the next assemble
+  setFillDelaySlots: (enabled: boolean) => void
+  setEntryLabel: (label: string) => void
+  // what the assembler accepts, and what the disassembler decodes with a sample word each
+  getSupportedInstructions: () => {
+    mnemonics: string[]
+    opcodes: Array<{ mnemonic: string, example: number }>
+  }
   getNativeEndian: () => 'big' | 'little'
   convertToPipeline: () => void
 }
@@ -19,19 +120,89 @@ interface IDisassembly {
   [k: string]: [number, string]
 }
 
+// pc, encoded instruction, disassembly, then the written register and its new value if any
+type ITraceEntry = [number, number, string, number?, number?]
+
+// register number or name (`f0` and on for coprocessor 1), old value, new value
+type IRegChange = [number | `f${number}` | 'hi' | 'lo' | 'pc', number, number]
+
+// return address, and the nearest label before it as `name+0x10`
+interface ICallFrame {
+  addr: number
+  label: string | null
+}
+
+interface ICacheStats {
+  hits: number
+  misses: number
+  missRate: number
+}
+
+interface IBranchStats {
+  predicted: number
+  mispredicted: number
+  accuracy: number
+}
+
 interface IModuleStateRaw {
+  // with setDiffUpdates(true), regs and an unmoved pc only come in the first state
   regs: number[]
+  // with setDiffUpdates(true), the registers changed since the last state as [number, value]
+  dirtyRegs?: Array<[number, number]>
+  // coprocessor-1 registers as raw bits; single-precision values read back through a Float32Array.
+  // With setDiffUpdates(true), only in the first state
+  fpRegs: number[]
+  // with setDiffUpdates(true), the FP registers changed since the last state as [number, value]
+  dirtyFpRegs?: Array<[number, number]>
   pc: number
+  hi: number
+  lo: number
+  regsFormatted?: string[]
+  hiFormatted?: string
+  loFormatted?: string
+  trace?: ITraceEntry[]
+  changed?: IRegChange[]
+  // what the last interpreted add or subtract would set in a flags register; MIPS has none
+  flags?: { carry: boolean, overflow: boolean, zero: boolean, negative: boolean } | null
+  // with setDelaySlots(true), the next step runs the delay slot of a taken branch
+  inDelaySlot?: boolean
+  // since the last setCache, while a cache is set
+  cache?: ICacheStats
+  // totals for all branches, with setBranchPrediction(true)
+  branches?: IBranchStats
+  // executions by mnemonic, with setProfiling(true)
+  profile?: { [mnemonic: string]: number }
+  // innermost call last
+  callStack?: ICallFrame[]
+  // the last 65536 characters stored to the memory-mapped console at 0xffff000c. With
+  // setDiffUpdates(true), only in the first state, and later ones carry consoleAppend
+  console?: string
+  // with setDiffUpdates(true), the console output since the last state
+  consoleAppend?: string
+  // instructions run by compiled code, and by the interpreter when the JIT can't
+  jitStats?: { compiled: number, interpreted: number }
   running: boolean
   disasm: IDisassembly
   disasmList: number[]
   cleanAfterReset: boolean
   canUseJit: boolean
   canUsePipeline: boolean
+  endian: 'big' | 'little'
+  forceInterpreter: boolean
+  waitingForInput: boolean
+  timedOut: boolean
+  paused: boolean
+  lastError: string | null
+  // what the last step or run stopped on, if it was an execution error
+  errorKind: 'invalidInstruction' | 'overflow' | 'address' | 'unaligned' | 'trap'
+    | 'invalidInput' | 'randomBound' | null
+  // why the last runSteps or execWithTimeout stopped, with the exit code or the address
+  stopped: { reason: 'exit' | 'breakpoint' | 'watchpoint', value: number } | null
   pipelineDetail: string
 }
 
 export interface IPipelineDetail {
+  // the instruction in each stage, missing while the stage holds a bubble
   debugInsIf?: string
   debugInsId?: string
   debugInsEx?: string
@@ -96,7 +96,7 @@ const DisassemblyPage = (): JSX.Element | null => {
         <Title>데이터 섹션</Title>
         <MemoryViewer initialAddr={0x10008000} />
         <Title>스택</Title>
-        <MemoryViewer initialAddr={0x7ffffe40} />
+        <MemoryViewer initialAddr={0x7fffeffc} />
       </VerticalAlign>
     </Root>
   );